@@ -0,0 +1,43 @@
+//! Deciphers a cryptogram a slice of time at a time while printing the progress of the search,
+//! and settles for the best partial plaintext if the time runs out.
+//!
+//! ```text
+//! cargo run --release --example anytime_progress -- [SECONDS]
+//! ```
+
+use std::time::Duration;
+use substitution::{testgen, Dictionary, Solver, SolverConfig, StepResult};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let seconds = match std::env::args().nth(1) {
+        Some(seconds) => seconds.parse()?,
+        None => 1.,
+    };
+    let dict = Dictionary::from_reader(testgen::CORPUS.as_bytes())?;
+    let cryptogram = &testgen::cryptograms(testgen::CORPUS, 5, 1, 12..=12)[0];
+    println!("Ciphertext: {}", cryptogram.ciphertext);
+
+    let config = SolverConfig::new()
+        .anytime(true)
+        .max_duration(Duration::from_secs_f64(seconds))
+        .on_progress(|progress| {
+            println!(
+                "{} of {} words placed, {} at best, {} guesses tested",
+                progress.words_placed, progress.words, progress.best_words_placed, progress.nodes
+            );
+        });
+    let mut solver = Solver::new(&dict, &cryptogram.ciphertext, &config)?;
+    let solution = loop {
+        match solver.step(Duration::from_millis(50)) {
+            StepResult::Pending => {}
+            StepResult::Solved(solution) => break solution,
+            result => return Err(format!("No solution: {result:?}").into()),
+        }
+    };
+    if solution.partial {
+        println!("Out of time, best guess:");
+    }
+    println!("Plaintext:  {}", solution.plaintext);
+    println!("Expected:   {}", cryptogram.plaintext);
+    Ok(())
+}
@@ -0,0 +1,42 @@
+//! Deciphers many cryptograms with one dictionary, which is read only once. The dictionary is
+//! built from the given plaintext file, or the built-in corpus if there is none, and the
+//! cryptograms are generated from the same text.
+//!
+//! ```text
+//! cargo run --release --example batch -- [FILE]
+//! ```
+
+use std::time::{Duration, Instant};
+use substitution::{testgen, Dictionary, Error, SolverConfig};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let corpus = match std::env::args_os().nth(1) {
+        Some(path) => std::fs::read_to_string(path)?,
+        None => testgen::CORPUS.to_owned(),
+    };
+    let words = substitution::normalize::Normalizer::default()
+        .normalize(&corpus)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("\n");
+    let dict = Dictionary::from_reader(words.as_bytes())?;
+
+    // Give up on a cryptogram after a while instead of holding up the rest of the batch
+    let config = SolverConfig::new().max_duration(Duration::from_secs(10));
+    let mut solved = 0;
+    let cryptograms = testgen::cryptograms(&corpus, 1, 10, 3..=6);
+    for cryptogram in &cryptograms {
+        let start = Instant::now();
+        match dict.decrypt_full(&cryptogram.ciphertext, &config) {
+            Ok(solution) if solution.plaintext == cryptogram.plaintext => {
+                solved += 1;
+                println!("{:>8.2?}  {}", start.elapsed(), solution.plaintext);
+            }
+            Ok(solution) => println!("{:>8.2?}  other: {}", start.elapsed(), solution.plaintext),
+            Err(Error::Timeout) => println!("{:>8}  timed out", "-"),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    println!("Solved {solved} of {}", cryptograms.len());
+    Ok(())
+}
@@ -0,0 +1,41 @@
+//! Deciphers a sentence with a dictionary built from a word list in memory, and shows the key and
+//! score of the solution.
+//!
+//! ```text
+//! cargo run --example custom_dictionary
+//! ```
+
+use rand::{rngs::StdRng, SeedableRng};
+use substitution::{Dictionary, Key, SolverConfig};
+
+const WORDS: &str = "
+a
+cat
+dog
+lazy
+over
+quick
+sleeps
+the
+while
+";
+
+fn main() -> Result<(), substitution::Error> {
+    let dict = Dictionary::from_reader(WORDS.as_bytes())?;
+    println!("Dictionary of {} words", dict.len());
+
+    // Encipher with a seeded key, so that every run deciphers the same ciphertext
+    let key = Key::random_with(&mut StdRng::seed_from_u64(7));
+    let ciphertext = key.encipher("The quick cat sleeps while a lazy dog... sleeps over the cat!");
+    println!("Ciphertext: {ciphertext}");
+
+    let config = SolverConfig::new().keep_formatting(true);
+    let solution = dict.decrypt_full(&ciphertext, &config)?;
+    println!("Plaintext:  {}", solution.plaintext);
+    println!("Key:        {}", solution.key);
+    println!(
+        "{} words in the dictionary, score {:.2}",
+        solution.matched_words, solution.score
+    );
+    Ok(())
+}
@@ -0,0 +1,32 @@
+//! Enciphers text with a random key, saves the key as text, and deciphers the ciphertext with the
+//! key parsed back, in English and in a bigger alphabet.
+//!
+//! ```text
+//! cargo run --example key_round_trip
+//! ```
+
+use substitution::{Alphabet, Key};
+
+fn main() -> Result<(), substitution::Error> {
+    let plaintext = "Attack at dawn, retreat at dusk.";
+    let key = Key::random();
+    let ciphertext = key.encipher(plaintext);
+
+    // A key is written as the ciphertext letter of each plaintext letter of its alphabet
+    let saved = key.to_string();
+    let parsed: Key = saved.parse()?;
+    assert_eq!(parsed, key);
+    assert_eq!(parsed.decipher(&ciphertext), plaintext);
+    // The inverse key enciphers the ciphertext back into the plaintext
+    assert_eq!(key.inverse().encipher(&ciphertext), plaintext);
+    println!("{saved}: {ciphertext}");
+
+    let finnish = Alphabet::finnish();
+    let plaintext = "Hyökkäys aamunkoitteessa.";
+    let key = Key::random_in(&finnish, &mut rand::thread_rng());
+    let ciphertext = key.encipher(plaintext);
+    let parsed = Key::from_letters(&key.to_string(), &finnish)?;
+    assert_eq!(parsed.decipher(&ciphertext), plaintext);
+    println!("{key}: {ciphertext}");
+    Ok(())
+}
@@ -0,0 +1,218 @@
+use crate::{Error, START};
+use std::sync::Arc;
+
+/// Letters which text is written with, and which a substitution key maps to each other.
+///
+/// Letters are lowercase. Text in other case is lowercased before looking up its letters, and
+/// anything which isn't a letter of the alphabet is left alone by keys and left out by
+/// normalization. The first letters of the built-in alphabets are a to z, so English keys and
+/// text stay the same in every alphabet.
+///
+/// ```
+/// use substitution::{Alphabet, Key};
+///
+/// let finnish = Alphabet::finnish();
+/// assert_eq!(finnish.len(), 29);
+/// assert!(finnish.contains('ä'));
+///
+/// let key = Key::identity_in(&finnish);
+/// assert_eq!(key.encipher("Hyvää yötä"), "Hyvää yötä");
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct Alphabet {
+    letters: Arc<[char]>,
+}
+
+impl Alphabet {
+    /// Most letters an alphabet can have
+    pub const MAX_LETTERS: usize = 32;
+
+    /// Create an alphabet of `letters` in order
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidAlphabet`] if `letters` is empty, has more than [`Alphabet::MAX_LETTERS`]
+    /// letters, repeats a letter or has something else than lowercase letters in it.
+    pub fn new(letters: &str) -> Result<Self, Error> {
+        let letters: Vec<char> = letters.chars().collect();
+        let lowercase = |c: &char| c.is_alphabetic() && c.to_lowercase().eq([*c]);
+        if letters.is_empty()
+            || letters.len() > Self::MAX_LETTERS
+            || !letters.iter().all(lowercase)
+            || (1..letters.len()).any(|i| letters[..i].contains(&letters[i]))
+        {
+            return Err(Error::InvalidAlphabet);
+        }
+        Ok(Self {
+            letters: letters.into(),
+        })
+    }
+
+    /// The letters a to z
+    #[must_use]
+    pub fn english() -> Self {
+        Self::with_extra_letters("")
+    }
+
+    /// The letters a to z, å, ä and ö
+    #[must_use]
+    pub fn finnish() -> Self {
+        Self::with_extra_letters("åäö")
+    }
+
+    /// The letters a to z, ä, ö, ü and ß
+    #[must_use]
+    pub fn german() -> Self {
+        Self::with_extra_letters("äöüß")
+    }
+
+    /// The letters a to z followed by `extra`
+    fn with_extra_letters(extra: &str) -> Self {
+        Self {
+            letters: ('a'..='z').chain(extra.chars()).collect(),
+        }
+    }
+
+    /// Number of letters
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.letters.len()
+    }
+
+    /// Returns true if there are no letters, which no alphabet is allowed to be
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.letters.is_empty()
+    }
+
+    /// The letters in order
+    #[must_use]
+    pub fn letters(&self) -> &[char] {
+        &self.letters
+    }
+
+    /// Returns true if `letter` is in the alphabet, in either case
+    #[must_use]
+    pub fn contains(&self, letter: char) -> bool {
+        self.code(letter).is_some()
+    }
+
+    /// Byte code of a letter in either case. Letters are coded from [`START`] on in order.
+    pub(crate) fn code(&self, letter: char) -> Option<u8> {
+        let mut lowercase = letter.to_lowercase();
+        let (Some(letter), None) = (lowercase.next(), lowercase.next()) else {
+            return None;
+        };
+        let idx = self.letters.iter().position(|l| *l == letter)?;
+        u8::try_from(idx).ok().map(|idx| START + idx)
+    }
+
+    /// Lowercase letter of a byte code
+    pub(crate) fn letter(&self, code: u8) -> Option<char> {
+        self.letters
+            .get(usize::from(code.checked_sub(START)?))
+            .copied()
+    }
+
+    /// Returns true if `byte` is the code of a letter in some alphabet
+    pub(crate) fn is_code(byte: u8) -> bool {
+        usize::from(byte.wrapping_sub(START)) < Self::MAX_LETTERS
+    }
+
+    /// Byte codes of the letters in order
+    pub(crate) fn codes(&self) -> impl Iterator<Item = u8> + '_ {
+        (START..).take(self.len())
+    }
+
+    /// Code the letters of `text` as bytes. ASCII whitespace is kept and everything else is left
+    /// out.
+    pub(crate) fn encode(&self, text: &str) -> Vec<u8> {
+        text.chars()
+            .filter_map(|c| {
+                if c.is_ascii_whitespace() {
+                    u8::try_from(c).ok()
+                } else {
+                    self.code(c)
+                }
+            })
+            .collect()
+    }
+
+    /// Turn byte codes of letters back into lowercase letters. Other bytes are kept if they are
+    /// ASCII and left out otherwise.
+    pub(crate) fn decode(&self, codes: &[u8]) -> String {
+        codes
+            .iter()
+            .filter_map(|c| {
+                self.letter(*c)
+                    .or_else(|| c.is_ascii().then(|| char::from(*c)))
+            })
+            .collect()
+    }
+}
+
+impl Default for Alphabet {
+    fn default() -> Self {
+        Self::english()
+    }
+}
+
+impl std::fmt::Display for Alphabet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.letters.iter().try_for_each(|c| write!(f, "{c}"))
+    }
+}
+
+impl std::fmt::Debug for Alphabet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Alphabet").field(&self.to_string()).finish()
+    }
+}
+
+impl std::str::FromStr for Alphabet {
+    type Err = Error;
+
+    /// Parse the name of a built-in alphabet, like `finnish`, or the letters of an alphabet
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_ref() {
+            "english" | "en" => Ok(Self::english()),
+            "finnish" | "fi" => Ok(Self::finnish()),
+            "german" | "de" => Ok(Self::german()),
+            _ => Self::new(s),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn codes_round_trip() {
+        let german = Alphabet::german();
+        let codes = german.encode("Grüße, Welt!\n");
+        assert_eq!(codes.len(), "grüße welt\n".chars().count());
+        assert_eq!(german.decode(&codes), "grüße welt\n");
+        assert_eq!(Alphabet::english().decode(&codes[..2]), "gr");
+        assert_eq!(Alphabet::english().encode("Grüße"), b"gre");
+    }
+
+    #[test]
+    fn invalid_alphabets() {
+        for letters in [
+            "",
+            "abca",
+            "aB",
+            "a b",
+            "a1",
+            "abcdefghijklmnopqrstuvwxyzåäöüßæøœ",
+        ] {
+            assert!(matches!(
+                Alphabet::new(letters),
+                Err(Error::InvalidAlphabet)
+            ));
+        }
+        assert_eq!("Finnish".parse::<Alphabet>().unwrap(), Alphabet::finnish());
+        assert_eq!("abc".parse::<Alphabet>().unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,228 @@
+use crate::{source::WordSource, verify, CancelToken, Error, Key, Pattern, R, START};
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+/// A partial key which the beam keeps, and what it cost to get there
+#[derive(Clone)]
+struct State {
+    /// Plaintext letter code of each ciphertext letter, or 0 if it isn't guessed yet
+    table: [u8; R],
+    /// Plaintext letters which some ciphertext letter is guessed to be, a bit for each
+    used: u32,
+    /// Skip cost of the words which were skipped
+    skipped: usize,
+    /// Sum of the prefix scores of the input words deciphered with the partial key
+    score: usize,
+}
+
+impl State {
+    /// Guess `plain` for the letters of `word`
+    fn with_word(&self, word: &[u8], plain: &[u8]) -> Self {
+        let mut state = self.clone();
+        for (&c, &p) in word.iter().zip(plain) {
+            state.table[usize::from(c - START)] = p;
+            state.used |= 1 << (p - START);
+        }
+        state
+    }
+
+    /// Call `f` with the plaintext letters of each dictionary word which `word`, of `pattern`, can
+    /// be deciphered into, keeping the guesses so far
+    fn for_each_candidate(
+        &self,
+        word: &[u8],
+        pattern: &Pattern,
+        dict: &impl WordSource,
+        f: impl FnMut(&[u8]),
+    ) {
+        let allow = |prefix: &[usize], plain: usize| {
+            let Some(c) = word.get(prefix.len()) else {
+                return false;
+            };
+            let Ok(plain) = u8::try_from(plain) else {
+                return false;
+            };
+            match self.table[usize::from(c - START)] {
+                0 => match word[..prefix.len()].iter().position(|earlier| earlier == c) {
+                    // The letter was guessed earlier in the word
+                    Some(idx) => prefix[idx] == usize::from(plain),
+                    // A free letter can't be a plaintext letter which another letter is
+                    None => {
+                        self.used & 1 << (plain - START) == 0
+                            && !prefix.contains(&usize::from(plain))
+                    }
+                },
+                guess => guess == plain,
+            }
+        };
+        dict.for_each_allowed(pattern, allow, f);
+    }
+
+    /// Sum of how far each of `words` deciphered with the partial key gets into the dictionary,
+    /// up to its first letter which isn't guessed yet
+    fn prefix_score(&self, words: &[(&[u8], usize)], dict: &(impl WordSource + Sync)) -> usize {
+        verify::verify_key(&self.table, words, dict, |word, dict| {
+            dict.prefix_score(word.letters) * word.count
+        })
+    }
+}
+
+/// Decipher `words` in order from the letters which `start` deciphers, keeping the `width` best
+/// partial keys after each word instead of one key which is backtracked from. Each key is extended
+/// with every dictionary word which the next word can be deciphered into, and the keys which
+/// skipped the least and whose deciphered words get the furthest into the dictionary are kept. A
+/// word is skipped with its skip cost only when a key has no dictionary word for it, up to a total
+/// of `max_skip`.
+///
+/// The search can miss the key when the right guesses for the first words don't look good
+/// until later words, which a wider beam makes less likely. With `bounds` and a `deadline`, the
+/// width is set between the bounds after each word, to as many keys as there is time to extend
+/// for the words left at the pace so far.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn search(
+    words: &[(Vec<u8>, usize)],
+    max_skip: usize,
+    start: &Key,
+    dict: &(impl WordSource + Sync),
+    mut width: usize,
+    bounds: Option<(usize, usize)>,
+    deadline: Option<Instant>,
+    cancel: Option<&CancelToken>,
+) -> Result<Key, Error> {
+    let mut distinct: Vec<&[u8]> = words.iter().map(|(word, _)| word.as_slice()).collect();
+    distinct.sort_unstable();
+    distinct.dedup();
+    // Each distinct word counts once
+    let distinct: Vec<(&[u8], usize)> = distinct.into_iter().map(|word| (word, 1)).collect();
+
+    let mut beam = vec![State {
+        table: start.table,
+        used: start
+            .table
+            .iter()
+            .filter(|p| **p != 0)
+            .fold(0, |used, p| used | 1 << (p - START)),
+        skipped: 0,
+        score: 0,
+    }];
+    let mut seen = HashSet::new();
+    let started = Instant::now();
+    // Keys extended so far
+    let mut extended = 0_usize;
+    for (idx, (word, cost)) in words.iter().enumerate() {
+        if cancel.is_some_and(CancelToken::is_cancelled) {
+            return Err(Error::Cancelled);
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Err(Error::Timeout);
+        }
+        extended += beam.len();
+        let pattern = Pattern::of_letters(word.iter().copied());
+        let mut next = Vec::new();
+        for state in &beam {
+            let before = next.len();
+            state.for_each_candidate(word, &pattern, dict, |plain| {
+                next.push(state.with_word(word, plain));
+            });
+            if next.len() == before && state.skipped + cost <= max_skip {
+                next.push(State {
+                    skipped: state.skipped + cost,
+                    ..state.clone()
+                });
+            }
+        }
+        for state in &mut next {
+            state.score = state.prefix_score(&distinct, dict);
+        }
+        next.sort_by(|a, b| a.skipped.cmp(&b.skipped).then(b.score.cmp(&a.score)));
+        seen.clear();
+        next.retain(|state| seen.insert(state.table));
+        if let (Some(bounds), Some(deadline)) = (bounds, deadline) {
+            let words_left = u32::try_from(words.len() - idx - 1).unwrap_or(u32::MAX);
+            let pace = started.elapsed() / u32::try_from(extended).unwrap_or(u32::MAX);
+            let time_left = deadline.saturating_duration_since(Instant::now());
+            width = affordable_width(bounds, pace, words_left, time_left).unwrap_or(width);
+        }
+        next.truncate(width.max(1));
+        if next.is_empty() {
+            return Err(Error::SearchExhausted);
+        }
+        beam = next;
+    }
+    Ok(Key::from_table(beam[0].table, start.alphabet.clone()))
+}
+
+/// Width between the bounds `min` and `max` of a beam which can be extended for each of
+/// `words_left` words in `time_left`, taking `pace` to extend each key. `None` if no words are
+/// left.
+fn affordable_width(
+    (min, max): (usize, usize),
+    pace: Duration,
+    words_left: u32,
+    time_left: Duration,
+) -> Option<usize> {
+    let time_per_word = time_left.checked_div(words_left)?;
+    let affordable = time_per_word.as_nanos() / pace.as_nanos().max(1);
+    Some(
+        usize::try_from(affordable)
+            .unwrap_or(usize::MAX)
+            .clamp(min, max),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Algorithm, Dictionary, SolverConfig};
+
+    #[test]
+    fn wider_beam_keeps_guesses_which_tie_at_first() {
+        // "bats" and "cats" look as good for the first word, but only "cats" leaves a dictionary
+        // word for the second one
+        let dict = Dictionary::from_reader("bats\ncats\ncow\n".as_bytes()).unwrap();
+        let key: Key = "bcdefghijklmnopqrstuvwxyza".parse().unwrap();
+        let ciphertext = key.encipher("cats cow");
+        let config = SolverConfig::new()
+            .algorithm(Algorithm::Beam)
+            .word_order_target(4);
+
+        let narrow = dict.decrypt_full(&ciphertext, &config.clone().beam_width(1));
+        assert!(narrow.unwrap().plaintext.starts_with("bats "));
+        let wide = dict
+            .decrypt_full(&ciphertext, &config.clone().beam_width(2))
+            .unwrap();
+        assert_eq!(wide.plaintext, "cats cow");
+        assert_eq!(wide.algorithm, Some(Algorithm::Beam));
+
+        // There's time to widen the beam right after the first word
+        let config = config
+            .beam_width(1)
+            .adaptive_beam_width(1, 2)
+            .max_duration(std::time::Duration::from_secs(90));
+        let adaptive = dict.decrypt_full(&ciphertext, &config).unwrap();
+        assert_eq!(adaptive.plaintext, "cats cow");
+        let config = config.max_duration(std::time::Duration::ZERO);
+        assert!(matches!(
+            dict.decrypt_full(&ciphertext, &config),
+            Err(Error::Timeout)
+        ));
+    }
+
+    #[test]
+    fn beam_is_as_wide_as_there_is_time_for() {
+        let ms = Duration::from_millis;
+        // Plenty of time widens the beam up to the bound
+        assert_eq!(affordable_width((1, 50), ms(1), 10, ms(1000)), Some(50));
+        // 5 ms for each word is time to extend 5 keys
+        assert_eq!(affordable_width((1, 50), ms(1), 10, ms(50)), Some(5));
+        // The pace slowing down narrows the beam, but never below the bound
+        assert_eq!(affordable_width((1, 50), ms(2), 10, ms(50)), Some(2));
+        assert_eq!(affordable_width((3, 50), ms(2), 10, ms(50)), Some(3));
+        assert_eq!(
+            affordable_width((3, 50), ms(1), 10, Duration::ZERO),
+            Some(3)
+        );
+        // After the last word, there's nothing to widen the beam for
+        assert_eq!(affordable_width((1, 50), ms(1), 0, ms(50)), None);
+    }
+}
@@ -0,0 +1,447 @@
+// Don't measure coverage, this is support code for CLI
+#![cfg(not(tarpaulin_include))]
+
+use color_eyre::Result;
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    time::{Duration, Instant},
+};
+
+/// Numbers of words in the generated cryptograms
+const LENGTHS: [usize; 3] = [4, 8, 12];
+
+/// Word order targets, in distinct letters, which tuning tries
+const WORD_ORDER_TARGETS: std::ops::RangeInclusive<usize> = 3..=10;
+
+/// Solve times which grow less than this many seconds don't count as regressions, however many
+/// percent they grow, because short times are mostly noise
+const MIN_REGRESSION: f64 = 0.01;
+
+/// Results of solving all cryptograms of one length
+struct Row {
+    words: usize,
+    solved: usize,
+    accuracy: f64,
+    times: Vec<Duration>,
+}
+
+impl Row {
+    /// Time at percentile `p` (0-100) of the sorted solve times
+    fn percentile(&self, p: usize) -> Duration {
+        self.times[(self.times.len() - 1) * p / 100]
+    }
+}
+
+/// Benchmark results saved as JSON, to compare with results of another version later
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Snapshot {
+    /// Seed which picked the passages and keys
+    seed: u64,
+    /// Cryptograms solved per length
+    samples: usize,
+    rows: Vec<SnapshotRow>,
+    /// Per-letter accuracy over all lengths, which older snapshots don't have
+    #[serde(default)]
+    letters: Vec<LetterRow>,
+}
+
+/// Results of one length in a [`Snapshot`], with times in seconds
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SnapshotRow {
+    words: usize,
+    solved: usize,
+    accuracy: f64,
+    p50: f64,
+    p90: f64,
+    max: f64,
+}
+
+/// How often a plaintext letter was deciphered right in a [`Snapshot`]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct LetterRow {
+    letter: char,
+    /// Cryptograms whose plaintext has the letter
+    seen: usize,
+    /// Cryptograms whose solution deciphers the letter right
+    correct: usize,
+}
+
+impl From<&Row> for SnapshotRow {
+    fn from(row: &Row) -> Self {
+        Self {
+            words: row.words,
+            solved: row.solved,
+            accuracy: row.accuracy,
+            p50: row.percentile(50).as_secs_f64(),
+            p90: row.percentile(90).as_secs_f64(),
+            max: row.percentile(100).as_secs_f64(),
+        }
+    }
+}
+
+impl Snapshot {
+    /// Table of the results, a row for each length, followed by a row for each letter
+    #[allow(clippy::cast_precision_loss)]
+    fn table(&self) -> Result<String> {
+        let mut table = String::from("words  solved  accuracy       p50       p90       max\n");
+        for row in &self.rows {
+            writeln!(
+                table,
+                "{:>5}  {:>6}  {:>7.1}%  {:>8.2}s {:>8.2}s {:>8.2}s",
+                row.words,
+                format!("{}/{}", row.solved, self.samples),
+                row.accuracy * 100.,
+                row.p50,
+                row.p90,
+                row.max,
+            )?;
+        }
+        if !self.letters.is_empty() {
+            writeln!(table, "\nletter  correct  accuracy")?;
+        }
+        for letter in &self.letters {
+            writeln!(
+                table,
+                "{:>6}  {:>7}  {:>7.1}%",
+                letter.letter,
+                format!("{}/{}", letter.correct, letter.seen),
+                letter.correct as f64 * 100. / letter.seen.max(1) as f64,
+            )?;
+        }
+        Ok(table.trim_end().to_owned())
+    }
+}
+
+/// Share of letters in `decrypted` that are the same as in `expected`
+#[allow(clippy::cast_precision_loss)]
+fn letter_accuracy(expected: &str, decrypted: &str) -> f64 {
+    let letters = expected.bytes().filter(u8::is_ascii_alphabetic).count();
+    let correct = expected
+        .bytes()
+        .zip(decrypted.bytes())
+        .filter(|(e, d)| e.is_ascii_alphabetic() && e == d)
+        .count();
+    if letters == 0 {
+        1.
+    } else {
+        correct as f64 / letters as f64
+    }
+}
+
+/// Encrypt and decrypt `samples` random passages of `corpus` at each length and report
+/// letter accuracy and solve time percentiles, and how often each letter was deciphered right
+/// according to the keys of the passages, as a table, or as a JSON [`Snapshot`] if `json`.
+/// The same `seed` gives the same passages and keys.
+#[allow(clippy::cast_precision_loss)]
+pub fn run(
+    corpus: &str,
+    dictionary: &str,
+    samples: usize,
+    seed: u64,
+    json: bool,
+) -> Result<String> {
+    let dictionary = substitution::Dictionary::from_reader(dictionary.as_bytes())?;
+    let words = corpus.split_whitespace().count();
+    let mut rows = Vec::with_capacity(LENGTHS.len());
+    let config = substitution::SolverConfig::new();
+    // Cryptograms which had each letter, and which of them were solved with it deciphered right
+    let mut letters: BTreeMap<char, (usize, usize)> = BTreeMap::new();
+
+    for len in LENGTHS.into_iter().filter(|len| *len <= words) {
+        let mut row = Row {
+            words: len,
+            solved: 0,
+            accuracy: 0.,
+            times: Vec::with_capacity(samples),
+        };
+
+        let cryptograms = substitution::testgen::cryptograms(corpus, seed, samples, len..=len);
+        for (sample, cryptogram) in cryptograms.iter().enumerate() {
+            eprintln!("Solving {len} word cryptogram {}/{samples}", sample + 1);
+
+            let timer = Instant::now();
+            let result = dictionary.decrypt_full(&cryptogram.ciphertext, &config);
+            row.times.push(timer.elapsed());
+
+            // A failed solve deciphers no letter right
+            let truth: substitution::Key = cryptogram.key.parse()?;
+            let confusion = match &result {
+                Ok(solution) => solution.key.confusion(&truth),
+                Err(_) => Vec::new(),
+            };
+            let mut plaintext_letters: Vec<char> = cryptogram
+                .plaintext
+                .chars()
+                .filter(char::is_ascii_alphabetic)
+                .collect();
+            plaintext_letters.sort_unstable();
+            plaintext_letters.dedup();
+            for plain in plaintext_letters {
+                let (times_seen, times_correct) = letters.entry(plain).or_default();
+                *times_seen += 1;
+                *times_correct += usize::from(
+                    confusion
+                        .iter()
+                        .any(|letter| letter.plain == plain && letter.is_correct()),
+                );
+            }
+
+            if let Ok(solution) = result {
+                row.solved += 1;
+                row.accuracy += letter_accuracy(&cryptogram.plaintext, &solution.plaintext);
+            }
+        }
+
+        row.accuracy /= samples.max(1) as f64;
+        row.times.sort_unstable();
+        rows.push(row);
+    }
+
+    let snapshot = Snapshot {
+        seed,
+        samples,
+        rows: rows
+            .iter()
+            .filter(|row| !row.times.is_empty())
+            .map(SnapshotRow::from)
+            .collect(),
+        letters: letters
+            .into_iter()
+            .map(|(letter, (seen, correct))| LetterRow {
+                letter,
+                seen,
+                correct,
+            })
+            .collect(),
+    };
+    if json {
+        Ok(serde_json::to_string_pretty(&snapshot)?)
+    } else {
+        snapshot.table()
+    }
+}
+
+/// Compare the `new` [`Snapshot`] to the `old` one, length by length, as a table of changes.
+/// Returns `true` too if fewer cryptograms were solved, accuracy dropped, or a solve time
+/// percentile grew by more than `threshold` percent.
+pub fn diff(old: &Snapshot, new: &Snapshot, threshold: f64) -> Result<(String, bool)> {
+    if (old.seed, old.samples) != (new.seed, new.samples) {
+        eprintln!(
+            "Warning: the snapshots were taken with different seeds or samples, \
+             so they didn't solve the same cryptograms"
+        );
+    }
+
+    // Percent change from `old` to `new` time, and whether it's a regression
+    let change = |old: f64, new: f64| {
+        let percent = if old > 0. {
+            (new - old) / old * 100.
+        } else {
+            0.
+        };
+        (percent, percent > threshold && new - old > MIN_REGRESSION)
+    };
+
+    let mut regressed = false;
+    let mut table = String::from("words    solved  accuracy         p50        p90        max\n");
+    for new_row in &new.rows {
+        let Some(old_row) = old.rows.iter().find(|row| row.words == new_row.words) else {
+            writeln!(table, "{:>5}  not in the old snapshot", new_row.words)?;
+            continue;
+        };
+        let times = [
+            change(old_row.p50, new_row.p50),
+            change(old_row.p90, new_row.p90),
+            change(old_row.max, new_row.max),
+        ];
+        // The slowest solve is too noisy to fail on
+        let row_regressed = new_row.solved < old_row.solved
+            || new_row.accuracy < old_row.accuracy
+            || times[..2].iter().any(|(_, regressed)| *regressed);
+        regressed |= row_regressed;
+        write!(
+            table,
+            "{:>5}  {:>+8}  {:>+7.1}%  ",
+            new_row.words,
+            new_row.solved.cast_signed() - old_row.solved.cast_signed(),
+            (new_row.accuracy - old_row.accuracy) * 100.,
+        )?;
+        for (percent, _) in times {
+            write!(table, "{percent:>+9.1}% ")?;
+        }
+        writeln!(table, "{}", if row_regressed { " REGRESSED" } else { "" })?;
+    }
+    for old_row in &old.rows {
+        if !new.rows.iter().any(|row| row.words == old_row.words) {
+            writeln!(table, "{:>5}  not in the new snapshot", old_row.words)?;
+        }
+    }
+    Ok((
+        table
+            .lines()
+            .map(str::trim_end)
+            .collect::<Vec<_>>()
+            .join("\n"),
+        regressed,
+    ))
+}
+
+/// Solve `samples` random passages of `corpus` at each length with every word order target, and
+/// return a `src/tuning.rs` which picks the target that solved the most cryptograms in the least
+/// total time for each length. The same `seed` gives the same passages and keys.
+pub fn tune(corpus: &str, dictionary: &str, samples: usize, seed: u64) -> Result<String> {
+    let dictionary = substitution::Dictionary::from_reader(dictionary.as_bytes())?;
+    let words = corpus.split_whitespace().count();
+    let mut targets = Vec::with_capacity(LENGTHS.len());
+
+    for len in LENGTHS.into_iter().filter(|len| *len <= words) {
+        let cryptograms = substitution::testgen::cryptograms(corpus, seed, samples, len..=len);
+        let mut best: Option<(usize, Duration, usize)> = None;
+        for target in WORD_ORDER_TARGETS {
+            eprintln!("Solving {len} word cryptograms with word order target {target}");
+            let config = substitution::SolverConfig::new().word_order_target(target);
+            let mut solved = 0;
+            let timer = Instant::now();
+            for cryptogram in &cryptograms {
+                if let Ok(solution) = dictionary.decrypt_full(&cryptogram.ciphertext, &config) {
+                    solved += usize::from(solution.plaintext == cryptogram.plaintext);
+                }
+            }
+            let elapsed = timer.elapsed();
+            eprintln!(
+                "  {solved}/{} correct in {:.2}s",
+                cryptograms.len(),
+                elapsed.as_secs_f64()
+            );
+
+            // More correct solutions win, then less time
+            if best.is_none_or(|(best_solved, best_elapsed, _)| {
+                (solved, std::cmp::Reverse(elapsed))
+                    > (best_solved, std::cmp::Reverse(best_elapsed))
+            }) {
+                best = Some((solved, elapsed, target));
+            }
+        }
+        if let Some((_, _, target)) = best {
+            targets.push((len, target));
+        }
+    }
+
+    // The longest inputs tried stand for all longer inputs too
+    let table = targets
+        .iter()
+        .enumerate()
+        .fold(String::new(), |mut table, (i, (len, target))| {
+            let separator = if i == 0 { "" } else { ", " };
+            let _ = if i + 1 == targets.len() {
+                write!(table, "{separator}(usize::MAX, {target})")
+            } else {
+                write!(table, "{separator}({len}, {target})")
+            };
+            table
+        });
+    Ok(format!(
+        "//! Solver heuristics tuned on generated cryptograms. Generated with `substitution tune`, which\n\
+         //! prints a new version of this file.\n\
+         \n\
+         /// Number of distinct letters which the words deciphered first should have, by the maximum number\n\
+         /// of words in the input. Words are deciphered in order of distance from this target.\n\
+         pub static WORD_ORDER_TARGETS: [(usize, usize); {}] = [{table}];",
+        targets.len()
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Snapshot of one length whose slowest solve took as long as the 90th percentile
+    fn snapshot(solved: usize, accuracy: f64, p50: f64, p90: f64) -> Snapshot {
+        Snapshot {
+            seed: 1,
+            samples: 10,
+            rows: vec![SnapshotRow {
+                words: 4,
+                solved,
+                accuracy,
+                p50,
+                p90,
+                max: p90,
+            }],
+            letters: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn diff_finds_regressions() {
+        let old = snapshot(9, 0.9, 1.0, 2.0);
+
+        let (table, regressed) = diff(&old, &snapshot(10, 1.0, 0.5, 1.0), 10.).unwrap();
+        assert!(!regressed);
+        assert!(table.contains("+1") && table.contains("-50.0%"));
+        assert!(!table.contains("REGRESSED"));
+
+        let (table, regressed) = diff(&old, &snapshot(9, 0.9, 1.5, 2.0), 10.).unwrap();
+        assert!(regressed);
+        assert!(table.contains("+50.0%") && table.contains("REGRESSED"));
+        assert!(diff(&old, &snapshot(8, 0.9, 1.0, 2.0), 10.).unwrap().1);
+        assert!(diff(&old, &snapshot(9, 0.8, 1.0, 2.0), 10.).unwrap().1);
+
+        // Within the threshold
+        let (table, regressed) = diff(&old, &snapshot(9, 0.9, 1.05, 2.1), 10.).unwrap();
+        assert!(!regressed);
+        assert!(!table.contains("REGRESSED"));
+        assert!(diff(&old, &snapshot(9, 0.9, 1.05, 2.1), 1.).unwrap().1);
+    }
+
+    #[test]
+    fn table_lists_letter_accuracy() {
+        let mut snapshot = snapshot(9, 0.9, 1.0, 2.0);
+        snapshot.letters = vec![LetterRow {
+            letter: 'q',
+            seen: 4,
+            correct: 3,
+        }];
+        let table = snapshot.table().unwrap();
+        assert!(table.ends_with("letter  correct  accuracy\n     q      3/4     75.0%"));
+
+        // Snapshots from before per-letter accuracy still load
+        let json = r#"{"seed": 1, "samples": 10, "rows": []}"#;
+        let old: Snapshot = serde_json::from_str(json).unwrap();
+        assert!(old.letters.is_empty());
+    }
+
+    #[test]
+    fn snapshot_formats() {
+        let mut snapshot = snapshot(9, 0.9, 1.0, 2.0);
+        snapshot.letters = vec![LetterRow {
+            letter: 'e',
+            seen: 10,
+            correct: 9,
+        }];
+        insta::assert_snapshot!("table", snapshot.table().unwrap());
+        insta::assert_snapshot!("json", serde_json::to_string_pretty(&snapshot).unwrap());
+        let (table, _) = diff(&snapshot, &self::snapshot(10, 1.0, 0.5, 2.5), 10.).unwrap();
+        insta::assert_snapshot!("diff", table);
+    }
+
+    #[test]
+    fn diff_ignores_noise() {
+        // Short times grow by many percent without being slower in practice
+        let old = snapshot(9, 0.9, 0.001, 0.002);
+        assert!(!diff(&old, &snapshot(9, 0.9, 0.004, 0.008), 10.).unwrap().1);
+
+        // The slowest solve doesn't count
+        let mut new = snapshot(9, 0.9, 0.001, 0.002);
+        new.rows[0].max = 10.;
+        assert!(!diff(&old, &new, 10.).unwrap().1);
+
+        // Lengths which only one snapshot has are listed, but aren't regressions
+        new.rows[0].words = 8;
+        let (table, regressed) = diff(&old, &new, 10.).unwrap();
+        assert!(!regressed);
+        assert!(table.contains("not in the old snapshot"));
+        assert!(table.contains("not in the new snapshot"));
+    }
+}
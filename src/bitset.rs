@@ -1,5 +1,5 @@
 /// A set of N*64 bits that can be individually addressed
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub struct BitSet64<const N: usize> {
     buf: [u64; N],
 }
@@ -14,21 +14,33 @@ impl<const N: usize> BitSet64<N> {
     pub fn insert<T: Into<u32> + Copy>(&mut self, value: T) {
         let idx = value.into() / u64::BITS;
         let off = value.into() % u64::BITS;
-        self.buf[usize::try_from(idx).unwrap()] |= 1 << off;
+        self.buf[idx as usize] |= 1 << off;
     }
 
     /// Set a bit at index corresponding to `value` to 0
     pub fn remove<T: Into<u32> + Copy>(&mut self, value: T) {
         let idx = value.into() / u64::BITS;
         let off = value.into() % u64::BITS;
-        self.buf[usize::try_from(idx).unwrap()] &= !(1 << off);
+        self.buf[idx as usize] &= !(1 << off);
     }
 
     /// Query if the bit corresponding to `value` is 1
     pub fn contains<T: Into<u32> + Copy>(&self, value: T) -> bool {
         let idx = value.into() / u64::BITS;
         let off = value.into() % u64::BITS;
-        (self.buf[usize::try_from(idx).unwrap()] & 1 << off) != 0
+        (self.buf[idx as usize] & 1 << off) != 0
+    }
+
+    /// Set every bit which isn't 1 in `other` to 0
+    pub fn intersect(&mut self, other: &Self) {
+        for (bits, other) in self.buf.iter_mut().zip(&other.buf) {
+            *bits &= other;
+        }
+    }
+
+    /// Number of bits which are 1
+    pub fn len(&self) -> u32 {
+        self.buf.iter().map(|bits| bits.count_ones()).sum()
     }
 }
 
@@ -58,5 +70,14 @@ mod test {
         for i in 100u32..256 {
             assert!(!bs.contains(i));
         }
+        assert_eq!(bs.len(), 96);
+
+        let mut other = BitSet64::<4>::new();
+        other.insert(3u32);
+        other.insert(5u32);
+        other.insert(200u32);
+        bs.intersect(&other);
+        assert_eq!(bs.len(), 1);
+        assert!(bs.contains(3u32));
     }
 }
@@ -0,0 +1,137 @@
+// Don't measure coverage, this is support code for CLI
+#![cfg(not(tarpaulin_include))]
+
+use color_eyre::{eyre::eyre, Result};
+use std::{
+    fmt::Write,
+    time::{Duration, Instant},
+};
+use substitution::{testgen, Dictionary, Language, LetterStats, SolverConfig, StepResult};
+
+/// Seed of the passage and key of the puzzle which the demo solves by default
+const DEFAULT_PUZZLE: u64 = 1;
+
+/// Number of words in the puzzles
+const PUZZLE_WORDS: usize = 12;
+
+/// Most common ciphertext letters to list
+const COMMON_LETTERS: usize = 5;
+
+/// Time that the search runs for between progress reports
+const STEP: Duration = Duration::from_millis(10);
+
+/// Decipher one of the puzzles generated from the built-in corpus, picked by `seed`, with a
+/// dictionary of the corpus, and explain each phase along the way. Progress is printed to stderr
+/// while searching. Fails if the plaintext isn't the passage which was enciphered, so that the
+/// demo doubles as a check that the whole program works.
+#[allow(clippy::cast_precision_loss)]
+pub fn run(seed: Option<u64>) -> Result<String> {
+    let seed = seed.unwrap_or(DEFAULT_PUZZLE);
+    let cryptogram = testgen::cryptograms(testgen::CORPUS, seed, 1, PUZZLE_WORDS..=PUZZLE_WORDS)
+        .pop()
+        .ok_or_else(|| eyre!("The built-in corpus is too short for a puzzle"))?;
+    let ciphertext = &cryptogram.ciphertext;
+    let mut report = String::new();
+    writeln!(
+        report,
+        "Puzzle {seed}: a passage of the built-in text, enciphered with a random key\n  \
+         {ciphertext}\n"
+    )?;
+
+    // What the program can tell before searching
+    let config = SolverConfig::new();
+    let estimate = substitution::estimate(ciphertext, testgen::CORPUS.as_bytes(), &config)?;
+    let mut stats = LetterStats::new();
+    stats.update(ciphertext.as_bytes());
+    let total = stats.total_letters().max(1) as f64;
+    let common: Vec<String> = stats
+        .letters()
+        .into_iter()
+        .take(COMMON_LETTERS)
+        .map(|(letter, count)| format!("{letter} {:.0}%", count as f64 * 100. / total))
+        .collect();
+    writeln!(
+        report,
+        "1. Analysis\n  \
+         {} words of {} distinct letters, so there are up to 10^{:.1} keys to try.\n  \
+         The most common letters are {}. In English, they are most likely {}.",
+        estimate.words,
+        estimate.distinct_letters,
+        estimate.keys_log10,
+        common.join(", "),
+        Language::English
+            .frequency_order()
+            .chars()
+            .take(COMMON_LETTERS)
+            .map(String::from)
+            .collect::<Vec<_>>()
+            .join(", "),
+    )?;
+    for warning in &estimate.warnings {
+        writeln!(report, "  Warning: {warning}")?;
+    }
+
+    // How the key is searched for
+    writeln!(
+        report,
+        "\n2. Strategy\n  \
+         Backtracking, with a dictionary of the {} words of the built-in text. Each word is\n  \
+         deciphered into a dictionary word by guessing its letters in the order of how common\n  \
+         they are in English, and when no guess works, the search goes back to the word before.\n  \
+         Words which the dictionary doesn't have can be skipped, in up to {} rounds.",
+        estimate.dictionary_words, estimate.rounds,
+    )?;
+
+    // The search, a slice of time at a time
+    let dict = Dictionary::from_reader(testgen::CORPUS.as_bytes())?;
+    let config = config.on_progress(|progress| {
+        eprintln!(
+            "Placed {} of {} words at best, tested {} guesses",
+            progress.best_words_placed, progress.words, progress.nodes
+        );
+    });
+    let mut solver = substitution::Solver::new(&dict, ciphertext, &config)?;
+    let started = Instant::now();
+    let mut steps = 1;
+    let solution = loop {
+        match solver.step(STEP) {
+            StepResult::Pending => steps += 1,
+            StepResult::Solved(solution) => break solution,
+            result => return Err(eyre!("The demo puzzle wasn't solved: {result:?}")),
+        }
+    };
+    writeln!(
+        report,
+        "\n3. Search\n  \
+         Found a key in {:.1} ms, in {steps} {} of up to {} ms.",
+        started.elapsed().as_secs_f64() * 1000.,
+        if steps == 1 { "step" } else { "steps" },
+        STEP.as_millis(),
+    )?;
+
+    // The outcome, checked against the passage
+    writeln!(
+        report,
+        "\n4. Result\n  \
+         {}\n  \
+         {:.0}% of the words are in the dictionary. The key, and each letter and its substitute:\n  \
+         {}\n  \
+         {}",
+        solution.plaintext,
+        solution.score * 100.,
+        solution.key,
+        substitution::key_table(&solution.key, &Language::English).replace('\n', "\n  "),
+    )?;
+    if solution.plaintext != cryptogram.plaintext {
+        return Err(eyre!(
+            "The demo puzzle was deciphered into \"{}\" instead of \"{}\"",
+            solution.plaintext,
+            cryptogram.plaintext
+        ));
+    }
+    write!(
+        report,
+        "\nThe plaintext is the passage which was enciphered. Try another puzzle with --seed."
+    )?;
+    Ok(report)
+}
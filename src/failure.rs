@@ -0,0 +1,212 @@
+// Don't measure coverage, this is support code for CLI
+#![cfg(not(tarpaulin_include))]
+
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+use std::{
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// File name of the ciphertext in a bundle directory
+const CIPHERTEXT_FILE: &str = "ciphertext.txt";
+/// File name of the dictionary details in a bundle directory
+const DICTIONARY_FILE: &str = "dictionary.txt";
+/// File name of the options in a bundle directory
+const OPTIONS_FILE: &str = "options.txt";
+/// File name of the partial key and the statistics of the search in a bundle directory
+const SEARCH_FILE: &str = "search.txt";
+
+/// How far the search of a failed decryption got
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Stats {
+    /// Best guesses found, as a partial key like `Solution::key`, if the search got that far
+    pub partial_key: Option<String>,
+    /// Number of words in the input
+    pub words: usize,
+    /// Most words which the guesses deciphered into dictionary words
+    pub words_placed: usize,
+    /// Guesses tested against the dictionary
+    pub nodes: usize,
+    /// Times that a word ran out of guesses
+    pub backtracks: usize,
+    /// Seconds from the start of the program until the search gave up
+    pub seconds: f64,
+}
+
+/// A failed decryption case which can be saved, shared and replayed. It is redacted so that it
+/// can be shared: the ciphertext is normalized and on one line, and files are only named, not
+/// given with the directories which they are in.
+pub struct Bundle {
+    /// Ciphertext in lowercase, without punctuation, with its words separated by single spaces
+    pub ciphertext: String,
+    /// File names of the dictionaries that were used
+    pub dictionaries: Vec<PathBuf>,
+    /// Hashes of the dictionary files' contents, to tell if the replay uses the same wordlists
+    pub dictionary_hashes: Vec<u64>,
+    /// Command line options which the search was run with
+    pub options: Vec<String>,
+    /// How far the search got before it failed
+    pub stats: Stats,
+}
+
+impl Bundle {
+    /// Create a bundle from ciphertext, the dictionary file paths, the options which the search
+    /// was run with, and how far it got
+    pub fn new(
+        ciphertext: &str,
+        dictionaries: &[PathBuf],
+        options: Vec<String>,
+        stats: Stats,
+    ) -> Result<Self> {
+        let ciphertext = substitution::normalize::Normalizer::default().normalize(ciphertext);
+        Ok(Self {
+            ciphertext: ciphertext.split_whitespace().collect::<Vec<_>>().join(" "),
+            dictionaries: dictionaries.iter().map(|path| redact(path)).collect(),
+            dictionary_hashes: dictionaries
+                .iter()
+                .map(|path| hash_file(path))
+                .collect::<Result<_>>()?,
+            options,
+            stats,
+        })
+    }
+
+    /// Write the bundle into a new subdirectory of `dir` and return the subdirectory's path
+    pub fn save(&self, dir: &Path) -> Result<PathBuf> {
+        let path = dir.join(format!(
+            "failure-{:016x}",
+            fnv1a(self.ciphertext.as_bytes())
+        ));
+        fs::create_dir_all(&path)
+            .wrap_err_with(|| format!("Cannot create directory {}", path.display()))?;
+        fs::write(path.join(CIPHERTEXT_FILE), &self.ciphertext)
+            .wrap_err_with(|| format!("Cannot write ciphertext to {}", path.display()))?;
+        // A line with the path and a line with the hash for each dictionary
+        let details = self.dictionaries.iter().zip(&self.dictionary_hashes).fold(
+            String::new(),
+            |mut details, (dictionary, hash)| {
+                let _ = write!(details, "{}\n{hash:016x}\n", dictionary.display());
+                details
+            },
+        );
+        fs::write(path.join(DICTIONARY_FILE), details)
+            .wrap_err_with(|| format!("Cannot write dictionary details to {}", path.display()))?;
+        // An argument on each line
+        let options = self
+            .options
+            .iter()
+            .fold(String::new(), |mut options, option| {
+                let _ = writeln!(options, "{option}");
+                options
+            });
+        fs::write(path.join(OPTIONS_FILE), options)
+            .wrap_err_with(|| format!("Cannot write options to {}", path.display()))?;
+        fs::write(path.join(SEARCH_FILE), self.stats.to_lines())
+            .wrap_err_with(|| format!("Cannot write search statistics to {}", path.display()))?;
+        Ok(path)
+    }
+
+    /// Read a bundle from a directory created by [`Bundle::save`]
+    pub fn load(path: &Path) -> Result<Self> {
+        let ciphertext = fs::read_to_string(path.join(CIPHERTEXT_FILE))
+            .wrap_err_with(|| format!("Cannot read ciphertext from {}", path.display()))?;
+        let details = fs::read_to_string(path.join(DICTIONARY_FILE))
+            .wrap_err_with(|| format!("Cannot read dictionary details from {}", path.display()))?;
+        let mut lines = details.lines();
+        let (mut dictionaries, mut dictionary_hashes) = (Vec::new(), Vec::new());
+        while let Some(dictionary) = lines.next() {
+            dictionaries.push(dictionary.into());
+            dictionary_hashes.push(
+                u64::from_str_radix(lines.next().unwrap_or_default(), 16)
+                    .wrap_err_with(|| format!("Invalid dictionary hash in {}", path.display()))?,
+            );
+        }
+        let options = fs::read_to_string(path.join(OPTIONS_FILE))
+            .wrap_err_with(|| format!("Cannot read options from {}", path.display()))?
+            .lines()
+            .map(str::to_owned)
+            .collect();
+        let stats = fs::read_to_string(path.join(SEARCH_FILE))
+            .wrap_err_with(|| format!("Cannot read search statistics from {}", path.display()))?;
+        Ok(Self {
+            ciphertext,
+            dictionaries,
+            dictionary_hashes,
+            options,
+            stats: Stats::from_lines(&stats)
+                .wrap_err_with(|| format!("Invalid search statistics in {}", path.display()))?,
+        })
+    }
+
+    /// Of `dictionaries` which a replay uses in place of the bundle's, the ones which don't have
+    /// the same contents as when the bundle was created
+    pub fn changed_dictionaries<'a>(&self, dictionaries: &'a [PathBuf]) -> Result<Vec<&'a Path>> {
+        let mut changed = Vec::new();
+        for (dictionary, hash) in dictionaries.iter().zip(&self.dictionary_hashes) {
+            if hash_file(dictionary)? != *hash {
+                changed.push(dictionary.as_path());
+            }
+        }
+        Ok(changed)
+    }
+}
+
+impl Stats {
+    /// The statistics as lines of a name and a value
+    fn to_lines(&self) -> String {
+        let mut lines = String::new();
+        if let Some(key) = &self.partial_key {
+            let _ = writeln!(lines, "partial-key {key}");
+        }
+        let _ = write!(
+            lines,
+            "words {}\nwords-placed {}\nnodes {}\nbacktracks {}\nseconds {:.3}\n",
+            self.words, self.words_placed, self.nodes, self.backtracks, self.seconds
+        );
+        lines
+    }
+
+    /// Parse statistics written by [`Stats::to_lines`]
+    fn from_lines(lines: &str) -> Result<Self> {
+        let mut stats = Self::default();
+        for line in lines.lines() {
+            let (name, value) = line.split_once(' ').unwrap_or((line, ""));
+            match name {
+                "partial-key" => stats.partial_key = Some(value.to_owned()),
+                "words" => stats.words = value.parse()?,
+                "words-placed" => stats.words_placed = value.parse()?,
+                "nodes" => stats.nodes = value.parse()?,
+                "backtracks" => stats.backtracks = value.parse()?,
+                "seconds" => stats.seconds = value.parse()?,
+                _ => return Err(eyre!("Unknown statistic \"{name}\"")),
+            }
+        }
+        Ok(stats)
+    }
+}
+
+/// Name of the file at `path` without the directories which it is in, which may tell about the
+/// user who saved the bundle
+pub fn redact(path: &Path) -> PathBuf {
+    path.file_name().map_or_else(PathBuf::new, PathBuf::from)
+}
+
+/// Hash the contents of a file
+fn hash_file(path: &Path) -> Result<u64> {
+    let bytes = fs::read(path).wrap_err_with(|| format!("Cannot read {}", path.display()))?;
+    Ok(fnv1a(&bytes))
+}
+
+/// 64-bit FNV-1a hash, which unlike std's hashers stays the same across Rust versions
+///
+/// ## Sources
+/// <https://en.wikipedia.org/wiki/Fowler%E2%80%93Noll%E2%80%93Vo_hash_function>
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
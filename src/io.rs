@@ -1,7 +1,10 @@
 // Don't measure coverage, this is support code for CLI
 #![cfg(not(tarpaulin_include))]
 
-use color_eyre::eyre::Context;
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
 use std::{
     fs::File,
     io::{Read, Stdin, Stdout, Write},
@@ -12,6 +15,19 @@ use std::{
 pub enum Input {
     File(File, PathBuf),
     Stdin(Stdin),
+    #[cfg(feature = "clipboard")]
+    Clipboard(std::io::Cursor<String>),
+}
+
+#[cfg(feature = "clipboard")]
+impl Input {
+    /// Take the current text contents of the system clipboard as input
+    pub fn clipboard() -> Result<Self> {
+        let text = arboard::Clipboard::new()
+            .and_then(|mut clipboard| clipboard.get_text())
+            .wrap_err("Cannot read from clipboard")?;
+        Ok(Self::Clipboard(std::io::Cursor::new(text)))
+    }
 }
 
 /// Conversion from optional file path to input types
@@ -38,6 +54,8 @@ impl<'a> AsMut<dyn Read + 'a> for Input {
         match self {
             Self::File(f, _) => f,
             Self::Stdin(s) => s,
+            #[cfg(feature = "clipboard")]
+            Self::Clipboard(c) => c,
         }
     }
 }
@@ -48,6 +66,8 @@ impl std::fmt::Display for Input {
         match self {
             Self::File(_, path) => path.display().fmt(f),
             Self::Stdin(_) => write!(f, "stdin"),
+            #[cfg(feature = "clipboard")]
+            Self::Clipboard(_) => write!(f, "clipboard"),
         }
     }
 }
@@ -56,6 +76,29 @@ impl std::fmt::Display for Input {
 pub enum Output {
     File(File, PathBuf),
     Stdout(Stdout),
+    /// Buffer which is copied to the clipboard in [`Output::finish`]
+    #[cfg(feature = "clipboard")]
+    Clipboard(Vec<u8>),
+}
+
+impl Output {
+    /// Flush everything that has been written, copying it to the clipboard if that's the output
+    pub fn finish(mut self) -> Result<()> {
+        self.as_mut()
+            .flush()
+            .wrap_err_with(|| format!("Cannot write to {self}"))?;
+
+        #[cfg(feature = "clipboard")]
+        if let Self::Clipboard(buf) = self {
+            arboard::Clipboard::new()
+                .and_then(|mut clipboard| {
+                    clipboard.set_text(String::from_utf8_lossy(&buf).trim_end())
+                })
+                .wrap_err("Cannot write to clipboard")?;
+        }
+
+        Ok(())
+    }
 }
 
 /// Conversion from optional file path to output types
@@ -83,6 +126,8 @@ impl<'a> AsMut<dyn Write + 'a> for Output {
         match self {
             Self::File(f, _) => f,
             Self::Stdout(s) => s,
+            #[cfg(feature = "clipboard")]
+            Self::Clipboard(b) => b,
         }
     }
 }
@@ -93,6 +138,73 @@ impl std::fmt::Display for Output {
         match self {
             Self::File(_, path) => path.display().fmt(f),
             Self::Stdout(_) => write!(f, "stdout"),
+            #[cfg(feature = "clipboard")]
+            Self::Clipboard(_) => write!(f, "clipboard"),
         }
     }
 }
+
+/// Find a dictionary file from common locations
+pub fn find_dictionary() -> Result<PathBuf> {
+    substitution::find_dictionary().map_err(|e| match e {
+        substitution::Error::DictionaryNotFound { tried } => {
+            let tried: Vec<String> = tried
+                .iter()
+                .map(|path| format!("  {}", path.display()))
+                .collect();
+            eyre!(
+                "No dictionary found. Specify one with --dictionary.\nTried:\n{}",
+                tried.join("\n")
+            )
+        }
+        e => e.into(),
+    })
+}
+
+/// Byte order mark of UTF-8 text
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// Decode text read from an input and convert CRLF line endings to LF.
+///
+/// A UTF-8 byte order mark is dropped. With the `encoding_rs` feature, text with a UTF-16 byte
+/// order mark, or which isn't valid UTF-8, is converted from UTF-16 or Windows-1252 respectively,
+/// and the name of the original encoding is returned along with the text.
+pub fn decode(bytes: Vec<u8>) -> Result<(String, Option<&'static str>)> {
+    #[cfg(feature = "encoding_rs")]
+    let (text, transcoded_from) = {
+        let (encoding, bom_len) = encoding_rs::Encoding::for_bom(&bytes)
+            .filter(|(encoding, _)| *encoding != encoding_rs::UTF_8)
+            .or_else(|| {
+                std::str::from_utf8(&bytes)
+                    .is_err()
+                    .then_some((encoding_rs::WINDOWS_1252, 0))
+            })
+            .unwrap_or((encoding_rs::UTF_8, 0));
+        if encoding == encoding_rs::UTF_8 {
+            (from_utf8(bytes)?, None)
+        } else {
+            let (text, _) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+            (text.into_owned(), Some(encoding.name()))
+        }
+    };
+
+    #[cfg(not(feature = "encoding_rs"))]
+    let (text, transcoded_from) = {
+        if bytes.starts_with(b"\xff\xfe") || bytes.starts_with(b"\xfe\xff") {
+            return Err(eyre!(
+                "Input is UTF-16, which needs the encoding_rs feature.\nSave it as UTF-8 instead."
+            ));
+        }
+        (from_utf8(bytes)?, None)
+    };
+
+    Ok((text.replace("\r\n", "\n"), transcoded_from))
+}
+
+/// Convert bytes to a string without a UTF-8 byte order mark
+fn from_utf8(mut bytes: Vec<u8>) -> Result<String> {
+    if bytes.starts_with(UTF8_BOM) {
+        bytes.drain(..UTF8_BOM.len());
+    }
+    String::from_utf8(bytes).wrap_err("Input is not valid UTF-8")
+}
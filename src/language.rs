@@ -0,0 +1,388 @@
+use crate::{Alphabet, Error, ASCII_LETTERS};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Languages which the solver has letter statistics of.
+///
+/// The statistics decide which letters are guessed first while searching for a key, so picking
+/// the language of the plaintext makes the search faster. Languages which have letters beyond a
+/// to z come with an alphabet of them. Other languages can be loaded from language packs with
+/// [`Language::from_path`].
+///
+/// ```
+/// use substitution::Language;
+///
+/// let finnish: Language = "fi".parse().unwrap();
+/// assert_eq!(finnish, Language::Finnish);
+/// assert!(finnish.frequency_order().starts_with('a'));
+/// assert!(finnish.alphabet().contains('ä'));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum Language {
+    /// English, written with a to z
+    #[default]
+    English,
+    /// Finnish, written with a to z, å, ä and ö
+    Finnish,
+    /// German, written with a to z, ä, ö, ü and ß
+    German,
+    /// French, written with a to z without accents
+    French,
+    /// Spanish, written with a to z without accents
+    Spanish,
+    /// A language loaded from a language pack
+    Custom(Arc<LanguagePack>),
+}
+
+impl Language {
+    /// Every language, in the order of their names in English
+    pub const ALL: [Self; 5] = [
+        Self::English,
+        Self::Finnish,
+        Self::French,
+        Self::German,
+        Self::Spanish,
+    ];
+
+    /// Load a language pack from a TOML file. See [`LanguagePack`] for the format.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidLanguagePack`] if the file can't be read or doesn't follow the
+    /// format, telling what is wrong and where.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, Error> {
+        LanguagePack::load(path.as_ref()).map(|pack| Self::Custom(Arc::new(pack)))
+    }
+
+    /// The alphabet which the language is written with
+    #[must_use]
+    pub fn alphabet(&self) -> Alphabet {
+        match self {
+            Self::Finnish => Alphabet::finnish(),
+            Self::German => Alphabet::german(),
+            Self::English | Self::French | Self::Spanish => Alphabet::english(),
+            Self::Custom(pack) => pack.alphabet.clone(),
+        }
+    }
+
+    /// Letters of the language's alphabet from the most to the least common
+    ///
+    /// ## Sources
+    /// <https://en.wikipedia.org/wiki/Letter_frequency>
+    #[must_use]
+    pub fn frequency_order(&self) -> &str {
+        match self {
+            Self::English => "etaonihsrdluwmcfgypbkvjxqz",
+            Self::Finnish => "aintesloukämrvjhpydögcbfwzxqå",
+            Self::German => "ensriatdhulcgomwbfkzvpüäößjyxq",
+            Self::French => "esaitnruoldcpmvqfbghjxzykw",
+            Self::Spanish => "eaosrnidlctumpbgvyqhfzjxwk",
+            Self::Custom(pack) => &pack.frequency_order,
+        }
+    }
+
+    /// Letters of the language's alphabet in the order the language sorts them, like ä after a in
+    /// German, for showing letters to people who speak the language
+    ///
+    /// ```
+    /// use substitution::Language;
+    ///
+    /// assert!(Language::German.collation().starts_with(&['a', 'ä', 'b']));
+    /// assert!(Language::Finnish.collation().ends_with(&['z', 'å', 'ä', 'ö']));
+    /// ```
+    #[must_use]
+    pub fn collation(&self) -> Vec<char> {
+        match self {
+            // DIN 5007 sorts umlauts as the letter without them, and ß as ss
+            Self::German => "aäbcdefghijklmnoöpqrsßtuüvwxyz".chars().collect(),
+            Self::Custom(pack) => pack.collation.clone(),
+            _ => self.alphabet().letters().to_vec(),
+        }
+    }
+
+    /// Per mille of the language's words which start with each letter from a to z, if known
+    pub(crate) fn initial_frequencies(&self) -> Option<&'static [u16; ASCII_LETTERS]> {
+        match self {
+            Self::English => Some(&ENGLISH_INITIAL_FREQ),
+            _ => None,
+        }
+    }
+
+    /// Per mille of the language's letters which are each letter from a to z, if known
+    pub(crate) fn letter_frequencies(&self) -> Option<&'static [u16; ASCII_LETTERS]> {
+        match self {
+            Self::English => Some(&ENGLISH_LETTER_FREQ),
+            _ => None,
+        }
+    }
+}
+
+/// Per mille of english words which start with each letter, from a to z
+///
+/// ## Sources
+/// <https://en.wikipedia.org/wiki/Letter_frequency>
+static ENGLISH_INITIAL_FREQ: [u16; ASCII_LETTERS] = [
+    117, 44, 52, 32, 28, 40, 16, 42, 73, 5, 9, 24, 38, 23, 76, 43, 2, 28, 67, 160, 12, 8, 55, 0, 8,
+    0,
+];
+
+/// Per mille of english letters which are each letter, from a to z
+///
+/// ## Sources
+/// <https://en.wikipedia.org/wiki/Letter_frequency>
+static ENGLISH_LETTER_FREQ: [u16; ASCII_LETTERS] = [
+    82, 15, 28, 43, 127, 22, 20, 61, 70, 2, 8, 40, 24, 67, 75, 19, 1, 60, 63, 91, 28, 10, 24, 2,
+    20, 1,
+];
+
+/// Letter statistics and words of a language which isn't built in, loaded from a TOML file with
+/// [`Language::from_path`].
+///
+/// ```toml
+/// # Name of the language
+/// name = "italian"
+/// # Letters of the language, a to z if left out
+/// alphabet = "abcdefghijklmnopqrstuvwxyz"
+/// # The alphabet in the order the language sorts letters, for showing keys (optional)
+/// collation = "abcdefghijklmnopqrstuvwxyz"
+/// # Every letter of the alphabet once, from the most to the least common
+/// frequency_order = "eaionlrtscdpumvghfbqzjkwxy"
+/// # Table of quadgram counts for hill climbing, relative to the pack file (optional)
+/// ngrams = "italian-quadgrams.txt"
+/// # The most common words (optional)
+/// stop_words = ["di", "che", "il", "la"]
+/// # Words which are a single letter (optional)
+/// one_letter_words = ["a", "e", "i", "o"]
+/// ```
+///
+/// Words are written in the letters of the alphabet, in lowercase.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LanguagePack {
+    name: String,
+    alphabet: Alphabet,
+    collation: Vec<char>,
+    frequency_order: String,
+    ngrams: Option<PathBuf>,
+    stop_words: Vec<String>,
+    one_letter_words: Vec<String>,
+}
+
+/// A language pack as it's written in a file, before it's checked
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PackFile {
+    name: String,
+    alphabet: Option<String>,
+    collation: Option<String>,
+    frequency_order: String,
+    ngrams: Option<PathBuf>,
+    #[serde(default)]
+    stop_words: Vec<String>,
+    #[serde(default)]
+    one_letter_words: Vec<String>,
+}
+
+impl LanguagePack {
+    /// Read and check a language pack file
+    fn load(path: &Path) -> Result<Self, Error> {
+        let invalid =
+            |problem: String| Error::InvalidLanguagePack(format!("{}: {problem}", path.display()));
+        let text = std::fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+        let file: PackFile = toml::from_str(&text).map_err(|e| invalid(e.to_string()))?;
+        let pack = Self::from_file(file).map_err(invalid)?;
+        if let Some(ngrams) = &pack.ngrams {
+            let ngrams = path.parent().unwrap_or(Path::new("")).join(ngrams);
+            if !ngrams.is_file() {
+                return Err(invalid(format!(
+                    "ngrams file {} doesn't exist",
+                    ngrams.display()
+                )));
+            }
+            return Ok(Self {
+                ngrams: Some(ngrams),
+                ..pack
+            });
+        }
+        Ok(pack)
+    }
+
+    /// Check the fields of a language pack file, returning what is wrong with them if anything
+    fn from_file(file: PackFile) -> Result<Self, String> {
+        if file.name.trim().is_empty() {
+            return Err("name is empty".to_owned());
+        }
+        let alphabet = match &file.alphabet {
+            Some(letters) => Alphabet::new(letters).map_err(|e| format!("alphabet: {e}"))?,
+            None => Alphabet::english(),
+        };
+        // Orders of the letters have every letter of the alphabet once
+        let check_order = |field: &str, order: &str| {
+            for letter in alphabet.letters() {
+                match order.matches(*letter).count() {
+                    0 => return Err(format!("{field} doesn't have '{letter}'")),
+                    1 => {}
+                    _ => return Err(format!("{field} has '{letter}' more than once")),
+                }
+            }
+            match order.chars().find(|c| !alphabet.letters().contains(c)) {
+                Some(c) => Err(format!("{field} has '{c}', which isn't in the alphabet")),
+                None => Ok(()),
+            }
+        };
+        check_order("frequency_order", &file.frequency_order)?;
+        if let Some(collation) = &file.collation {
+            check_order("collation", collation)?;
+        }
+        let in_alphabet = |word: &String| word.chars().all(|c| alphabet.letters().contains(&c));
+        if let Some(word) = file
+            .stop_words
+            .iter()
+            .chain(&file.one_letter_words)
+            .find(|word| word.is_empty() || !in_alphabet(word))
+        {
+            return Err(format!(
+                "\"{word}\" isn't a word of lowercase letters of the alphabet"
+            ));
+        }
+        if let Some(word) = file
+            .one_letter_words
+            .iter()
+            .find(|word| word.chars().count() != 1)
+        {
+            return Err(format!("one_letter_words has \"{word}\""));
+        }
+        Ok(Self {
+            name: file.name,
+            collation: file.collation.map_or_else(
+                || alphabet.letters().to_vec(),
+                |collation| collation.chars().collect(),
+            ),
+            alphabet,
+            frequency_order: file.frequency_order,
+            ngrams: file.ngrams,
+            stop_words: file.stop_words,
+            one_letter_words: file.one_letter_words,
+        })
+    }
+
+    /// Name of the language
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Path of the table of quadgram counts, if the pack has one. See
+    /// [`Quadgrams::from_table`](crate::Quadgrams::from_table) for the format.
+    #[must_use]
+    pub fn ngrams(&self) -> Option<&Path> {
+        self.ngrams.as_deref()
+    }
+
+    /// The most common words of the language
+    #[must_use]
+    pub fn stop_words(&self) -> &[String] {
+        &self.stop_words
+    }
+
+    /// Words of the language which are a single letter
+    #[must_use]
+    pub fn one_letter_words(&self) -> &[String] {
+        &self.one_letter_words
+    }
+}
+
+impl std::fmt::Display for Language {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::English => "english",
+            Self::Finnish => "finnish",
+            Self::German => "german",
+            Self::French => "french",
+            Self::Spanish => "spanish",
+            Self::Custom(pack) => &pack.name,
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl std::str::FromStr for Language {
+    type Err = Error;
+
+    /// Parse the name of a language in English, like `finnish`, or its two letter code, like `fi`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_ref() {
+            "english" | "en" => Ok(Self::English),
+            "finnish" | "fi" => Ok(Self::Finnish),
+            "german" | "de" => Ok(Self::German),
+            "french" | "fr" => Ok(Self::French),
+            "spanish" | "es" => Ok(Self::Spanish),
+            _ => Err(Error::UnknownLanguage),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn frequency_order_has_every_letter_once() {
+        for language in Language::ALL {
+            let alphabet = language.alphabet();
+            let order = language.frequency_order();
+            assert_eq!(order.chars().count(), alphabet.len(), "{language}");
+            assert!(alphabet.letters().iter().all(|c| order.contains(*c)));
+            let mut collation = language.collation();
+            collation.sort_unstable();
+            let mut letters = alphabet.letters().to_vec();
+            letters.sort_unstable();
+            assert_eq!(collation, letters, "{language}");
+            assert_eq!(language.to_string().parse::<Language>().unwrap(), language);
+        }
+    }
+
+    #[test]
+    fn example_language_pack_loads() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("languages/italian.toml");
+        let italian = Language::from_path(path).unwrap();
+        assert_eq!(italian.to_string(), "italian");
+        assert_eq!(italian.alphabet(), Alphabet::english());
+        assert!(italian.frequency_order().starts_with("eaio"));
+        let Language::Custom(pack) = italian else {
+            panic!("Not a language pack");
+        };
+        assert!(pack.one_letter_words().contains(&"e".to_owned()));
+        assert!(pack.ngrams().is_none());
+    }
+
+    #[test]
+    fn invalid_language_packs() {
+        let check = |toml: &str| LanguagePack::from_file(toml::from_str(toml).unwrap());
+        assert!(check("name = 'x'\nalphabet = 'abc'\nfrequency_order = 'cab'").is_ok());
+        for (toml, problem) in [
+            ("name = ''\nfrequency_order = 'a'", "name is empty"),
+            (
+                "name = 'x'\nalphabet = 'abc'\nfrequency_order = 'ca'",
+                "frequency_order doesn't have 'b'",
+            ),
+            (
+                "name = 'x'\nalphabet = 'abc'\nfrequency_order = 'cabd'",
+                "frequency_order has 'd', which isn't in the alphabet",
+            ),
+            (
+                "name = 'x'\nalphabet = 'abc'\nfrequency_order = 'cab'\ncollation = 'ab'",
+                "collation doesn't have 'c'",
+            ),
+            (
+                "name = 'x'\nalphabet = 'ab'\nfrequency_order = 'ab'\none_letter_words = ['ab']",
+                "one_letter_words has \"ab\"",
+            ),
+        ] {
+            assert_eq!(check(toml).unwrap_err(), problem);
+        }
+        let unknown = toml::from_str::<PackFile>("name = 'x'\nfrequency_order = 'a'\nfoo = 1");
+        assert!(unknown.is_err());
+    }
+}
@@ -13,99 +13,692 @@
 #![allow(clippy::missing_panics_doc)]
 // Warnings about missing Cargo.toml fields
 #![warn(clippy::cargo)]
+#![warn(clippy::unwrap_used)]
+#![warn(clippy::expect_used)]
 // More about lint levels https://doc.rust-lang.org/rustc/lints/levels.html
 
-// "Include" bitset.rs and trie.rs
+// "Include" alphabet.rs, bitset.rs, language.rs, normalize.rs, pattern.rs, quadgrams.rs,
+// render.rs, solver.rs, stats.rs, testgen.rs, trie.rs, tuning.rs and verify.rs
+mod alphabet;
+mod beam;
 mod bitset;
+mod language;
+pub mod normalize;
+mod pattern;
+mod quadgrams;
+mod render;
+mod solver;
+mod source;
+mod stats;
+pub mod testgen;
 mod trie;
+mod tuning;
+mod verify;
+mod worksheet;
 
 use rand::prelude::*;
 use std::io::BufRead;
+use std::time::Duration;
 use thiserror::Error;
 
+pub use alphabet::Alphabet;
+pub use language::{Language, LanguagePack};
+pub use pattern::{pattern_of, Pattern};
+pub use quadgrams::Quadgrams;
+pub use render::{answer_key, estimate_report, key_table, puzzle_layout, ProgressEvent};
+pub use solver::{
+    Algorithm, CancelToken, KeySearch, Objective, SearchProgress, Solver, StepResult, Strategy,
+    WordOrdering,
+};
+pub use stats::LetterStats;
+pub use trie::Error as TrieError;
+pub use worksheet::{Conflict, Conflicts, Worksheet};
+
 /// Errors that can result from failed decryption
+///
+/// New variants may be added in future versions, so matching on this enum requires a wildcard arm.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// IO Error while parsing dictionary
     #[error("Failed to load dictionary")]
     LoadDictionary(#[from] std::io::Error),
+    /// A word could not be stored in the dictionary trie
+    #[error("Failed to insert a word into the dictionary")]
+    DictionaryInsert(#[from] TrieError),
+    /// The dictionary did not contain a single word
+    #[error("Dictionary is empty")]
+    DictionaryEmpty,
+    /// No wordlist was found in the common locations, see [`find_dictionary`]
+    #[error("No dictionary found")]
+    DictionaryNotFound {
+        /// The locations which were looked in
+        tried: Vec<std::path::PathBuf>,
+    },
+    /// The input doesn't contain any words which could be deciphered
+    #[error("Input does not contain any letters to decipher")]
+    UnsupportedInput,
+    /// The input has more words than the search is allowed to go deep, see
+    /// [`SolverConfig::max_words`]
+    #[error("Input has {words} words, more than the limit of {max}")]
+    InputTooLong {
+        /// Number of words in the input
+        words: usize,
+        /// The limit of words
+        max: usize,
+    },
     /// The entire search space has been iterated through but text doesn't match dictionary well enough
     #[error("Search exhausted. Insufficient dictionary?")]
     SearchExhausted,
+    /// The search was stopped because it ran out of time, see [`SolverConfig::max_duration`]
+    #[error("Search timed out")]
+    Timeout,
+    /// The search was stopped because it used up its work budget, see
+    /// [`SolverConfig::max_nodes`] and [`SolverConfig::max_backtracks`]
+    #[error("Search budget exceeded")]
+    BudgetExceeded,
+    /// The search was stopped with a [`CancelToken`]
+    #[error("Search cancelled")]
+    Cancelled,
+    /// A key is not a distinct letter or underscore for each letter of its alphabet
+    #[error("Key must have a distinct letter or '_' for each letter of the alphabet")]
+    InvalidKey,
+    /// IO Error while reading a key file, see [`Key::from_csv_in`]
+    #[error("Failed to read key")]
+    ReadKey(#[source] std::io::Error),
+    /// A key file doesn't follow the format of [`Key::from_csv_in`]
+    #[error("Invalid key CSV {0}")]
+    InvalidKeyCsv(String),
+    /// A worksheet doesn't follow the format of [`Worksheet`]
+    #[error("Invalid worksheet: {0}")]
+    InvalidWorksheet(String),
+    /// An alphabet is not 1 to 32 distinct lowercase letters
+    #[error("Alphabet must be 1 to 32 distinct lowercase letters")]
+    InvalidAlphabet,
+    /// A language name is not one of [`Language`]
+    #[error("Unknown language")]
+    UnknownLanguage,
+    /// An algorithm name is not one of [`Algorithm`]
+    #[error("Unknown strategy")]
+    UnknownStrategy,
+    /// A word ordering name is not one of [`WordOrdering`]
+    #[error("Unknown word ordering")]
+    UnknownWordOrdering,
+    /// An objective name is not one of [`Objective`]
+    #[error("Unknown objective")]
+    UnknownObjective,
+    /// A language pack file can't be read or doesn't follow the format of [`LanguagePack`]
+    #[error("Invalid language pack {0}")]
+    InvalidLanguagePack(String),
+    /// A table of quadgram counts doesn't follow the format of [`Quadgrams::from_table`], or
+    /// quadgrams are in another alphabet than the dictionary
+    #[error("Invalid quadgrams: {0}")]
+    InvalidNgrams(String),
+    /// A letter is not in the alphabet
+    #[error("'{0}' is not a letter of the alphabet")]
+    UnknownLetter(char),
+    /// A crib fits no ciphertext word, see [`SolverConfig::crib`]
+    #[error("No word of the ciphertext fits the crib '{0}'")]
+    UnplacedCrib(String),
+    /// Two letter mappings contradict each other
+    #[error("Conflicting mappings for '{cipher}' and '{plain}'")]
+    KeyConflict {
+        /// Ciphertext letter of the conflicting mapping
+        cipher: char,
+        /// Plaintext letter of the conflicting mapping
+        plain: char,
+    },
+}
+
+/// Conditions which don't prevent decryption but make it unreliable
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Warning {
+    /// The input has so few words or distinct letters that many keys fit the dictionary equally well
+    InputTooShort {
+        /// Number of distinct letters in the input
+        distinct_letters: usize,
+        /// Number of words in the input
+        words: usize,
+    },
+    /// Some words are so long that they are probably several words without separators between
+    /// them. They are left out of the search, see [`MAX_WORD_LEN`].
+    OverlongWords {
+        /// Number of words longer than [`MAX_WORD_LEN`]
+        words: usize,
+        /// Length of the longest word
+        longest: usize,
+    },
+    /// The letter frequencies of the input are unlike English even when its most common letter
+    /// is taken as the most common English letter and so on, so the plaintext is probably in
+    /// another language or enciphered with something else than a substitution cipher
+    UnusualLetterFrequencies {
+        /// How much of the letter frequencies of the input are the same as English after
+        /// matching up the letters, in percent
+        similarity: usize,
+    },
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InputTooShort {
+                distinct_letters,
+                words,
+            } => write!(
+                f,
+                "Input is too short to solve reliably ({words} words, {distinct_letters} distinct letters)"
+            ),
+            Self::OverlongWords { words, longest } => write!(
+                f,
+                "{words} words of the input are longer than {MAX_WORD_LEN} letters (up to {longest}), \
+                 so they are left out of the search"
+            ),
+            Self::UnusualLetterFrequencies { similarity } => write!(
+                f,
+                "Letter frequencies of the input are only {similarity}% like English, so it may be \
+                 in another language or not a substitution cipher"
+            ),
+        }
+    }
+}
+
+/// Inputs with fewer distinct letters than this are likely to have several plausible solutions
+const MIN_DISTINCT_LETTERS: usize = 10;
+/// Inputs with fewer words than this are likely to have several plausible solutions
+const MIN_WORDS: usize = 4;
+/// Inputs with fewer letters than this are too short for their letter frequencies to tell anything
+const MIN_FREQUENCY_LETTERS: usize = 200;
+/// Substitutions of English have letter frequencies at least this similar to English in percent,
+/// see [`frequency_similarity`]. Random letters and Vigenère ciphers of a few hundred letters
+/// are below 80%, and English above 90%.
+const MIN_FREQUENCY_SIMILARITY: usize = 85;
+/// Words longer than this are probably several words whose separators are missing. The search
+/// leaves them out, so that guessing a key isn't slowed down by deciphering them over and over,
+/// unless every word of the input is this long.
+pub const MAX_WORD_LEN: usize = 64;
+
+/// Leave the words longer than [`MAX_WORD_LEN`] out of `words`, unless there's nothing else
+fn leave_out_overlong(words: &mut Vec<&[u8]>) {
+    if words.iter().any(|word| word.len() <= MAX_WORD_LEN) {
+        words.retain(|word| word.len() <= MAX_WORD_LEN);
+    }
 }
 
-/// The range of ASCII lowercase letters that will be used in dictionary
+/// The range of ASCII lowercase letters
 const START: u8 = b'a';
 const END: u8 = b'z';
-const R: trie::AlphabetSize = START.abs_diff(END) as trie::AlphabetSize + 1;
+const ASCII_LETTERS: usize = START.abs_diff(END) as usize + 1;
+/// Letters of an [`Alphabet`] are coded as bytes from [`START`] on, and keys and the dictionary
+/// have room for this many of them
+const R: trie::AlphabetSize = Alphabet::MAX_LETTERS;
 
-/// Key that stores details about an encryption or decryption process
-struct Key {
+/// Substitution key, which maps each plaintext letter to a ciphertext letter.
+///
+/// Displayed and parsed as the ciphertext letters of each plaintext letter of its [`Alphabet`] in
+/// order, with `_` for letters which a partially solved key doesn't map. Keys parsed with
+/// [`str::parse`] are in the English alphabet, see [`Key::from_letters`] for other alphabets.
+///
+/// ```
+/// use substitution::{decrypt_with_key, encrypt_with_key, Key};
+///
+/// let key: Key = "bcdefghijklmnopqrstuvwxyza".parse().unwrap();
+/// assert_eq!(encrypt_with_key("Hello, world!", &key), "ifmmp xpsme");
+/// assert_eq!(decrypt_with_key("ifmmp xpsme", &key), "hello world");
+/// ```
+#[derive(Clone)]
+pub struct Key {
     table: [u8; R],
-    started_from: [u8; R],
     input_freq_index: [usize; R],
+    first_guesses: [u8; R],
     lang_freq_index: [usize; R],
     lang_freq_order: [u8; R],
     guesses: bitset::BitSet64<4>,
+    /// Input letters whose guesses were given instead of guessed, which stay as they are
+    pinned: bitset::BitSet64<1>,
+    alphabet: Alphabet,
+    metadata: Metadata,
+}
+
+/// A label and freeform notes for keeping track of keys and the solutions which they belong to,
+/// like the puzzle which a key enciphers or where a ciphertext came from. Keys carry it in their
+/// CSV files, see [`Key::with_metadata`], and solutions in their keys, see
+/// [`SolverConfig::metadata`].
+///
+/// ```
+/// use substitution::Metadata;
+///
+/// let metadata = Metadata::new().label("puzzle 12").note("From the Sunday paper");
+/// assert_eq!(metadata.to_string(), "Label: puzzle 12\nNote: From the Sunday paper");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Metadata {
+    /// Short name, like the ID of a puzzle
+    pub label: Option<String>,
+    /// Notes, a line each
+    pub notes: Vec<String>,
+}
+
+impl Metadata {
+    /// Create metadata without a label or notes
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the label
+    #[must_use]
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = Some(label.trim().to_owned());
+        self
+    }
+
+    /// Add a note. A note of many lines is added as a note for each line.
+    #[must_use]
+    pub fn note(mut self, note: &str) -> Self {
+        self.notes
+            .extend(note.lines().map(|line| line.trim().to_owned()));
+        self
+    }
+
+    /// Returns true if there is no label and no notes
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.label.is_none() && self.notes.is_empty()
+    }
+}
+
+/// The label and each note on their own lines, like `Label: puzzle 12`
+impl std::fmt::Display for Metadata {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = self.label.iter().map(|label| ("Label", label));
+        let notes = self.notes.iter().map(|note| ("Note", note));
+        for (i, (name, text)) in label.chain(notes).enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{name}: {text}")?;
+        }
+        Ok(())
+    }
 }
 
 impl Key {
     /// Create a random substitution key that can be used to encrypt a plaintext input
-    fn random() -> Self {
-        let mut table: Vec<u8> = (START..=END).collect();
-        let mut rng = rand::thread_rng();
-        table.shuffle(&mut rng);
+    #[must_use]
+    pub fn random() -> Self {
+        Self::random_with(&mut rand::thread_rng())
+    }
+
+    /// Create a key which maps every letter to itself
+    #[must_use]
+    pub fn identity() -> Self {
+        Self::identity_in(&Alphabet::english())
+    }
+
+    /// Create a key which maps every letter of `alphabet` to itself
+    #[must_use]
+    pub fn identity_in(alphabet: &Alphabet) -> Self {
+        let mut table = [0; R];
+        for (entry, chr) in table.iter_mut().zip(alphabet.codes()) {
+            *entry = chr;
+        }
+        Self::from_table(table, alphabet.clone())
+    }
+
+    /// The alphabet which the key maps
+    #[must_use]
+    pub fn alphabet(&self) -> &Alphabet {
+        &self.alphabet
+    }
+
+    /// The label and notes of the key
+    #[must_use]
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Attach a label and notes to the key, which its CSV file keeps. They don't change what the
+    /// key substitutes, so keys which only differ by them are equal.
+    ///
+    /// ```
+    /// use substitution::{Key, Metadata};
+    ///
+    /// let key = Key::identity().with_metadata(Metadata::new().label("puzzle 12"));
+    /// let mut csv = Vec::new();
+    /// key.to_csv(&mut csv).unwrap();
+    /// let read = Key::from_csv(csv.as_slice()).unwrap();
+    /// assert_eq!(read.metadata().label.as_deref(), Some("puzzle 12"));
+    /// assert_eq!(read, Key::identity());
+    /// ```
+    #[must_use]
+    pub fn with_metadata(mut self, metadata: Metadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Substitute plaintext letters in `text` with their ciphertext letters. Case, punctuation,
+    /// whitespace and unmapped letters are kept as they are.
+    ///
+    /// ```
+    /// use substitution::Key;
+    ///
+    /// let key: Key = "bcdefghijklmnopqrstuvwxyza".parse().unwrap();
+    /// assert_eq!(key.encipher("Hello, world!"), "Ifmmp, xpsme!");
+    /// assert_eq!(key.decipher("Ifmmp, xpsme!"), "Hello, world!");
+    /// ```
+    #[must_use]
+    pub fn encipher(&self, text: &str) -> String {
+        text.chars()
+            .map(|c| {
+                let translation = self
+                    .alphabet
+                    .code(c)
+                    .map(|code| self.table[Self::index(code)])
+                    .and_then(|code| self.alphabet.letter(code));
+                match translation {
+                    Some(letter) if c.is_uppercase() => letter.to_uppercase().collect(),
+                    Some(letter) => letter.to_string(),
+                    None => c.to_string(),
+                }
+            })
+            .collect()
+    }
+
+    /// Substitute ciphertext letters in `text` with their plaintext letters, undoing
+    /// [`Key::encipher`]
+    #[must_use]
+    pub fn decipher(&self, text: &str) -> String {
+        self.inverse().encipher(text)
+    }
+
+    /// Create a random substitution key using the given random number generator. A seeded
+    /// generator gives the same key every time.
+    pub fn random_with(rng: &mut impl Rng) -> Self {
+        Self::random_in(&Alphabet::english(), rng)
+    }
+
+    /// Create a random substitution key for the letters of `alphabet` using the given random
+    /// number generator
+    pub fn random_in(alphabet: &Alphabet, rng: &mut impl Rng) -> Self {
+        let mut table = Self::identity_in(alphabet).table;
+        table[..alphabet.len()].shuffle(rng);
+        Self::from_table(table, alphabet.clone())
+    }
+
+    /// Parse a key from the ciphertext letters of each letter of `alphabet` in order, in either
+    /// case, with `_` for unmapped letters
+    ///
+    /// ```
+    /// use substitution::{Alphabet, Key};
+    ///
+    /// let finnish = Alphabet::finnish();
+    /// let key = Key::from_letters("bcdefghijklmnopqrstuvwxyzåäöa", &finnish).unwrap();
+    /// assert_eq!(key.encipher("Yö"), "Za");
+    /// assert_eq!(key.encipher("zÄ"), "åÖ");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidKey`] if `letters` doesn't have a distinct letter of `alphabet` or
+    /// `_` for each letter of it.
+    pub fn from_letters(letters: &str, alphabet: &Alphabet) -> Result<Self, Error> {
+        let mut table = [0; R];
+        let mut used = bitset::BitSet64::<1>::new();
+        if letters.chars().count() != alphabet.len() {
+            return Err(Error::InvalidKey);
+        }
+        for (entry, chr) in table.iter_mut().zip(letters.chars()) {
+            match alphabet.code(chr) {
+                _ if chr == '_' => {}
+                Some(code) if !used.contains(code - START) => {
+                    used.insert(code - START);
+                    *entry = code;
+                }
+                _ => return Err(Error::InvalidKey),
+            }
+        }
+        Ok(Self::from_table(table, alphabet.clone()))
+    }
+
+    /// Read a key in the English alphabet from a CSV file of two columns, like
+    /// [`Key::from_csv_in`]
+    ///
+    /// # Errors
+    ///
+    /// See [`Key::from_csv_in`].
+    pub fn from_csv(reader: impl BufRead) -> Result<Self, Error> {
+        Self::from_csv_in(reader, &Alphabet::english())
+    }
+
+    /// Read a key from a CSV file with a ciphertext letter and its plaintext letter of `alphabet`
+    /// in either case on each row, like `x,a`, as spreadsheets export a substitution table.
+    /// Empty lines, lines starting with `#` and a `cipher,plain` header are skipped, except for
+    /// `# label:` and `# note:` lines, which are read as the [`Metadata`] of the key. Plaintext
+    /// letters without a row are unmapped.
+    ///
+    /// ```
+    /// use substitution::Key;
+    ///
+    /// let csv = "cipher,plain\nB,a\nc,b\n";
+    /// let key = Key::from_csv(csv.as_bytes()).unwrap();
+    /// assert_eq!(key.encipher("Abba"), "Bccb");
+    /// assert_eq!(key.to_string(), "bc________________________");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::ReadKey`] if reading fails, [`Error::InvalidKeyCsv`] if a row isn't
+    /// two letters of `alphabet`, and [`Error::KeyConflict`] if a letter has two rows.
+    pub fn from_csv_in(reader: impl BufRead, alphabet: &Alphabet) -> Result<Self, Error> {
+        let mut table = [0; R];
+        let mut used = bitset::BitSet64::<1>::new();
+        let mut metadata = Metadata::new();
+        for (number, line) in (1..).zip(reader.lines()) {
+            let line = line.map_err(Error::ReadKey)?;
+            let line = line.trim();
+            if let Some(comment) = line.strip_prefix('#') {
+                let comment = comment.trim_start();
+                if let Some(label) = comment.strip_prefix("label:") {
+                    metadata = metadata.label(label);
+                } else if let Some(note) = comment.strip_prefix("note:") {
+                    metadata = metadata.note(note);
+                }
+                continue;
+            }
+            if line.is_empty() || line.eq_ignore_ascii_case("cipher,plain") {
+                continue;
+            }
+            let invalid = || Error::InvalidKeyCsv(format!("line {number}: \"{line}\""));
+            // Spreadsheets may quote the cells
+            let letter = |cell: &str| {
+                let mut chars = cell.trim().trim_matches('"').chars();
+                match (chars.next(), chars.next()) {
+                    (Some(chr), None) => alphabet.code(chr).map(|code| (chr, code)),
+                    _ => None,
+                }
+            };
+            let (cipher, plain) = line.split_once(',').ok_or_else(invalid)?;
+            let ((cipher, cipher_code), (plain, plain_code)) =
+                letter(cipher).zip(letter(plain)).ok_or_else(invalid)?;
+            let entry = &mut table[Self::index(plain_code)];
+            if *entry != 0 || used.contains(cipher_code - START) {
+                return Err(Error::KeyConflict { cipher, plain });
+            }
+            *entry = cipher_code;
+            used.insert(cipher_code - START);
+        }
+        Ok(Self::from_table(table, alphabet.clone()).with_metadata(metadata))
+    }
+
+    /// Write the key as a CSV file which [`Key::from_csv_in`] reads, with a `cipher,plain` header
+    /// and a row for each plaintext letter which the key maps, in the order of the alphabet. The
+    /// label and notes of the key are written as `# label:` and `# note:` lines before the
+    /// header.
+    ///
+    /// ```
+    /// use substitution::Key;
+    ///
+    /// let key: Key = "bc________________________".parse().unwrap();
+    /// let mut csv = Vec::new();
+    /// key.to_csv(&mut csv).unwrap();
+    /// assert_eq!(String::from_utf8(csv).unwrap(), "cipher,plain\nb,a\nc,b\n");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing to `writer` fails.
+    pub fn to_csv(&self, mut writer: impl std::io::Write) -> std::io::Result<()> {
+        if let Some(label) = &self.metadata.label {
+            writeln!(writer, "# label: {label}")?;
+        }
+        for note in &self.metadata.notes {
+            writeln!(writer, "# note: {note}")?;
+        }
+        writeln!(writer, "cipher,plain")?;
+        for (plain, cipher) in self.alphabet.codes().zip(self.table) {
+            if let (Some(plain), Some(cipher)) =
+                (self.alphabet.letter(plain), self.alphabet.letter(cipher))
+            {
+                writeln!(writer, "{cipher},{plain}")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Create a key for encryption or decryption from a substitution table
+    fn from_table(table: [u8; R], alphabet: Alphabet) -> Self {
         Self {
-            table: table.try_into().unwrap(),
-            started_from: [0; R],
+            table,
             input_freq_index: [0; R],
+            first_guesses: [0; R],
             lang_freq_index: [0; R],
             lang_freq_order: [0; R],
             guesses: bitset::BitSet64::<4>::new(),
+            pinned: bitset::BitSet64::<1>::new(),
+            alphabet,
+            metadata: Metadata::new(),
         }
     }
 
     /// Create an uninitialized substitution key that can be used to search for the correct key during decryption
-    fn new(input: &[u8], lang_freq_order: [u8; R]) -> Self {
+    ///
+    /// `freqs` has the letters of `alphabet` in the language's frequency order and the per mille
+    /// of the language's words starting with each letter. The latter adjusts the first guess of
+    /// each input character in [`Key::pick_first_guesses`].
+    fn new(input: &[u8], alphabet: &Alphabet, freqs: &LanguageFreqs) -> Self {
+        let lang_freq_order = freqs.order;
+
         // Count input characters
-        let mut freqs = [0; R];
-        for chr in input.iter().filter(|c| c.is_ascii_alphabetic()) {
-            freqs[usize::from(*chr - START)] += 1;
+        let mut freqs_in_input = [0; R];
+        for chr in input.iter().filter(|c| Alphabet::is_code(**c)) {
+            freqs_in_input[usize::from(*chr - START)] += 1;
         }
         // Sort by frequency
-        let mut freqs: Vec<(u8, &usize)> = (START..).zip(freqs.iter()).collect();
-        freqs.sort_unstable_by_key(|e| std::cmp::Reverse(e.1));
+        let mut freqs_in_input: Vec<(u8, &usize)> = (START..).zip(freqs_in_input.iter()).collect();
+        freqs_in_input.sort_unstable_by_key(|e| std::cmp::Reverse(e.1));
         // Create a table for each input character's frequency index
         let mut input_freq_index = [0; R];
-        for chr in START..=END {
-            let idx = freqs
+        for (idx, (chr, _)) in freqs_in_input.iter().enumerate() {
+            input_freq_index[Self::index(*chr)] = idx;
+        }
+        // Count how many ranks next to each input character have counts too close to its own to
+        // tell them apart, within a standard deviation of how much counts of letters vary
+        let mut rank_spread = [0; R];
+        for (rank, (chr, count)) in freqs_in_input.iter().enumerate() {
+            let close = |(_, other): &&(u8, &usize)| {
+                let gap = count.abs_diff(**other);
+                gap * gap <= **count + **other
+            };
+            let below = freqs_in_input[..rank]
                 .iter()
-                .enumerate()
-                .find(|(_, (c, _))| *c == chr)
-                .unwrap()
-                .0;
-            input_freq_index[usize::from(chr - START)] = idx;
+                .rev()
+                .take_while(close)
+                .count();
+            let above = freqs_in_input[rank + 1..].iter().take_while(close).count();
+            rank_spread[Self::index(*chr)] = below.max(above);
         }
 
         // Create a table for each language character's frequency index
         let mut lang_freq_index = [0; R];
-        for chr in START..=END {
-            let idx = lang_freq_order
-                .iter()
-                .enumerate()
-                .find(|(_, c)| **c == chr)
-                .unwrap()
-                .0;
-            lang_freq_index[usize::from(chr - START)] = idx;
+        for (idx, chr) in lang_freq_order[..alphabet.len()].iter().enumerate() {
+            lang_freq_index[Self::index(*chr)] = idx;
         }
 
-        Self {
+        let mut key = Self {
             table: [0; R],
-            started_from: [0; R],
             input_freq_index,
+            first_guesses: [0; R],
             lang_freq_index,
             lang_freq_order,
             guesses: bitset::BitSet64::<4>::new(),
+            pinned: bitset::BitSet64::<1>::new(),
+            alphabet: alphabet.clone(),
+            metadata: Metadata::new(),
+        };
+        key.pick_first_guesses(input, &freqs.initial, &rank_spread);
+        key
+    }
+
+    /// Create a key which deciphers each letter of `input` as the language letter of the same
+    /// frequency rank, without looking at the words at all
+    fn frequency_rank(input: &[u8], alphabet: &Alphabet, freqs: &LanguageFreqs) -> Self {
+        let mut key = Self::new(input, alphabet, freqs);
+        for chr in alphabet.codes().filter(|chr| input.contains(chr)) {
+            let idx = Self::index(chr);
+            key.table[idx] = key.lang_freq_order[key.input_freq_index[idx]];
+        }
+        key
+    }
+
+    /// Pick the first guess for every input character: the language character at the same
+    /// frequency rank, or a close one if it matches how often the input character starts words
+    /// better. For example, in English 't' starts many more words than 'e' or 'a' does.
+    ///
+    /// The rank of a character is weak evidence when `rank_spread` other characters next to it
+    /// have about as many occurrences, so distance in rank then counts less against a guess, and
+    /// how often the character starts words decides more.
+    fn pick_first_guesses(
+        &mut self,
+        input: &[u8],
+        lang_initial_freq: &[u16; R],
+        rank_spread: &[usize; R],
+    ) {
+        // Count the words and the input characters which start them
+        let mut initials = [0; R];
+        let mut words = 0;
+        for word in input.split(u8::is_ascii_whitespace) {
+            if let Some(chr) = word.first() {
+                initials[Self::index(*chr)] += 1;
+                words += 1;
+            }
+        }
+
+        let lang_freq_order = &self.lang_freq_order;
+        let size = self.alphabet.len();
+        for chr in (START..).take(size) {
+            let rank = self.input_freq_index[Self::index(chr)];
+            self.first_guesses[Self::index(chr)] = if words < MIN_WORDS {
+                lang_freq_order[rank]
+            } else {
+                // Per mille of words which the input character starts
+                let initial_freq = initials[Self::index(chr)] * 1000 / words;
+                let spread = rank_spread[Self::index(chr)];
+                let rank_cost = FIRST_GUESS_RANK_COST / (1 + spread);
+                let window = rank.saturating_sub(FIRST_GUESS_WINDOW)..=(rank + FIRST_GUESS_WINDOW);
+                lang_freq_order[window
+                    .filter(|idx| *idx < size)
+                    .min_by_key(|idx| {
+                        let guess = lang_freq_order[*idx];
+                        let distance = idx.abs_diff(rank);
+                        let mismatch = initial_freq
+                            .abs_diff(usize::from(lang_initial_freq[Self::index(guess)]));
+                        (distance * rank_cost + mismatch, distance)
+                    })
+                    // The window always has the rank itself
+                    .unwrap_or(rank)]
+            };
         }
     }
 
@@ -114,21 +707,37 @@ impl Key {
         usize::from(input - START)
     }
 
-    /// Set a guess for a given input character
+    /// Set a guess for a given input character, unless it's pinned
     fn attach(&mut self, input: u8, guess: u8) -> Result<(), ()> {
-        if self.guesses.contains(guess) {
+        if self.guesses.contains(guess) || self.pinned.contains(input - START) {
             return Err(());
         }
         let idx = Self::index(input);
         self.guesses.remove(self.table[idx]);
-        if self.table[idx] == 0 {
-            self.started_from[idx] = guess;
-        }
         self.table[idx] = guess;
         self.guesses.insert(guess);
         Ok(())
     }
 
+    /// Decipher each input character of `pins` as its plaintext character for good, see
+    /// [`SolverConfig::pin_letter`]. A character which was deciphered as a pinned plaintext
+    /// character takes the earlier guess of the pinned one instead.
+    fn with_pins(mut self, pins: &[(u8, u8)]) -> Self {
+        for &(input, plain) in pins {
+            let idx = Self::index(input);
+            let earlier = self.table[idx];
+            if let Some(other) = self.table.iter().position(|guess| *guess == plain) {
+                self.table[other] = earlier;
+            } else {
+                self.guesses.remove(earlier);
+                self.guesses.insert(plain);
+            }
+            self.table[idx] = plain;
+            self.pinned.insert(input - START);
+        }
+        self
+    }
+
     /// Get the next character in language frequency order
     fn next_in_freq_order(&self, start_guess: u8, current_guess: u8) -> u8 {
         use std::cmp::Ordering;
@@ -144,7 +753,8 @@ impl Key {
         let lower = (diff < start_idx).then(|| start_idx - diff - 1);
 
         // Some next higher index, or None if out of bounds
-        let higher = (start_idx + diff < R).then(|| start_idx + diff);
+        let size = self.alphabet.len();
+        let higher = (start_idx + diff < size).then(|| start_idx + diff);
 
         // Determine the following index to choose
         let idx = match (current_idx.cmp(&start_idx), lower, higher) {
@@ -155,59 +765,143 @@ impl Key {
             // If currently at start or higher/right of start, pick lower if available
             | (Ordering::Equal | Ordering::Greater, Some(idx), _) => idx,
             // If currently at start of higher/right of start, but no lower/left option available, pick right/higher
-            (Ordering::Equal | Ordering::Greater, None, Some(idx)) => {
-                // But increment by one because we're currently already at the position
-                if idx + 1 < R {
-                    idx + 1
-                } else {
-                    return 0;
-                }
-            }
+            // But increment by one because we're currently already at the position
+            (Ordering::Equal | Ordering::Greater, None, Some(idx)) if idx + 1 < size => idx + 1,
             _ => return 0,
         };
         self.lang_freq_order[idx]
     }
 
-    /// Set a next guess in language frequency order for input character
-    fn attach_next(&mut self, input: u8) -> Result<(), ()> {
-        let idx = Self::index(input);
+    /// Letters to guess for input character in language frequency order, from its first guess
+    /// out to both sides
+    fn guess_order(&self, input: u8) -> impl Iterator<Item = u8> + '_ {
+        let first_guess = self.first_guesses[Self::index(input)];
+        std::iter::successors(Some(first_guess), move |current| {
+            Some(self.next_in_freq_order(first_guess, *current)).filter(|guess| *guess != 0)
+        })
+    }
 
-        // Make a first guess if needed, or just load the previous/current guess and it's starting point
-        let (start_guess, mut current_guess) = match self.table[idx] {
-            0 => {
-                let freq_index = self.input_freq_index[usize::from(input - START)];
-                let first_guess = self.lang_freq_order[freq_index];
-                if self.attach(input, first_guess).is_ok() {
-                    return Ok(());
-                }
-                (first_guess, first_guess)
-            }
-            current_guess => (self.started_from[idx], current_guess),
+    /// Set a next guess in language frequency order for input character which appears alone in a word
+    /// of length `len`, such that the word of the guess repeated `len` times is in the dictionary
+    fn attach_next_uniform(
+        &mut self,
+        input: u8,
+        len: usize,
+        dict: &impl source::WordSource,
+        scratch: &mut [u8],
+    ) -> Result<(), ()> {
+        // Continue from the position of the current guess in frequency order
+        let start = match self.table[Self::index(input)] {
+            0 => 0,
+            current_guess => self.lang_freq_index[Self::index(current_guess)] + 1,
         };
 
-        while {
-            current_guess = self.next_in_freq_order(start_guess, current_guess);
-            if current_guess == 0 {
-                return Err(());
+        let order = self.lang_freq_order;
+        for &guess in &order[start..self.alphabet.len()] {
+            scratch[..len].fill(guess);
+            if dict.contains(&scratch[..len]) && self.attach(input, guess).is_ok() {
+                return Ok(());
             }
-            // Try attaching a next guess for as long as it fails
-            self.attach(input, current_guess).is_err()
-        } {}
-        Ok(())
+        }
+        Err(())
+    }
+
+    /// Create a key which undoes the substitution of this key. Letters which this key doesn't map
+    /// to are left unmapped in the inverse.
+    #[must_use]
+    pub fn inverse(&self) -> Self {
+        let mut table = [0; R];
+        for (plain, cipher) in self.alphabet.codes().zip(self.table) {
+            if cipher != 0 {
+                table[Self::index(cipher)] = plain;
+            }
+        }
+        Self::from_table(table, self.alphabet.clone()).with_metadata(self.metadata.clone())
+    }
+
+    /// Compare the key to `truth`, the key which is known to be right, like the key which
+    /// generated a cryptogram. For each plaintext letter which `truth` maps, tells which letter
+    /// this key deciphers its ciphertext letter into, so that letters which were mistaken for
+    /// each other can be told apart from unsolved ones. Letters are compared by their characters,
+    /// so the keys can be of different alphabets.
+    ///
+    /// ```
+    /// use substitution::Key;
+    ///
+    /// let truth: Key = "bcdefghijklmnopqrstuvwxyza".parse().unwrap();
+    /// // 'a' and 'b' are swapped, and 'c' isn't solved
+    /// let found: Key = "cb_efghijklmnopqrstuvwxyza".parse().unwrap();
+    /// let confusion = found.confusion(&truth);
+    /// assert_eq!(confusion.len(), 26);
+    /// assert_eq!(confusion[0].deciphered, Some('b'));
+    /// assert_eq!(confusion[2].deciphered, None);
+    /// assert_eq!(confusion.iter().filter(|letter| letter.is_correct()).count(), 23);
+    /// ```
+    #[must_use]
+    pub fn confusion(&self, truth: &Self) -> Vec<LetterConfusion> {
+        let inverse = self.inverse();
+        truth
+            .alphabet
+            .codes()
+            .zip(truth.table)
+            .filter_map(|(plain, cipher)| {
+                let plain = truth.alphabet.letter(plain)?;
+                let cipher = truth.alphabet.letter(cipher)?;
+                let deciphered = self
+                    .alphabet
+                    .code(cipher)
+                    .and_then(|code| self.alphabet.letter(inverse.table[Self::index(code)]));
+                Some(LetterConfusion { plain, deciphered })
+            })
+            .collect()
     }
 
-    /// Remove the current guess from a given input character
+    /// Remove the current guess from a given input character, unless it's pinned
     fn clear(&mut self, input: u8) {
+        if self.pinned.contains(input - START) {
+            return;
+        }
         let idx = Self::index(input);
         self.guesses.remove(self.table[idx]);
         self.table[idx] = 0;
     }
 
-    /// Replace characters in text according to current key state.
+    /// Panic if the table maps two letters to the same letter or outside the alphabet, or if
+    /// the set of guesses isn't the letters which the table maps to
+    #[cfg(feature = "debug-invariants")]
+    fn check_invariants(&self) {
+        let letters = self.alphabet.len();
+        for (idx, guess) in self
+            .table
+            .iter()
+            .enumerate()
+            .filter(|(_, guess)| **guess != 0)
+        {
+            assert!(
+                idx < letters && self.alphabet.letter(*guess).is_some(),
+                "Key maps {idx} to {guess}, outside an alphabet of {letters} letters"
+            );
+            assert!(
+                !self.table[..idx].contains(guess),
+                "Key maps two letters to {}",
+                char::from(*guess)
+            );
+        }
+        for code in 1..=u8::MAX {
+            assert_eq!(
+                self.guesses.contains(code),
+                self.table.contains(&code),
+                "Guesses and the key disagree on {code}"
+            );
+        }
+    }
+
+    /// Replace letter codes in text according to current key state.
     /// In other words, perform the substitution. Encrypt or decrypt.
+    /// All other bytes are left as they are.
     fn translate(&self, text: &mut [u8]) {
         for c in text {
-            if c.is_ascii_alphabetic() {
+            if Alphabet::is_code(*c) {
                 let translation = self.table[Self::index(*c)];
                 if translation != 0 {
                     *c = translation;
@@ -215,259 +909,2547 @@ impl Key {
             }
         }
     }
-}
 
-/// Substitutes uppercase ASCII alphabetic (A-Z) characters with lowercase equivalents.
-/// Replaces dashes with spaces and leaves out everything else.
-fn filter_input(input: &str) -> Vec<u8> {
-    input
-        .chars()
-        .filter_map(|c| {
-            if c.is_ascii_alphabetic() || c.is_ascii_whitespace() {
-                c.to_ascii_lowercase().try_into().ok()
-            } else if c == '-' {
-                Some(b' ')
-            } else {
-                None
+    /// Replace the letter codes in `text` which the key doesn't map with `mask`
+    fn mask_unmapped(&self, text: &mut [u8], mask: u8) {
+        for c in text {
+            if Alphabet::is_code(*c) && self.table[Self::index(*c)] == 0 {
+                *c = mask;
             }
-        })
-        .collect()
-}
-
-/// Encrypts the string provided from CLI with a randomly generated substitution cipher.
-#[must_use]
-pub fn encrypt(input: &str) -> String {
-    let mut input = filter_input(input);
-
-    // Create a random substitution
-    let key = Key::random();
-
-    // Encrypt
-    key.translate(&mut input);
+        }
+    }
 
-    String::from_utf8(input).unwrap()
+    /// Substitute ASCII letters in text like [`Key::translate`] does letter codes, for keys in
+    /// the English alphabet. Uppercase letters keep their case and all other bytes are left as
+    /// they are.
+    fn translate_ascii(&self, text: &mut [u8]) {
+        for c in text {
+            if c.is_ascii_alphabetic() {
+                let translation = self.table[Self::index(c.to_ascii_lowercase())];
+                if translation != 0 {
+                    *c = if c.is_ascii_uppercase() {
+                        translation.to_ascii_uppercase()
+                    } else {
+                        translation
+                    };
+                }
+            }
+        }
+    }
 }
 
-/// Returns a list of all unique alphabetic characters in input.
-fn unique_chars(input: &[u8]) -> Vec<u8> {
-    let mut uc = Vec::with_capacity(16);
-    let mut set = bitset::BitSet64::<4>::new();
-    for c in input {
-        if c.is_ascii_alphabetic() && !set.contains(*c) {
-            uc.push(*c);
-            set.insert(*c);
+impl std::fmt::Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for chr in &self.table[..self.alphabet.len()] {
+            write!(f, "{}", self.alphabet.letter(*chr).unwrap_or('_'))?;
         }
+        Ok(())
     }
-    uc
 }
 
-/// Recursive backtracking deciphering word by word
-fn decrypt_words<'a>(
-    words: &[&'a [u8]],
-    scratch: &mut [u8],
-    key: &mut Key,
-    chars_set: &mut bitset::BitSet64<1>,
-    dict: &trie::Set<R, { START as usize }>,
-    skip_words: &mut Vec<&'a [u8]>,
-    can_skip: usize,
-) -> Result<(), ()> {
-    // Happy path end for recursion
-    if words.is_empty() {
-        return Ok(());
-    }
-
-    // Create a convenience binding for current input word
-    let word = words[0];
-
-    // Check if this word should be skipped for now
-    if skip_words.contains(&word) {
-        // Proceed to next
-        if decrypt_words(
-            &words[1..],
-            scratch,
-            key,
-            chars_set,
-            dict,
-            skip_words,
-            can_skip,
-        )
-        .is_ok()
-        {
-            return Ok(());
-        }
+impl std::fmt::Debug for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Key").field(&self.to_string()).finish()
     }
+}
 
-    // Generate list of currently relevant and unset chars in input
-    let free_chars: Vec<u8> = unique_chars(word)
-        .into_iter()
-        .filter(|c| !chars_set.contains(c - START))
-        .collect();
+/// Keys are equal when they substitute the same letters of the same alphabet, regardless of how
+/// they were found
+impl PartialEq for Key {
+    fn eq(&self, other: &Self) -> bool {
+        self.table == other.table && self.alphabet == other.alphabet
+    }
+}
+
+impl Eq for Key {}
+
+impl std::hash::Hash for Key {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.table.hash(state);
+    }
+}
 
-    // Set input chars in stone for next round so they won't be iterated
-    free_chars.iter().for_each(|c| chars_set.insert(*c - START));
+impl std::str::FromStr for Key {
+    type Err = Error;
 
-    'test: loop {
-        // Set input word to scratch
-        (&mut scratch[..word.len()]).copy_from_slice(word);
+    /// Parse a key from the ciphertext letters of plaintext letters a to z, in either case, with
+    /// `_` for unmapped letters
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_letters(s, &Alphabet::english())
+    }
+}
 
-        // Try to translate by current key state
-        key.translate(&mut scratch[..word.len()]);
+/// Normalizes input like the default [`normalize::Normalizer`]: substitutes uppercase characters
+/// with lowercase equivalents, replaces dashes with spaces and leaves out everything else than
+/// ASCII letters and whitespace. Letters are coded as bytes, which for ASCII letters are the
+/// letters themselves.
+fn filter_input(input: &str) -> Vec<u8> {
+    filter_input_with(input, None, &Alphabet::english())
+}
 
-        // Check the validity of the attempt
-        let score = dict.prefix_score(&scratch[..word.len()]).unwrap();
-        if score == word.len() + 1 {
-            #[cfg(debug_assertions)]
-            eprintln!(
-                "Found likely word \"{}\"",
-                String::from_utf8_lossy(&scratch[..word.len()])
-            );
+/// Normalizes input with `normalizer` followed by lowercasing it if given, or by lowercasing it
+/// and replacing dashes with spaces otherwise. Then codes the letters of `alphabet` and leaves out
+/// everything else than them and ASCII whitespace.
+fn filter_input_with(
+    input: &str,
+    normalizer: Option<&normalize::Normalizer>,
+    alphabet: &Alphabet,
+) -> Vec<u8> {
+    use normalize::Stage;
+    alphabet.encode(&match normalizer {
+        Some(normalizer) => normalize::Lowercase.apply(&normalizer.normalize(input)),
+        None => normalize::MapDashToSpace.apply(&normalize::Lowercase.apply(input)),
+    })
+}
 
-            // Proceed to next without skipping current
-            if decrypt_words(
-                &words[1..],
-                scratch,
-                key,
-                chars_set,
-                dict,
-                skip_words,
-                can_skip,
-            )
-            .is_ok()
-            {
-                return Ok(());
-            }
-        }
+/// Letter case of encrypted and deciphered text, see [`EncryptOptions::case`] and
+/// [`SolverConfig::case`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Case {
+    /// Lowercase letters
+    #[default]
+    Lower,
+    /// Uppercase letters
+    Upper,
+    /// The case of the input's letter in the same place, counting only letters of the alphabet
+    MatchInput,
+}
 
-        for chr in &free_chars {
-            match key.attach_next(*chr) {
-                Ok(()) => continue 'test,
-                Err(()) => {
-                    key.clear(*chr);
+impl Case {
+    /// Write the letters of `output` in this case, which for [`Case::MatchInput`] is taken from
+    /// the letters of `input` in order. `_` in `output` stands for a letter which isn't known.
+    fn apply(self, output: &str, input: &str, alphabet: &Alphabet) -> String {
+        match self {
+            Self::Lower => output.to_lowercase(),
+            Self::Upper => output.to_uppercase(),
+            Self::MatchInput => {
+                let mut upper = input
+                    .chars()
+                    .filter(|c| alphabet.code(*c).is_some())
+                    .map(char::is_uppercase);
+                let mut cased = String::with_capacity(output.len());
+                for c in output.chars() {
+                    if c != '_' && alphabet.code(c).is_none() {
+                        cased.push(c);
+                    } else if upper.next() == Some(true) {
+                        cased.extend(c.to_uppercase());
+                    } else {
+                        cased.extend(c.to_lowercase());
+                    }
                 }
+                cased
             }
         }
-        break;
     }
+}
 
-    // Key exhausted but it's possible that this word is not in the dictionary, try skipping
-    if can_skip > 0 {
-        #[cfg(debug_assertions)]
-        eprintln!("Trying to skip",);
-        skip_words.push(word);
-        // Proceed to next, skipping current
-        if decrypt_words(
-            &words[1..],
-            scratch,
-            key,
-            chars_set,
-            dict,
-            skip_words,
-            can_skip - 1,
-        )
-        .is_ok()
-        {
-            return Ok(());
-        }
-        skip_words.pop();
-        #[cfg(debug_assertions)]
-        eprintln!("Failed, backtracking");
+/// Options for [`encrypt_with`], created with a builder style API.
+///
+/// ```
+/// let options = substitution::EncryptOptions::new().seed(42).group(5);
+/// let ciphertext = substitution::encrypt_with("Hello world!", &options);
+/// assert_eq!(ciphertext.len(), 11);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EncryptOptions {
+    seed: Option<u64>,
+    key: Option<Key>,
+    group: Option<usize>,
+    case: Case,
+    normalizer: Option<normalize::Normalizer>,
+    alphabet: Alphabet,
+}
+
+impl EncryptOptions {
+    /// Options for encrypting with a random key into lowercase text, keeping word boundaries
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    // Clear set characters so that caller up in the stack can keep iterating it's key
-    free_chars.iter().for_each(|c| chars_set.remove(*c - START));
+    /// Generate the key from `seed`, so that the same input always encrypts to the same ciphertext
+    /// with the same version of this library
+    #[must_use]
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
 
-    Err(())
-}
+    /// Write the ciphertext in groups of `size` letters, hiding the word boundaries
+    #[must_use]
+    pub fn group(mut self, size: usize) -> Self {
+        self.group = (size > 0).then_some(size);
+        self
+    }
 
-/// Read through a dictionary file and insert every word in a trie set
-fn load_dict(from: impl BufRead) -> Result<trie::Set<R, { START as usize }>, std::io::Error> {
-    let mut dict = trie::Set::<R, { START as usize }>::new();
-    for line in from.lines() {
-        let bytes = filter_input(&line?);
-        for word in bytes
-            .split(u8::is_ascii_whitespace)
-            .filter(|w| !w.is_empty())
-        {
-            dict.insert(word).unwrap();
-        }
+    /// Write the ciphertext in uppercase letters, or in lowercase ones if `uppercase` is false
+    #[must_use]
+    pub fn uppercase(self, uppercase: bool) -> Self {
+        self.case(if uppercase { Case::Upper } else { Case::Lower })
+    }
+
+    /// Write the ciphertext in `case` instead of lowercase
+    ///
+    /// ```
+    /// use substitution::{Case, EncryptOptions, Key};
+    ///
+    /// let key: Key = "bcdefghijklmnopqrstuvwxyza".parse().unwrap();
+    /// let options = EncryptOptions::new().key(key).case(Case::MatchInput);
+    /// assert_eq!(substitution::encrypt_with("Hello, World!", &options), "Ifmmp Xpsme");
+    /// ```
+    #[must_use]
+    pub fn case(mut self, case: Case) -> Self {
+        self.case = case;
+        self
+    }
+
+    /// Prepare the input with `normalizer` instead of the default one. Anything else than letters
+    /// of the alphabet and ASCII whitespace which it leaves in is still left out.
+    #[must_use]
+    pub fn normalizer(mut self, normalizer: normalize::Normalizer) -> Self {
+        self.normalizer = Some(normalizer);
+        self
+    }
+
+    /// Encrypt the letters of `alphabet` instead of a to z
+    ///
+    /// ```
+    /// use substitution::{Alphabet, EncryptOptions};
+    ///
+    /// let options = EncryptOptions::new().alphabet(Alphabet::finnish());
+    /// let ciphertext = substitution::encrypt_with("Hyvää yötä!", &options);
+    /// assert_eq!(ciphertext.chars().count(), 10);
+    /// ```
+    #[must_use]
+    pub fn alphabet(mut self, alphabet: Alphabet) -> Self {
+        self.alphabet = alphabet;
+        self
+    }
+
+    /// Encrypt with `key` instead of a random one, in the key's alphabet. Overrides
+    /// [`EncryptOptions::seed`] and [`EncryptOptions::alphabet`], for ciphertext which is known
+    /// in advance, like test fixtures.
+    ///
+    /// ```
+    /// use substitution::{EncryptOptions, Key};
+    ///
+    /// let key: Key = "bcdefghijklmnopqrstuvwxyza".parse().unwrap();
+    /// let options = EncryptOptions::new().key(key).group(3).uppercase(true);
+    /// assert_eq!(substitution::encrypt_with("Hello world!", &options), "IFM MPX PSM E");
+    /// ```
+    #[must_use]
+    pub fn key(mut self, key: Key) -> Self {
+        self.alphabet = key.alphabet.clone();
+        self.key = Some(key);
+        self
     }
-    Ok(dict)
 }
 
-static ENGLISH_FREQ_ORDER: [u8; R] = [
-    b'e', b't', b'a', b'o', b'n', b'i', b'h', b's', b'r', b'd', b'l', b'u', b'w', b'm', b'c', b'f',
-    b'g', b'y', b'p', b'b', b'k', b'v', b'j', b'x', b'q', b'z',
-];
+/// Encrypts the string provided from CLI with a randomly generated substitution cipher.
+#[must_use]
+pub fn encrypt(input: &str) -> String {
+    encrypt_with(input, &EncryptOptions::default())
+}
 
-/// Deciphers the string `input` using brute force, statistics about english language and given dictionary `dict`.
+/// Encrypts `input` with a key generated by `rng`, normalizing it like [`encrypt`]. A seeded
+/// generator makes the encryption reproducible.
 ///
-/// # Errors
+/// ```
+/// use rand::{rngs::StdRng, SeedableRng};
 ///
-/// See [`enum@Error`].
-pub fn decrypt(input: &str, dict: impl BufRead) -> Result<String, Error> {
-    // Create a dictionary of valid words
-    let dict = load_dict(dict)?;
+/// let first = substitution::encrypt_with_rng("Hello world!", &mut StdRng::seed_from_u64(7));
+/// let second = substitution::encrypt_with_rng("Hello world!", &mut StdRng::seed_from_u64(7));
+/// assert_eq!(first, second);
+/// ```
+#[must_use]
+pub fn encrypt_with_rng(input: &str, rng: &mut impl Rng) -> String {
+    encrypt_with_key(input, &Key::random_with(rng))
+}
 
-    // Create a list of input words
-    let mut input = filter_input(input);
-    let words: Vec<&[u8]> = input
-        .split(u8::is_ascii_whitespace)
-        .filter(|word| !word.is_empty())
-        .collect();
+/// Encrypts `input` with a substitution cipher, configured by `options`.
+#[must_use]
+pub fn encrypt_with(input: &str, options: &EncryptOptions) -> String {
+    let alphabet = &options.alphabet;
+    let original = input;
+    let mut input = filter_input_with(input, options.normalizer.as_ref(), alphabet);
 
-    // Associate each input word with it's number of unique characters and sort by distance from the sweet spot
-    let mut words: Vec<(&[u8], usize)> = words
-        .iter()
-        .map(|word| (*word, unique_chars(word).len()))
-        .collect();
-    words.sort_unstable_by_key(|(_, len)| len.abs_diff(7));
-
-    // Clean up the words array again, now in descending length order
-    let words: Vec<_> = words.iter().map(|(word, _)| *word).collect();
-
-    // Create a key for deciphering
-    let mut key = Key::new(&input, ENGLISH_FREQ_ORDER);
-
-    // Allocate support structures for decryption
-    let mut scratch = vec![0; input.len()];
-    let mut chars_set = bitset::BitSet64::<1>::new();
-    let can_skip = words.len() / 10;
-    let mut skip_words = Vec::with_capacity(can_skip);
-    #[cfg(debug_assertions)]
-    eprintln!("Can skip {can_skip} words");
-
-    // Recursive deciphering
-    match decrypt_words(
-        &words,
-        &mut scratch,
-        &mut key,
-        &mut chars_set,
-        &dict,
-        &mut skip_words,
-        can_skip,
-    ) {
-        Ok(()) => {
-            key.translate(&mut input);
-            Ok(String::from_utf8(input).unwrap())
+    // Create a random substitution, unless the key is given
+    let key = match (&options.key, options.seed) {
+        (Some(key), _) => key.clone(),
+        (None, Some(seed)) => Key::random_in(alphabet, &mut StdRng::seed_from_u64(seed)),
+        (None, None) => Key::random_in(alphabet, &mut rand::thread_rng()),
+    };
+
+    // Encrypt
+    key.translate(&mut input);
+
+    // Regroup the letters if requested
+    if let Some(size) = options.group {
+        let letters: Vec<u8> = input
+            .into_iter()
+            .filter(|c| Alphabet::is_code(*c))
+            .collect();
+        input = letters.chunks(size).collect::<Vec<_>>().join(&b' ');
+    }
+
+    options
+        .case
+        .apply(&alphabet.decode(&input), original, alphabet)
+}
+
+/// Partial keys which beam search keeps by default, see [`SolverConfig::beam_width`]
+const DEFAULT_BEAM_WIDTH: usize = 100;
+
+/// Most that skipped words may cost in total by default, see [`SolverConfig::max_skip_cost`]
+const DEFAULT_MAX_SKIP_COST: usize = 3;
+
+/// Configuration for [`decrypt_with_config`], created with a builder style API.
+///
+/// ```
+/// let config = substitution::SolverConfig::new().proper_nouns(false);
+/// ```
+#[derive(Debug, Clone)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct SolverConfig {
+    proper_nouns: bool,
+    lenient_dictionary: bool,
+    keep_formatting: bool,
+    case: Option<Case>,
+    word_order_target: Option<usize>,
+    word_ordering: WordOrdering,
+    objective: Objective,
+    skipping: bool,
+    max_skip_cost: Option<usize>,
+    max_skip_ratio: Option<f64>,
+    min_word_len: usize,
+    short_word_len: usize,
+    normalizer: Option<normalize::Normalizer>,
+    alphabet: Alphabet,
+    language: Language,
+    frequency_order: Option<String>,
+    pins: Vec<(char, char)>,
+    cribs: Vec<String>,
+    fallback: bool,
+    anytime: bool,
+    algorithm: Algorithm,
+    beam_width: usize,
+    beam_width_bounds: Option<(usize, usize)>,
+    strategy: Option<std::sync::Arc<dyn Strategy>>,
+    quadgrams: Option<std::sync::Arc<Quadgrams>>,
+    max_duration: Option<Duration>,
+    max_nodes: Option<usize>,
+    max_backtracks: Option<usize>,
+    max_words: Option<usize>,
+    cancel: Option<CancelToken>,
+    progress: Option<solver::ProgressHook>,
+    metadata: Metadata,
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        Self {
+            proper_nouns: true,
+            lenient_dictionary: false,
+            keep_formatting: false,
+            case: None,
+            word_order_target: None,
+            word_ordering: WordOrdering::default(),
+            objective: Objective::default(),
+            skipping: true,
+            max_skip_cost: None,
+            max_skip_ratio: None,
+            min_word_len: 0,
+            short_word_len: SHORT_WORD_LEN,
+            normalizer: None,
+            alphabet: Alphabet::english(),
+            language: Language::English,
+            frequency_order: None,
+            pins: Vec::new(),
+            cribs: Vec::new(),
+            fallback: false,
+            anytime: false,
+            algorithm: Algorithm::Backtracking,
+            beam_width: DEFAULT_BEAM_WIDTH,
+            beam_width_bounds: None,
+            strategy: None,
+            quadgrams: None,
+            max_duration: None,
+            max_nodes: None,
+            max_backtracks: None,
+            max_words: None,
+            cancel: None,
+            progress: None,
+            metadata: Metadata::new(),
         }
-        Err(()) => Err(Error::SearchExhausted),
     }
 }
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use std::collections::HashMap;
-    use std::hash::Hash;
+impl SolverConfig {
+    /// Create the default configuration
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-    #[test]
-    fn filter_input_keeps_ascii_alphabetic_and_whitespace() {
-        assert_eq!(filter_input("hello, world! 😊".into()), b"hello world ");
+    /// Let words which look like names be left out of the dictionary for free, see
+    /// [`proper_nouns`]. Enabled by default.
+    #[must_use]
+    pub fn proper_nouns(mut self, enabled: bool) -> Self {
+        self.proper_nouns = enabled;
+        self
+    }
+
+    /// Leave out dictionary lines which can't be decoded instead of failing, see
+    /// [`check_dictionary`]. Disabled by default.
+    #[must_use]
+    pub fn lenient_dictionary(mut self, enabled: bool) -> Self {
+        self.lenient_dictionary = enabled;
+        self
+    }
+
+    /// Decipher the original text with the solved key, keeping its case, punctuation and spacing,
+    /// instead of returning normalized plaintext. Disabled by default.
+    ///
+    /// ```
+    /// let dict = "hello\nworld\nthe\nend\n".as_bytes();
+    /// let ciphertext = "Ifmmp, Xpsme! Uif foe.";
+    /// let config = substitution::SolverConfig::new().keep_formatting(true);
+    /// let plaintext = substitution::decrypt_with_config(ciphertext, dict, &config).unwrap();
+    /// assert_eq!(plaintext, "Hello, World! The end.");
+    /// ```
+    #[must_use]
+    pub fn keep_formatting(mut self, enabled: bool) -> Self {
+        self.keep_formatting = enabled;
+        self
+    }
+
+    /// Write deciphered letters in `case`, instead of lowercase or with
+    /// [`SolverConfig::keep_formatting`], in the case of the input.
+    ///
+    /// ```
+    /// use substitution::{Case, SolverConfig};
+    ///
+    /// let dict = "hello\nworld\n".as_bytes();
+    /// let config = SolverConfig::new().case(Case::MatchInput);
+    /// let plaintext = substitution::decrypt_with_config("Ifmmp, XPSME!", dict, &config).unwrap();
+    /// assert_eq!(plaintext, "Hello WORLD");
+    /// ```
+    #[must_use]
+    pub fn case(mut self, case: Case) -> Self {
+        self.case = Some(case);
+        self
+    }
+
+    /// Decipher words with about `distinct_letters` different letters first, instead of using the
+    /// tuned target for the input's number of words. Mainly for tuning the solver.
+    #[must_use]
+    pub fn word_order_target(mut self, distinct_letters: usize) -> Self {
+        self.word_order_target = Some(distinct_letters);
+        self
+    }
+
+    /// Decipher words in `ordering`, [`WordOrdering::Overlap`] by default
+    ///
+    /// ```
+    /// use substitution::{SolverConfig, WordOrdering};
+    ///
+    /// let config = SolverConfig::new().word_ordering(WordOrdering::Target);
+    /// ```
+    #[must_use]
+    pub fn word_ordering(mut self, ordering: WordOrdering) -> Self {
+        self.word_ordering = ordering;
+        self
+    }
+
+    /// Search for the key which makes the most of `objective`, [`Objective::AllWords`] by default
+    #[must_use]
+    pub fn objective(mut self, objective: Objective) -> Self {
+        self.objective = objective;
+        self
+    }
+
+    /// Prepare the input with `normalizer` instead of the default one. Anything else than letters
+    /// of the alphabet and ASCII whitespace which it leaves in is still left out. The dictionary
+    /// is always loaded with the default normalizer.
+    ///
+    /// ```
+    /// use substitution::normalize::{Normalizer, SpellDigits};
+    /// use substitution::{EncryptOptions, SolverConfig};
+    ///
+    /// let normalizer = Normalizer::builder().stage(SpellDigits).build();
+    /// let options = EncryptOptions::new().normalizer(normalizer.clone());
+    /// let ciphertext = substitution::encrypt_with("Two 2", &options);
+    ///
+    /// let dict = "two\n".as_bytes();
+    /// let config = SolverConfig::new().normalizer(normalizer);
+    /// let plaintext = substitution::decrypt_with_config(&ciphertext, dict, &config).unwrap();
+    /// assert_eq!(plaintext, "two two");
+    /// ```
+    #[must_use]
+    pub fn normalizer(mut self, normalizer: normalize::Normalizer) -> Self {
+        self.normalizer = Some(normalizer);
+        self
+    }
+
+    /// Decipher the letters of `alphabet` instead of a to z. The dictionary is loaded in the same
+    /// alphabet, unless it's already loaded into a [`Dictionary`], which has its own.
+    ///
+    /// ```
+    /// use substitution::{Alphabet, EncryptOptions, SolverConfig};
+    ///
+    /// let options = EncryptOptions::new().alphabet(Alphabet::finnish());
+    /// let ciphertext = substitution::encrypt_with("Hyvää yötä", &options);
+    ///
+    /// let dict = "hyvää\nyötä\n".as_bytes();
+    /// let config = SolverConfig::new().alphabet(Alphabet::finnish());
+    /// let plaintext = substitution::decrypt_with_config(&ciphertext, dict, &config).unwrap();
+    /// assert_eq!(plaintext, "hyvää yötä");
+    /// ```
+    #[must_use]
+    pub fn alphabet(mut self, alphabet: Alphabet) -> Self {
+        self.alphabet = alphabet;
+        self
+    }
+
+    /// Guess letters in the frequency order of `language`, and decipher the letters of its
+    /// alphabet. English by default. Set another alphabet with [`SolverConfig::alphabet`] after
+    /// this if needed.
+    #[must_use]
+    pub fn language(mut self, language: Language) -> Self {
+        self.alphabet = language.alphabet();
+        self.language = language;
+        self
+    }
+
+    /// Guess letters in `order` instead of the language's frequency order, for example one
+    /// counted from texts of the same field as the ciphertext. `order` lists letters from the most
+    /// to the least common. Letters of the alphabet which it leaves out are guessed last, and
+    /// anything else than them is ignored.
+    ///
+    /// ```
+    /// let config = substitution::SolverConfig::new().frequency_order("etaoinshrdlu");
+    /// let dict = "hello\nworld\n".as_bytes();
+    /// let plaintext = substitution::decrypt_with_config("ifmmp xpsme", dict, &config).unwrap();
+    /// assert_eq!(plaintext, "hello world");
+    /// ```
+    #[must_use]
+    pub fn frequency_order(mut self, order: &str) -> Self {
+        self.frequency_order = Some(order.to_owned());
+        self
+    }
+
+    /// Decipher the ciphertext letter `cipher` as `plain` in every key which is tried, for
+    /// letters which are already known, like from solving by hand. Call again for each known
+    /// letter.
+    ///
+    /// ```
+    /// let config = substitution::SolverConfig::new().pin_letter('c', 'd');
+    /// let dict = "cat\nhat\n".as_bytes();
+    /// let plaintext = substitution::decrypt_with_config("dbu", dict, &config).unwrap();
+    /// assert_eq!(plaintext, "cat");
+    /// ```
+    #[must_use]
+    pub fn pin_letter(mut self, plain: char, cipher: char) -> Self {
+        self.pins.push((plain, cipher));
+        self
+    }
+
+    /// Require `word` to be in the plaintext, like a name which the text is known to mention.
+    /// The search places it on the ciphertext words of the same pattern, the most common ones
+    /// first, and deciphers the rest of the text around it. The words which it's placed on are
+    /// free to skip, so it doesn't have to be in the dictionary. A crib of many words is placed
+    /// word by word. Call again for each crib.
+    ///
+    /// ```
+    /// let config = substitution::SolverConfig::new().crib("alice");
+    /// let dict = "hello\nworld\n".as_bytes();
+    /// let plaintext = substitution::decrypt_with_config("ifmmp bmjdf", dict, &config).unwrap();
+    /// assert_eq!(plaintext, "hello alice");
+    /// ```
+    #[must_use]
+    pub fn crib(mut self, word: &str) -> Self {
+        self.cribs.push(word.to_owned());
+        self
+    }
+
+    /// Instead of failing with [`Error::SearchExhausted`], or running out of the time and work
+    /// budgets, decipher the input by matching the frequency ranks of its letters to the
+    /// language's, and mark the solution with [`Solution::fallback`]. The plaintext is rarely
+    /// right, but it's a starting point for solving by hand. Disabled by default.
+    ///
+    /// ```
+    /// let config = substitution::SolverConfig::new().fallback(true);
+    /// let solution = substitution::decrypt_full("qqq", "hello\n".as_bytes(), &config).unwrap();
+    /// assert!(solution.fallback);
+    /// assert_eq!(solution.plaintext, "eee");
+    /// ```
+    #[must_use]
+    pub fn fallback(mut self, enabled: bool) -> Self {
+        self.fallback = enabled;
+        self
+    }
+
+    /// Instead of failing with [`Error::SearchExhausted`], or running out of the time and work
+    /// budgets, decipher the input with the guesses which placed the most words in the
+    /// dictionary during the search, and mark the solution with [`Solution::partial`]. Letters
+    /// which the guesses don't cover are written as `_`. If no word was ever placed, the search
+    /// fails or falls back to [`SolverConfig::fallback`] as usual. Disabled by default.
+    ///
+    /// ```
+    /// let config = substitution::SolverConfig::new()
+    ///     .anytime(true)
+    ///     .max_backtracks(0);
+    /// let dict = "hello\nworld\n".as_bytes();
+    /// let solution = substitution::decrypt_full("ifmmp xpsme zzzzy", dict, &config).unwrap();
+    /// assert!(solution.partial);
+    /// assert_eq!(solution.matched_words, 2);
+    /// assert_eq!(solution.plaintext, "hello world _____");
+    /// ```
+    #[must_use]
+    pub fn anytime(mut self, enabled: bool) -> Self {
+        self.anytime = enabled;
+        self
+    }
+
+    /// Search for the key with `algorithm`, [`Algorithm::Backtracking`] by default
+    ///
+    /// ```
+    /// use substitution::{Algorithm, SolverConfig};
+    ///
+    /// let config = SolverConfig::new().algorithm(Algorithm::Annealing);
+    /// ```
+    #[must_use]
+    pub fn algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Keep the `width` best partial keys after each word in [`Algorithm::Beam`], 100 by
+    /// default. A wider beam misses the key less often but takes longer.
+    #[must_use]
+    pub fn beam_width(mut self, width: usize) -> Self {
+        self.beam_width = width;
+        self
+    }
+
+    /// With [`SolverConfig::max_duration`], let [`Algorithm::Beam`] set its width between `min`
+    /// and `max` after each word, keeping as many partial keys as it has time to extend for the
+    /// following words. The beam starts with [`SolverConfig::beam_width`] keys, and without a
+    /// time limit it keeps that width.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use substitution::{Algorithm, SolverConfig};
+    ///
+    /// let config = SolverConfig::new()
+    ///     .algorithm(Algorithm::Beam)
+    ///     .adaptive_beam_width(10, 10_000)
+    ///     .max_duration(Duration::from_secs(2));
+    /// ```
+    #[must_use]
+    pub fn adaptive_beam_width(mut self, min: usize, max: usize) -> Self {
+        let min = min.max(1);
+        self.beam_width_bounds = Some((min, max.max(min)));
+        self
+    }
+
+    /// Decipher with a [`Strategy`] of your own instead of the built-in algorithms. The
+    /// strategy is given this configuration, so it can use the other settings as it likes.
+    /// [`Solver`] doesn't use it, because it steps through the built-in algorithms.
+    #[must_use]
+    pub fn strategy(mut self, strategy: impl Strategy + 'static) -> Self {
+        self.strategy = Some(std::sync::Arc::new(strategy));
+        self
+    }
+
+    /// Score keys with `quadgrams` in the strategies which need them, like
+    /// [`Algorithm::Annealing`]. By default the quadgrams are counted from the dictionary, which
+    /// works worse than counting them from running text.
+    #[must_use]
+    pub fn quadgrams(mut self, quadgrams: Quadgrams) -> Self {
+        self.quadgrams = Some(std::sync::Arc::new(quadgrams));
+        self
+    }
+
+    /// Stop searching with [`Error::Timeout`] after about `duration`. No limit by default.
+    /// [`Algorithm::Beam`] gives up when the time is out before its last word.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use substitution::{Error, SolverConfig};
+    ///
+    /// let config = SolverConfig::new().max_duration(Duration::ZERO);
+    /// let result = substitution::decrypt_with_config("ifmmp xpsme", "hello\nworld\n".as_bytes(), &config);
+    /// assert!(matches!(result, Err(Error::Timeout)));
+    /// ```
+    #[must_use]
+    pub fn max_duration(mut self, duration: Duration) -> Self {
+        self.max_duration = Some(duration);
+        self
+    }
+
+    /// Search with the limits of [`crack`]: for up to 10 seconds unless
+    /// [`SolverConfig::max_duration`] is set already, and with [`SolverConfig::fallback`]
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// let config = substitution::SolverConfig::new().max_duration(Duration::from_secs(1));
+    /// let solution = substitution::decrypt_full("qqq", "hello\n".as_bytes(), &config.crack_limits()).unwrap();
+    /// assert!(solution.fallback);
+    /// ```
+    #[must_use]
+    pub fn crack_limits(mut self) -> Self {
+        self.max_duration = self.max_duration.or(Some(CRACK_TIMEOUT));
+        self.fallback = true;
+        self
+    }
+
+    /// Stop searching with [`Error::BudgetExceeded`] after testing about `nodes` guesses against
+    /// the dictionary. Unlike [`SolverConfig::max_duration`], the same input and configuration
+    /// always stop at the same point. No limit by default.
+    #[must_use]
+    pub fn max_nodes(mut self, nodes: usize) -> Self {
+        self.max_nodes = Some(nodes);
+        self
+    }
+
+    /// Stop searching with [`Error::BudgetExceeded`] after words have run out of guesses
+    /// `backtracks` times, making the search go back to earlier words. No limit by default.
+    #[must_use]
+    pub fn max_backtracks(mut self, backtracks: usize) -> Self {
+        self.max_backtracks = Some(backtracks);
+        self
+    }
+
+    /// Refuse inputs of more than `words` words with [`Error::InputTooLong`] before searching.
+    /// The search keeps the state of each word it's deciphering on the heap, so long inputs
+    /// can't overflow the call stack, but the limit bounds the memory and time that untrusted
+    /// input can take. No limit by default.
+    ///
+    /// ```
+    /// use substitution::{Error, SolverConfig};
+    ///
+    /// let config = SolverConfig::new().max_words(1);
+    /// let result = substitution::decrypt_with_config("ifmmp xpsme", "hello\nworld\n".as_bytes(), &config);
+    /// assert!(matches!(result, Err(Error::InputTooLong { words: 2, max: 1 })));
+    /// ```
+    #[must_use]
+    pub fn max_words(mut self, words: usize) -> Self {
+        self.max_words = Some(words);
+        self
+    }
+
+    /// Let words which the dictionary doesn't have be skipped. The search first looks for a key
+    /// which skips no words, and then for keys which skip words of a growing total cost, see
+    /// [`Objective`]. Each bigger cost is a new round of the search, so an input which has no key
+    /// takes as many times as long to give up on. The rounds stop at a cost of 3 by default, which
+    /// [`SolverConfig::max_skip_cost`] or [`SolverConfig::max_skip_ratio`] can raise.
+    /// Without skipping, every word has to be in the dictionary, except the ones which are free
+    /// to skip, like [`proper_nouns`]. Enabled by default.
+    ///
+    /// ```
+    /// let config = substitution::SolverConfig::new().skipping(false);
+    /// let dict = "hello\nworld\n".as_bytes();
+    /// assert!(substitution::decrypt_with_config("ifmmp xpsme zzzzy", dict, &config).is_err());
+    /// ```
+    #[must_use]
+    pub fn skipping(mut self, enabled: bool) -> Self {
+        self.skipping = enabled;
+        self
+    }
+
+    /// Skip words of at most a total cost of `cost`, with short words and words of rare patterns
+    /// costing 1 and other words 2 by default, see [`Objective`]. By default, words costing up to 3
+    /// may be skipped, and at most every word but one.
+    #[must_use]
+    pub fn max_skip_cost(mut self, cost: usize) -> Self {
+        self.max_skip_cost = Some(cost);
+        self
+    }
+
+    /// Skip words of at most `ratio` (0 to 1) of the cost of skipping every word, like
+    /// [`SolverConfig::max_skip_cost`] but scaling with the length of the input. Replaces the
+    /// default cost limit, unless a cost is given too.
+    ///
+    /// ```
+    /// let config = substitution::SolverConfig::new().max_skip_ratio(0.1);
+    /// ```
+    #[must_use]
+    pub fn max_skip_ratio(mut self, ratio: f64) -> Self {
+        self.max_skip_ratio = Some(ratio);
+        self
+    }
+
+    /// Count words of at most `letters` letters as short, which cost half as much to skip as
+    /// longer words with [`Objective::AllWords`]. Words of up to three letters are short by
+    /// default.
+    ///
+    /// ```
+    /// let config = substitution::SolverConfig::new().short_word_len(4);
+    /// ```
+    #[must_use]
+    pub fn short_word_len(mut self, letters: usize) -> Self {
+        self.short_word_len = letters;
+        self
+    }
+
+    /// Let words shorter than `letters` be skipped without using up the skip budget, so that only
+    /// the longer words have to be in the dictionary, unless every word is shorter. All words have
+    /// to match by default.
+    ///
+    /// ```
+    /// let config = substitution::SolverConfig::new().min_word_len(3).skipping(false);
+    /// let dict = "hello\nworld\n".as_bytes();
+    /// let plaintext = substitution::decrypt_with_config("ifmmp zz xpsme", dict, &config).unwrap();
+    /// assert!(plaintext.starts_with("hello "));
+    /// ```
+    #[must_use]
+    pub fn min_word_len(mut self, letters: usize) -> Self {
+        self.min_word_len = letters;
+        self
+    }
+
+    /// Biggest skip budget to search with when skipping every word costs `total_cost`
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    fn max_skip(&self, total_cost: usize) -> usize {
+        if !self.skipping {
+            return 0;
+        }
+        let mut max = total_cost.saturating_sub(1);
+        if self.max_skip_cost.is_none() && self.max_skip_ratio.is_none() {
+            max = max.min(DEFAULT_MAX_SKIP_COST);
+        }
+        if let Some(cost) = self.max_skip_cost {
+            max = max.min(cost);
+        }
+        if let Some(ratio) = self.max_skip_ratio {
+            max = max.min((total_cost as f64 * ratio.clamp(0., 1.)) as usize);
+        }
+        max
+    }
+
+    /// Stop searching with [`Error::Cancelled`] soon after `token` is cancelled, for example when
+    /// the user of an application leaves the view which the search was for. Searches which
+    /// share a token are all stopped.
+    #[must_use]
+    pub fn cancel_token(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Call `hook` with the progress of the search every now and then while searching, at most
+    /// ten times a second, and once more when the search ends. When searching on many threads,
+    /// each thread reports the progress of its part of the search.
+    ///
+    /// ```
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let best = Arc::new(Mutex::new(0));
+    /// let reported = Arc::clone(&best);
+    /// let config = substitution::SolverConfig::new().on_progress(move |progress| {
+    ///     *reported.lock().unwrap() = progress.best_words_placed;
+    /// });
+    /// ```
+    #[must_use]
+    pub fn on_progress(mut self, hook: impl Fn(&SearchProgress) + Send + Sync + 'static) -> Self {
+        self.progress = Some(solver::ProgressHook(std::sync::Arc::new(hook)));
+        self
+    }
+
+    /// Attach a label and notes to the key of the solution, like the puzzle which the input is
+    /// from
+    ///
+    /// ```
+    /// use substitution::{Metadata, SolverConfig};
+    ///
+    /// let config = SolverConfig::new().metadata(Metadata::new().label("puzzle 12"));
+    /// let solution = substitution::decrypt_full("ifmmp", "hello\n".as_bytes(), &config).unwrap();
+    /// assert_eq!(solution.key.metadata().label.as_deref(), Some("puzzle 12"));
+    /// ```
+    #[must_use]
+    pub fn metadata(mut self, metadata: Metadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Ciphertext letter codes of `alphabet` which are pinned, and their plaintext letter codes
+    fn pins(&self, alphabet: &Alphabet) -> Result<Vec<(u8, u8)>, Error> {
+        let mut pins: Vec<(u8, u8)> = Vec::with_capacity(self.pins.len());
+        for &(plain, cipher) in &self.pins {
+            let code = |letter: char| alphabet.code(letter).ok_or(Error::UnknownLetter(letter));
+            let pin = (code(cipher)?, code(plain)?);
+            if pins.iter().any(|(c, p)| (*c == pin.0) != (*p == pin.1)) {
+                return Err(Error::KeyConflict { cipher, plain });
+            }
+            pins.push(pin);
+        }
+        Ok(pins)
+    }
+
+    /// Letter frequencies to guess letters of `alphabet` by
+    fn language_freqs(&self, alphabet: &Alphabet) -> LanguageFreqs {
+        let order = self
+            .frequency_order
+            .as_deref()
+            .unwrap_or_else(|| self.language.frequency_order());
+        LanguageFreqs::new(order, &self.language, alphabet)
+    }
+
+    /// Returns true if words which look like names may be left out of the dictionary for free
+    #[must_use]
+    pub fn allows_proper_nouns(&self) -> bool {
+        self.proper_nouns
+    }
+
+    /// Words of `input` in `alphabet` which may be skipped without using up the skip budget
+    fn free_skips(&self, input: &str, alphabet: &Alphabet) -> Vec<String> {
+        if self.proper_nouns {
+            proper_nouns(input, alphabet)
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+/// Finds words which are capitalized in the middle of a sentence and never written in lowercase.
+/// They are likely names, which a dictionary doesn't have. Only the letters of `alphabet` count,
+/// and words are returned normalized to them.
+///
+/// Capitalization only survives in ciphertext which keeps the case of letters, like the output
+/// of [`encrypt_bytes`]. Words of a single letter and words in all caps are never included.
+///
+/// ```
+/// use substitution::{proper_nouns, Alphabet};
+///
+/// let text = "Me ajoimme Äänekoskelle asti.";
+/// assert_eq!(proper_nouns(text, &Alphabet::finnish()), ["äänekoskelle"]);
+/// ```
+#[must_use]
+pub fn proper_nouns(input: &str, alphabet: &Alphabet) -> Vec<String> {
+    let mut capitalized = Vec::new();
+    let mut lowercase = Vec::new();
+    let mut sentence_start = true;
+
+    for token in input.split_whitespace() {
+        let mut letters = token.chars().filter(|c| alphabet.contains(*c));
+        let list = match letters.next() {
+            Some(first) if first.is_lowercase() => Some(&mut lowercase),
+            Some(first)
+                if first.is_uppercase() && !sentence_start && letters.any(char::is_lowercase) =>
+            {
+                Some(&mut capitalized)
+            }
+            _ => None,
+        };
+        if let Some(list) = list {
+            list.extend(
+                alphabet
+                    .decode(&filter_input_with(token, None, alphabet))
+                    .split_whitespace()
+                    .filter(|word| word.chars().nth(1).is_some())
+                    .map(str::to_owned),
+            );
+        }
+        sentence_start = token
+            .trim_end_matches(['"', '\'', ')', ']'])
+            .ends_with(['.', '!', '?']);
+    }
+
+    let mut nouns: Vec<String> = Vec::new();
+    for word in capitalized {
+        if !lowercase.contains(&word) && !nouns.contains(&word) {
+            nouns.push(word);
+        }
+    }
+    nouns
+}
+
+/// Encrypts a byte string with a randomly generated substitution cipher.
+///
+/// Only ASCII letters are substituted, keeping their case. Every other byte is passed through
+/// untouched, including bytes which are not valid UTF-8.
+#[must_use]
+pub fn encrypt_bytes(input: &[u8]) -> Vec<u8> {
+    let mut output = input.to_vec();
+    Key::random().translate_ascii(&mut output);
+    output
+}
+
+/// Encrypts `input` with a known `key`. The input is normalized like in [`encrypt`], keeping the
+/// letters of the key's alphabet.
+#[must_use]
+pub fn encrypt_with_key(input: &str, key: &Key) -> String {
+    let mut input = filter_input_with(input, None, &key.alphabet);
+    key.translate(&mut input);
+    key.alphabet.decode(&input)
+}
+
+/// Deciphers `input` with a known `key`, undoing [`encrypt_with_key`]. The input is normalized
+/// like in [`decrypt`].
+#[must_use]
+pub fn decrypt_with_key(input: &str, key: &Key) -> String {
+    encrypt_with_key(input, &key.inverse())
+}
+
+/// How a key deciphers a plaintext letter compared to the right key, found by [`Key::confusion`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LetterConfusion {
+    /// Plaintext letter which the right key maps
+    pub plain: char,
+    /// Plaintext letter which the compared key deciphers the ciphertext letter of `plain` into,
+    /// or `None` if it doesn't map it
+    pub deciphered: Option<char>,
+}
+
+impl LetterConfusion {
+    /// Returns true if the compared key deciphers the letter right
+    #[must_use]
+    pub fn is_correct(&self) -> bool {
+        self.deciphered == Some(self.plain)
+    }
+}
+
+/// A letter of a manually supplied key which deciphers text mostly into words which a
+/// dictionary doesn't have, found by [`lint_key`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyLint {
+    /// Ciphertext letter
+    pub cipher: char,
+    /// Plaintext letter which the key deciphers `cipher` into
+    pub plain: char,
+    /// Number of deciphered words which have `plain`
+    pub words: usize,
+    /// Number of those words which aren't in the dictionary
+    pub unmatched: usize,
+    /// Plaintext letter which deciphers more words into the dictionary, when `cipher` is mapped to
+    /// it and the ciphertext letter of it to `plain` instead
+    pub suggestion: Option<char>,
+}
+
+impl std::fmt::Display for KeyLint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "'{}' deciphers to '{}', but {} of the {} words with it aren't in the dictionary",
+            self.cipher, self.plain, self.unmatched, self.words
+        )?;
+        if let Some(suggestion) = self.suggestion {
+            write!(f, ", did you mean '{suggestion}'?")?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks `key`, which encrypts plaintext into ciphertext like for [`decrypt_with_key`], against
+/// the ciphertext `input` and `dict`. Returns the mapped letters which decipher most of the words
+/// they appear in into words which `dict` doesn't have, which are likely transcription errors in
+/// the key. The letters which the most words don't match with come first.
+///
+/// ```
+/// use substitution::{lint_key, Dictionary, Key};
+///
+/// let dict = Dictionary::from_reader("hello\nworld\n".as_bytes()).unwrap();
+/// // Like "bcdefghijklmnopqrstuvwxyza", but 'l' and 'o' swapped by mistake
+/// let key: Key = "bcdefghijklpnomqrstuvwxyza".parse().unwrap();
+/// let lints = lint_key(&key, "Ifmmp, xpsme!", &dict).unwrap();
+/// assert_eq!(lints[0].cipher, 'm');
+/// assert_eq!(lints[0].plain, 'o');
+/// assert_eq!(lints[0].suggestion, Some('l'));
+/// ```
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidKey`] if `key` is in another alphabet than `dict`.
+pub fn lint_key(key: &Key, input: &str, dict: &Dictionary) -> Result<Vec<KeyLint>, Error> {
+    if key.alphabet() != &dict.alphabet {
+        return Err(Error::InvalidKey);
+    }
+    let alphabet = &dict.alphabet;
+    let filtered = filter_input_with(input, None, alphabet);
+    let words: Vec<&[u8]> = filtered
+        .split(u8::is_ascii_whitespace)
+        .filter(|word| !word.is_empty())
+        .collect();
+    // Whether each word, deciphered with a ciphertext to plaintext table, is in the dictionary
+    let in_dict = |table: &[u8; R]| -> Vec<bool> {
+        words
+            .iter()
+            .map(|word| {
+                let plain: Vec<u8> = word.iter().map(|&c| table[Key::index(c)]).collect();
+                !plain.contains(&0) && dict.has_word(&plain)
+            })
+            .collect()
+    };
+    let table = key.inverse().table;
+    let matched = in_dict(&table);
+    let total = matched.iter().filter(|&&m| m).count();
+
+    let mut lints = Vec::new();
+    for cipher in alphabet.codes() {
+        let plain = table[Key::index(cipher)];
+        if plain == 0 {
+            continue;
+        }
+        let (count, unmatched) = words
+            .iter()
+            .zip(&matched)
+            .filter(|(word, _)| word.contains(&cipher))
+            .fold((0, 0), |(count, unmatched), (_, &m)| {
+                (count + 1, unmatched + usize::from(!m))
+            });
+        if unmatched * 2 <= count {
+            continue;
+        }
+        // Swap the plaintext letters of `cipher` and of the ciphertext letter which deciphers
+        // into each other letter, and suggest the one which matches the most words
+        let suggestion = alphabet
+            .codes()
+            .filter(|&other| other != plain)
+            .map(|other| {
+                let mut swapped = table;
+                swapped[Key::index(cipher)] = other;
+                if let Some(i) = table.iter().position(|&p| p == other) {
+                    swapped[i] = plain;
+                }
+                let total = in_dict(&swapped).iter().filter(|&&m| m).count();
+                (total, std::cmp::Reverse(other))
+            })
+            .filter(|&(swapped_total, _)| swapped_total > total)
+            .max()
+            .and_then(|(_, std::cmp::Reverse(other))| alphabet.letter(other));
+        lints.push(KeyLint {
+            cipher: alphabet.letter(cipher).unwrap_or('_'),
+            plain: alphabet.letter(plain).unwrap_or('_'),
+            words: count,
+            unmatched,
+            suggestion,
+        });
+    }
+    lints.sort_by_key(|lint| std::cmp::Reverse(lint.unmatched));
+    Ok(lints)
+}
+
+/// Why a ciphertext word isn't deciphered into a dictionary word by a key, found by
+/// [`explain_word`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordExplanation {
+    /// The word deciphered with the key, with `_` for the letters which it doesn't map
+    pub deciphered: String,
+    /// True if the deciphered word is in the dictionary, so nothing blocks it
+    pub matched: bool,
+    /// Number of dictionary words of the word's [`Pattern`]. If there are none, no key can
+    /// decipher the word into a dictionary word.
+    pub candidates: usize,
+    /// Letters of the word whose plaintext letters block it, each of which alone could be
+    /// deciphered into another letter to make a dictionary word. If there are none but there are
+    /// candidates, more than one letter has to change.
+    pub blocking: Vec<BlockingLetter>,
+}
+
+/// A ciphertext letter whose plaintext letter keeps a word from being a dictionary word, see
+/// [`WordExplanation::blocking`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockingLetter {
+    /// Ciphertext letter
+    pub cipher: char,
+    /// Plaintext letter which the key deciphers `cipher` into, or `None` if it doesn't map it
+    pub plain: Option<char>,
+    /// Plaintext letters which make a dictionary word when `cipher` is deciphered into them
+    /// instead, and the ciphertext letter which was deciphered into them, if any, into `plain`
+    pub alternatives: Vec<char>,
+}
+
+impl std::fmt::Display for WordExplanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.matched {
+            return write!(f, "\"{}\" is in the dictionary", self.deciphered);
+        }
+        write!(f, "\"{}\" is not in the dictionary", self.deciphered)?;
+        if self.candidates == 0 {
+            return write!(f, ", which has no words of its pattern");
+        }
+        if self.blocking.is_empty() {
+            return write!(
+                f,
+                ", and more than one letter has to change to make one of the {} words of its \
+                 pattern",
+                self.candidates
+            );
+        }
+        for letter in &self.blocking {
+            let alternatives: Vec<String> = letter
+                .alternatives
+                .iter()
+                .map(|plain| format!("'{plain}'"))
+                .collect();
+            write!(
+                f,
+                "\n'{}' deciphered to '{}' blocks it, {} would unblock it",
+                letter.cipher,
+                letter.plain.unwrap_or('_'),
+                alternatives.join(" or ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Explains why `key`, which encrypts plaintext into ciphertext like for [`decrypt_with_key`],
+/// doesn't decipher the ciphertext `word` into a word which `dict` has. Finds the letters of the
+/// word whose plaintext letters block every dictionary word of its pattern, and the plaintext
+/// letters which would unblock it instead, keeping the other letters of the word as they are.
+///
+/// ```
+/// use substitution::{explain_word, Dictionary, Key};
+///
+/// let dict = Dictionary::from_reader("hello\nworld\n".as_bytes()).unwrap();
+/// // Like "bcdefghijklmnopqrstuvwxyza", but 'l' and 'x' swapped by mistake
+/// let key: Key = "bcdefghijklynopqrstuvwxmza".parse().unwrap();
+/// let explanation = explain_word("Ifmmp", &key, &dict).unwrap();
+/// assert_eq!(explanation.deciphered, "hexxo");
+/// assert!(!explanation.matched);
+/// assert_eq!(explanation.blocking[0].cipher, 'm');
+/// assert_eq!(explanation.blocking[0].alternatives, ['l']);
+/// ```
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidKey`] if `key` is in another alphabet than `dict`, and
+/// [`Error::UnsupportedInput`] if `word` isn't one word.
+pub fn explain_word(word: &str, key: &Key, dict: &Dictionary) -> Result<WordExplanation, Error> {
+    use source::WordSource;
+
+    if key.alphabet() != &dict.alphabet {
+        return Err(Error::InvalidKey);
+    }
+    let alphabet = &dict.alphabet;
+    let filtered = filter_input_with(word, None, alphabet);
+    let mut words = filtered
+        .split(u8::is_ascii_whitespace)
+        .filter(|word| !word.is_empty());
+    let (Some(word), None) = (words.next(), words.next()) else {
+        return Err(Error::UnsupportedInput);
+    };
+    let table = key.inverse().table;
+    let plain: Vec<u8> = word.iter().map(|&c| table[Key::index(c)]).collect();
+    let matched = !plain.contains(&0) && dict.has_word(&plain);
+    let pattern = Pattern::of_letters(word.iter().copied());
+    let mut explanation = WordExplanation {
+        deciphered: plain
+            .iter()
+            .map(|&p| alphabet.letter(p).unwrap_or('_'))
+            .collect(),
+        matched,
+        candidates: dict.pattern_count(&pattern),
+        blocking: Vec::new(),
+    };
+    if matched {
+        return Ok(explanation);
+    }
+
+    for freed in unique_chars(word) {
+        // Dictionary words of the pattern which keep the plaintext letters of the other letters of
+        // the word, and which decipher `freed` into a letter which none of them is deciphered into
+        let allow = |prefix: &[usize], next: usize| {
+            let (Some(&c), Ok(next)) = (word.get(prefix.len()), u8::try_from(next)) else {
+                return false;
+            };
+            if c != freed {
+                return table[Key::index(c)] == next;
+            }
+            match word[..prefix.len()]
+                .iter()
+                .position(|&earlier| earlier == c)
+            {
+                Some(idx) => prefix[idx] == usize::from(next),
+                None => !word
+                    .iter()
+                    .any(|&other| other != freed && table[Key::index(other)] == next),
+            }
+        };
+        let at = word.iter().position(|&c| c == freed).unwrap_or(0);
+        let current = table[Key::index(freed)];
+        let mut alternatives = Vec::new();
+        dict.for_each_allowed(&pattern, allow, |candidate| {
+            if let Some(letter) = alphabet
+                .letter(candidate[at])
+                .filter(|_| candidate[at] != current)
+            {
+                if !alternatives.contains(&letter) {
+                    alternatives.push(letter);
+                }
+            }
+        });
+        if !alternatives.is_empty() {
+            alternatives.sort_unstable();
+            explanation.blocking.push(BlockingLetter {
+                cipher: alphabet.letter(freed).unwrap_or('_'),
+                plain: alphabet.letter(current),
+                alternatives,
+            });
+        }
+    }
+    Ok(explanation)
+}
+
+/// Returns a list of all unique letter codes in input.
+fn unique_chars(input: &[u8]) -> Vec<u8> {
+    let mut uc = Vec::with_capacity(16);
+    let mut set = bitset::BitSet64::<4>::new();
+    for c in input {
+        if Alphabet::is_code(*c) && !set.contains(*c) {
+            uc.push(*c);
+            set.insert(*c);
+        }
+    }
+    uc
+}
+
+/// Words of at most this many letters are the cheapest to skip by default, see
+/// [`SolverConfig::short_word_len`]
+const SHORT_WORD_LEN: usize = 3;
+
+/// Words whose pattern at most this many dictionary words have are as cheap to skip as short words
+const RARE_PATTERN_WORDS: usize = 2;
+
+/// How much skipping a word costs from the skip budget, when `pattern_words` dictionary words
+/// have its pattern.
+/// Short words are more likely to be abbreviations, names or typos while long words carry a lot of
+/// information about the key, so skipping long words is made more expensive. A long word of a
+/// pattern which hardly any dictionary word has is likely a name or a word which the dictionary
+/// doesn't have, so it's as cheap to skip as a short word.
+fn skip_cost(word: &[u8], short_word_len: usize, pattern_words: usize) -> usize {
+    if word.len() <= short_word_len || pattern_words <= RARE_PATTERN_WORDS {
+        1
+    } else {
+        2
+    }
+}
+
+/// Returns the letter of a word which consists of only one repeated letter, if it's free
+fn uniform_free_char(word: &[u8], free_chars: &[u8]) -> Option<u8> {
+    match free_chars {
+        [chr] if word.iter().all(|c| c == chr) => Some(*chr),
+        _ => None,
+    }
+}
+
+/// Read through a dictionary file and insert every word, written in `alphabet`, in a trie set.
+///
+/// Lines which are not valid UTF-8 are an error, or when `lenient` is set, left out and returned.
+fn load_dict(
+    from: impl BufRead,
+    lenient: bool,
+    alphabet: &Alphabet,
+) -> Result<(trie::Set<R, { START as usize }>, Vec<SkippedLine>), Error> {
+    let mut dict = trie::Set::<R, { START as usize }>::new();
+    let mut skipped = Vec::new();
+    for (number, line) in (1..).zip(from.split(b'\n')) {
+        let line = match std::str::from_utf8(&line?) {
+            Ok(line) => filter_input_with(line, None, alphabet),
+            Err(_) if lenient => {
+                skipped.push(SkippedLine {
+                    line: number,
+                    problem: LineProblem::InvalidUtf8,
+                });
+                continue;
+            }
+            Err(e) => return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e).into()),
+        };
+        for word in line
+            .split(u8::is_ascii_whitespace)
+            .filter(|word| !word.is_empty())
+        {
+            dict.insert(word)?;
+        }
+    }
+    if dict.is_empty() {
+        return Err(Error::DictionaryEmpty);
+    }
+    dict.compact();
+    Ok((dict, skipped))
+}
+
+/// Reasons for leaving a line out of a leniently loaded dictionary
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LineProblem {
+    /// The line is not valid UTF-8
+    InvalidUtf8,
+}
+
+impl std::fmt::Display for LineProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidUtf8 => write!(f, "not valid UTF-8"),
+        }
+    }
+}
+
+/// A dictionary line which was left out when loading leniently
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedLine {
+    /// Line number, starting from 1
+    pub line: usize,
+    /// Why the line was left out
+    pub problem: LineProblem,
+}
+
+/// Words of a text which a [`Dictionary`] doesn't have, found by [`Dictionary::covers`]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("Dictionary doesn't have the words: {}", .words.join(", "))]
+pub struct MissingWords {
+    /// Missing words as normalized lowercase letters, once each in order of appearance
+    pub words: Vec<String>,
+}
+
+/// Loads `dict` leniently in `alphabet`, like [`decrypt_with_config`] does when
+/// [`SolverConfig::lenient_dictionary`] is set, and returns the lines which were left out.
+///
+/// ```
+/// use substitution::{check_dictionary, Alphabet};
+///
+/// let dict = "hyvää\nyötä\n".as_bytes();
+/// assert!(check_dictionary(dict, &Alphabet::finnish()).unwrap().is_empty());
+/// ```
+///
+/// # Errors
+///
+/// Returns [`Error::LoadDictionary`] if reading fails and [`Error::DictionaryEmpty`] if no words
+/// were found.
+pub fn check_dictionary(
+    dict: impl BufRead,
+    alphabet: &Alphabet,
+) -> Result<Vec<SkippedLine>, Error> {
+    Ok(load_dict(dict, true, alphabet)?.1)
+}
+
+/// Letter frequencies of a language, which guide the search for a key
+#[derive(Clone)]
+struct LanguageFreqs {
+    /// Letter codes of the alphabet from the most to the least common
+    order: [u8; R],
+    /// Per mille of words which start with each letter, by letter code
+    initial: [u16; R],
+}
+
+impl LanguageFreqs {
+    /// Letters of `alphabet` in `frequency_order`, and how often they start words in `language`.
+    /// Letters which `frequency_order` doesn't have are the least common ones in the order of the
+    /// alphabet, and letters which the language doesn't have never start a word. Anything else
+    /// than letters of the alphabet in `frequency_order` is ignored, and so are repeated letters.
+    fn new(frequency_order: &str, language: &Language, alphabet: &Alphabet) -> Self {
+        let mut order = [0; R];
+        let mut initial = [0; R];
+        let mut seen = bitset::BitSet64::<1>::new();
+        let mut known = Vec::with_capacity(alphabet.len());
+        for code in frequency_order.chars().filter_map(|c| alphabet.code(c)) {
+            if !seen.contains(code - START) {
+                seen.insert(code - START);
+                known.push(code);
+            }
+        }
+        let others = alphabet.codes().filter(|code| !seen.contains(code - START));
+        for (entry, code) in order.iter_mut().zip(known.iter().copied().chain(others)) {
+            *entry = code;
+        }
+        if let Some(initial_freq) = language.initial_frequencies() {
+            for (code, letter) in alphabet.codes().zip(alphabet.letters()) {
+                if let Some(idx) = u8::try_from(*letter).ok().filter(u8::is_ascii_lowercase) {
+                    initial[Key::index(code)] = initial_freq[usize::from(idx - START)];
+                }
+            }
+        }
+        Self { order, initial }
+    }
+}
+
+/// How far in frequency rank a first guess may be from the input character's rank
+const FIRST_GUESS_WINDOW: usize = 2;
+/// Cost of a first guess for each step of frequency rank away from the input character's rank,
+/// weighed against the difference in per mille of words started by the guess and the character
+const FIRST_GUESS_RANK_COST: usize = 30;
+
+/// Checks `input` for conditions that make deciphering it unreliable, before spending time on it.
+#[must_use]
+pub fn diagnose(input: &str) -> Vec<Warning> {
+    diagnose_in(input, &Language::English)
+}
+
+/// Checks `input` like [`diagnose`], comparing its letter frequencies to `language` if the
+/// letter frequencies of the language are known
+#[must_use]
+pub fn diagnose_in(input: &str, language: &Language) -> Vec<Warning> {
+    let input = filter_input(input);
+    let mut warnings = Vec::new();
+
+    let distinct_letters = unique_chars(&input).len();
+    let words: Vec<&[u8]> = input
+        .split(u8::is_ascii_whitespace)
+        .filter(|word| !word.is_empty())
+        .collect();
+    if distinct_letters < MIN_DISTINCT_LETTERS || words.len() < MIN_WORDS {
+        warnings.push(Warning::InputTooShort {
+            distinct_letters,
+            words: words.len(),
+        });
+    }
+
+    let overlong: Vec<usize> = words
+        .iter()
+        .map(|word| word.len())
+        .filter(|len| *len > MAX_WORD_LEN)
+        .collect();
+    if let Some(longest) = overlong.iter().max() {
+        warnings.push(Warning::OverlongWords {
+            words: overlong.len(),
+            longest: *longest,
+        });
+    }
+
+    if let Some(similarity) = frequency_similarity(&input, language)
+        .filter(|similarity| *similarity < MIN_FREQUENCY_SIMILARITY)
+    {
+        warnings.push(Warning::UnusualLetterFrequencies { similarity });
+    }
+
+    warnings
+}
+
+/// How similar the letter frequencies of filtered `input` are to `language` in percent, when its
+/// letters are matched with letters of the same frequency rank, or `None` if it has too few
+/// letters to tell or the letter frequencies of the language aren't known. This is one minus the
+/// total variation distance of the two distributions sorted from the most to the least common
+/// letter.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+fn frequency_similarity(input: &[u8], language: &Language) -> Option<usize> {
+    let mut counts = [0usize; ASCII_LETTERS];
+    for c in input.iter().filter(|c| Alphabet::is_code(**c)) {
+        counts[Key::index(*c)] += 1;
+    }
+    let letters: usize = counts.iter().sum();
+    let language = language.letter_frequencies()?;
+    if letters < MIN_FREQUENCY_LETTERS {
+        return None;
+    }
+    let shares = |counts: &[f64], total: f64| {
+        let mut shares: Vec<f64> = counts.iter().map(|count| count / total).collect();
+        shares.sort_by(|a, b| b.total_cmp(a));
+        shares
+    };
+    let input = shares(&counts.map(|count| count as f64), letters as f64);
+    let language = language.map(f64::from);
+    let language = shares(&language, language.iter().sum());
+    let distance: f64 = input
+        .iter()
+        .zip(&language)
+        .map(|(a, b)| (a - b).abs())
+        .sum::<f64>()
+        / 2.;
+    Some(((1. - distance) * 100.).round() as usize)
+}
+
+/// Size of a decryption job, found out without searching for the key
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Estimate {
+    /// Number of words in the normalized input
+    pub words: usize,
+    /// Number of distinct letters in the input
+    pub distinct_letters: usize,
+    /// Number of distinct words in the dictionary
+    pub dictionary_words: usize,
+    /// Base 10 logarithm of the number of keys which assign a letter to every distinct input
+    /// letter. The search prunes most of them, so this is an upper bound.
+    pub keys_log10: f64,
+    /// Maximum number of searches with a growing budget for skipping unknown words
+    pub rounds: usize,
+    /// Conditions which make deciphering unreliable, see [`diagnose`]
+    pub warnings: Vec<Warning>,
+}
+
+/// Normalizes `input`, loads `dict` and checks `config` like [`decrypt_full`] does, and reports
+/// how large the search would be without running it. The alphabet, skip budget and cribs of
+/// `config` count, but quadgrams aren't counted from the dictionary.
+///
+/// # Errors
+///
+/// Returns the same dictionary, input and configuration errors as [`decrypt_full`].
+#[allow(clippy::cast_precision_loss)]
+pub fn estimate(input: &str, dict: impl BufRead, config: &SolverConfig) -> Result<Estimate, Error> {
+    let (dict, _) = load_dict(dict, config.lenient_dictionary, &config.alphabet)?;
+    let dict = Dictionary::new(dict, config.alphabet.clone());
+    let solver = Solver::with_algorithm(&dict, input, config, Algorithm::Backtracking)?;
+    let filtered = solver.filtered();
+    let distinct_letters = unique_chars(filtered).len();
+    Ok(Estimate {
+        words: filtered
+            .split(u8::is_ascii_whitespace)
+            .filter(|word| !word.is_empty())
+            .count(),
+        distinct_letters,
+        dictionary_words: dict.len(),
+        keys_log10: (0..distinct_letters)
+            .map(|assigned| dict.alphabet.len().saturating_sub(assigned) as f64)
+            .map(f64::log10)
+            .sum(),
+        rounds: solver.rounds(),
+        warnings: diagnose_in(input, &config.language),
+    })
+}
+
+/// Texts where whitespace separated tokens are this long on average are probably not split into words
+const MIN_UNSEPARATED_TOKEN_LEN: usize = 16;
+/// Words between the occurrences of a letter that acts as a word separator are at least this many
+/// letters long on average, times two
+const MIN_SEPARATED_WORD_LEN_X2: usize = 5;
+/// Words between the occurrences of a letter that acts as a word separator are at most this many
+/// letters long on average
+const MAX_SEPARATED_WORD_LEN: usize = 9;
+
+/// Guesses which character separates words in `input` when it is not split by whitespace, like
+/// `.` in `ifmmp.xpsme` or `X` in `IFMMPXXPSME`.
+///
+/// The most frequent punctuation symbol is preferred. A letter is only considered when there are
+/// no symbols, and if it never starts or ends the text, never appears twice in a row, and leaves
+/// words of plausible lengths between its occurrences. Of those letters, one which is written in
+/// a case that no other letter is, like `X` in `wjsXwsrzjsaXplrysv`, is picked. Otherwise the
+/// letter has to be more common than any other letter, or there is no separator.
+#[must_use]
+pub fn detect_separator(input: &str) -> Option<char> {
+    let tokens: Vec<&str> = input.split_whitespace().collect();
+    let letters = input.chars().filter(char::is_ascii_alphabetic).count();
+    if tokens.is_empty() || letters / tokens.len() < MIN_UNSEPARATED_TOKEN_LEN {
+        return None;
+    }
+
+    // Count symbols and letters separately
+    let mut symbols = [0; 128];
+    let mut counts = [0; ASCII_LETTERS];
+    for c in input.bytes() {
+        if c.is_ascii_punctuation() {
+            symbols[usize::from(c)] += 1;
+        } else if c.is_ascii_alphabetic() {
+            counts[usize::from(c.to_ascii_lowercase() - START)] += 1;
+        }
+    }
+
+    if let Some((symbol, _)) = (0..128u8)
+        .zip(symbols)
+        .filter(|(_, count)| *count > 0)
+        .max_by_key(|(_, count)| *count)
+    {
+        return Some(char::from(symbol));
+    }
+
+    // Letters whose occurrences split the text into words which could be real
+    let bytes: Vec<u8> = input.bytes().filter(u8::is_ascii_alphabetic).collect();
+    let separates = |letter: u8| {
+        let words: Vec<usize> = bytes
+            .split(|c| c.to_ascii_lowercase() == letter)
+            .map(<[u8]>::len)
+            .collect();
+        let word_letters = bytes.len() + 1 - words.len();
+        // An empty word is a separator at either end of the text or twice in a row
+        words.len() > 1
+            && words
+                .iter()
+                .all(|len| (1..MIN_UNSEPARATED_TOKEN_LEN).contains(len))
+            && word_letters * 2 >= words.len() * MIN_SEPARATED_WORD_LEN_X2
+            && word_letters <= words.len() * MAX_SEPARATED_WORD_LEN
+    };
+    let candidates: Vec<u8> = (START..=END)
+        .zip(counts)
+        .filter(|(letter, count)| *count > 0 && separates(*letter))
+        .map(|(letter, _)| letter)
+        .collect();
+
+    // A letter written in a case of its own stands out from the words
+    let set_apart = |letter: u8| {
+        let (own, other): (Vec<u8>, Vec<u8>) =
+            bytes.iter().partition(|c| c.to_ascii_lowercase() == letter);
+        [u8::is_ascii_uppercase, u8::is_ascii_lowercase]
+            .iter()
+            .any(|case| own.iter().all(case) && !other.iter().any(case))
+    };
+    let letter = if let Some(letter) = candidates.iter().find(|letter| set_apart(**letter)) {
+        *letter
+    } else {
+        // Otherwise only the one most common letter is confidently a separator
+        let count = |letter: u8| counts[usize::from(letter - START)];
+        let letter = *candidates.iter().max_by_key(|letter| count(**letter))?;
+        let ahead = (START..=END)
+            .filter(|other| *other != letter)
+            .all(|other| count(other) < count(letter));
+        ahead.then_some(letter)?
+    };
+    let cased = input.bytes().find(|c| c.to_ascii_lowercase() == letter)?;
+    Some(char::from(cased))
+}
+
+/// Replaces every occurrence of `separator` in `input` with a space, so that words can be told
+/// apart. Letters are replaced regardless of case.
+#[must_use]
+pub fn separate(input: &str, separator: char) -> String {
+    input
+        .chars()
+        .map(|c| {
+            if c.eq_ignore_ascii_case(&separator) {
+                ' '
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// How long [`crack`] searches by backtracking before annealing instead
+const CRACK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Deciphers the string `input` with sane defaults, for when a dictionary isn't at hand. The
+/// wordlist is looked for with [`find_dictionary`], and the key is searched for with
+/// [`Algorithm::Auto`] for up to 10 seconds before annealing instead. If nothing else works, the
+/// key is guessed from letter frequencies, see [`SolverConfig::fallback`].
+///
+/// Use [`crack_with`] to tell how the plaintext was found.
+///
+/// ```no_run
+/// let plaintext = substitution::crack("Ifmmp, xpsme!").unwrap();
+/// assert_eq!(plaintext, "hello world");
+/// ```
+///
+/// # Errors
+///
+/// Returns [`Error::DictionaryNotFound`] if there is no wordlist in the common locations, and
+/// [`Error::UnsupportedInput`] if `input` has no words to decipher. See [`enum@Error`] for the
+/// rest.
+pub fn crack(input: &str) -> Result<String, Error> {
+    let config = SolverConfig::new()
+        .algorithm(Algorithm::Auto)
+        .crack_limits();
+    crack_with(input, &config).map(|solution| solution.plaintext)
+}
+
+/// Deciphers the string `input` like [`decrypt_full`] with the wordlist which
+/// [`find_dictionary`] finds
+///
+/// # Errors
+///
+/// Returns [`Error::DictionaryNotFound`] if there is no wordlist in the common locations. See
+/// [`enum@Error`] for the rest.
+pub fn crack_with(input: &str, config: &SolverConfig) -> Result<Solution, Error> {
+    let path = find_dictionary()?;
+    let file = std::fs::File::open(path)?;
+    decrypt_full(input, std::io::BufReader::new(file), config)
+}
+
+/// Find a wordlist in the common locations, in order of preference:
+///
+/// - `/usr/share/dict/words` and the other system wordlists of Linux, BSDs, macOS and Homebrew
+/// - `substitution/words` in the user's data directory, like `~/.local/share` on Linux or
+///   `%APPDATA%` on Windows, where a wordlist can be placed by hand
+///
+/// No wordlist is embedded in the crate as a last resort, because one which solves typical texts
+/// costs about a megabyte.
+///
+/// # Errors
+///
+/// Returns [`Error::DictionaryNotFound`] with the locations which were looked in if none of
+/// them has a file.
+pub fn find_dictionary() -> Result<std::path::PathBuf, Error> {
+    let candidates = dictionary_candidates();
+    candidates
+        .iter()
+        .find(|path| path.is_file())
+        .cloned()
+        .ok_or(Error::DictionaryNotFound { tried: candidates })
+}
+
+/// Wordlist locations to look for, in order of preference
+fn dictionary_candidates() -> Vec<std::path::PathBuf> {
+    use std::path::PathBuf;
+
+    // System wordlists on Linux and BSDs, macOS and Homebrew
+    let mut paths: Vec<PathBuf> = [
+        "/usr/share/dict/words",
+        "/usr/dict/words",
+        "/opt/homebrew/share/dict/words",
+        "/usr/local/share/dict/words",
+    ]
+    .iter()
+    .map(PathBuf::from)
+    .collect();
+
+    // Per-user data directory, where a wordlist can be placed manually (e.g. on Windows)
+    let var = |name| std::env::var_os(name).map(PathBuf::from);
+    let data_dir = if cfg!(windows) {
+        var("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        var("HOME").map(|home| home.join("Library").join("Application Support"))
+    } else {
+        var("XDG_DATA_HOME").or_else(|| var("HOME").map(|home| home.join(".local").join("share")))
+    };
+    if let Some(dir) = data_dir {
+        paths.push(dir.join("substitution").join("words"));
+    }
+
+    paths
+}
+
+/// Deciphers the string `input` using brute force, statistics about english language and given dictionary `dict`.
+///
+/// # Errors
+///
+/// Returns [`Error::UnsupportedInput`] if `input` has no words to decipher, even if it is empty.
+/// See [`enum@Error`] for the rest.
+pub fn decrypt(input: &str, dict: impl BufRead) -> Result<String, Error> {
+    decrypt_with_config(input, dict, &SolverConfig::default())
+}
+
+/// Deciphers the string `input` like [`decrypt`], configured by `config`.
+///
+/// # Errors
+///
+/// See [`enum@Error`].
+pub fn decrypt_with_config(
+    input: &str,
+    dict: impl BufRead,
+    config: &SolverConfig,
+) -> Result<String, Error> {
+    decrypt_full(input, dict, config).map(|solution| solution.plaintext)
+}
+
+/// Deciphers the string `input` like [`decrypt`], but with the letter statistics and alphabet of
+/// `language` instead of English.
+///
+/// ```
+/// use substitution::Language;
+///
+/// let dict = "el\ngato\nnegro\ncome\n".as_bytes();
+/// let key: substitution::Key = "bcdefghijklmnopqrstuvwxyza".parse().unwrap();
+/// let ciphertext = key.encipher("el gato negro come");
+/// let plaintext = substitution::decrypt_with_language(&ciphertext, dict, Language::Spanish);
+/// assert_eq!(plaintext.unwrap(), "el gato negro come");
+/// ```
+///
+/// # Errors
+///
+/// See [`enum@Error`].
+pub fn decrypt_with_language(
+    input: &str,
+    dict: impl BufRead,
+    language: Language,
+) -> Result<String, Error> {
+    decrypt_with_config(input, dict, &SolverConfig::new().language(language))
+}
+
+/// Result of deciphering text without a key
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Solution {
+    /// The deciphered text, normalized unless [`SolverConfig::keep_formatting`] is enabled
+    pub plaintext: String,
+    /// The key which encrypts the plaintext into the ciphertext. Letters which the ciphertext
+    /// doesn't use are unmapped. It has the label and notes of [`SolverConfig::metadata`].
+    pub key: Key,
+    /// Share of the plaintext words which are in the dictionary, from 0 to 1. Less than 1 when
+    /// some words had to be skipped.
+    pub score: f64,
+    /// Number of plaintext words which are in the dictionary
+    pub matched_words: usize,
+    /// True if the search failed and the key was guessed from letter frequencies alone, see
+    /// [`SolverConfig::fallback`]. The plaintext has low confidence then.
+    pub fallback: bool,
+    /// True if the search failed and the key is the partial one which placed the most words, see
+    /// [`SolverConfig::anytime`]
+    pub partial: bool,
+    /// The built-in algorithm which found the key, or `None` if it was found by a custom
+    /// [`Strategy`]. With [`Algorithm::Auto`], this tells whether the key was found by
+    /// backtracking or annealing.
+    pub algorithm: Option<Algorithm>,
+    /// Distinct plaintext words which aren't in the dictionary, in the order they first occur.
+    /// These are the words which the search skipped, written with `_` for letters which a
+    /// partial key doesn't map.
+    pub skipped_words: Vec<String>,
+}
+
+impl Solution {
+    /// Decipher `input` with `key`, which encrypts plaintext into ciphertext like
+    /// [`Solution::key`], and score it by how many of the deciphered words `dict` has, like the
+    /// solutions which deciphering without a key gives. Letters which `key` doesn't map are
+    /// written as `_`. Meant for custom [`Strategy`] implementations.
+    ///
+    /// ```
+    /// use substitution::{Dictionary, Key, Solution, SolverConfig};
+    ///
+    /// let dict = Dictionary::from_reader("hello\nworld\n".as_bytes()).unwrap();
+    /// let key: Key = "bcdefghijklmnopqrstuvwxyza".parse().unwrap();
+    /// let solution = Solution::from_key("Ifmmp, xpsme!", &key, &dict, &SolverConfig::new()).unwrap();
+    /// assert_eq!(solution.plaintext, "hello world");
+    /// assert_eq!(solution.score, 1.);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnsupportedInput`] if `input` has no words to decipher, and
+    /// [`Error::InvalidKey`] if `key` is in another alphabet than `dict`.
+    pub fn from_key(
+        input: &str,
+        key: &Key,
+        dict: &Dictionary,
+        config: &SolverConfig,
+    ) -> Result<Self, Error> {
+        if key.alphabet() != &dict.alphabet {
+            return Err(Error::InvalidKey);
+        }
+        let solver = Solver::with_algorithm(dict, input, config, Algorithm::Backtracking)?;
+        Ok(Self {
+            algorithm: None,
+            ..solver.solution(&key.inverse(), true)
+        })
+    }
+}
+
+/// A key which deciphers the input into dictionary words, one of the many which a
+/// [`KeySearch`] or [`Dictionary::top_candidates`] finds, or the key of a [`Solution`] of any
+/// algorithm
+///
+/// Candidates are equal when their keys are, and ordered by score, so the best of a set of
+/// candidates is its greatest one. Candidates of the same score are ordered by their keys.
+///
+/// ```
+/// use std::collections::BTreeSet;
+/// use substitution::{Candidate, Dictionary, KeySearch, SolverConfig};
+///
+/// let dict = Dictionary::from_reader("cat\ncot\nhat\n".as_bytes()).unwrap();
+/// let config = SolverConfig::new();
+/// let mut candidates: BTreeSet<Candidate> =
+///     KeySearch::new("dpu ibu", &dict, &config).unwrap().collect();
+/// // The solution of the solver is one of the keys of the search
+/// let solution = dict.decrypt_full("dpu ibu", &config).unwrap();
+/// assert!(!candidates.insert(solution.into()));
+/// assert_eq!(candidates.last().unwrap().score, 1.);
+/// ```
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Candidate {
+    /// The deciphered text, like [`Solution::plaintext`]
+    pub plaintext: String,
+    /// The key which encrypts the plaintext into the ciphertext, like [`Solution::key`]
+    pub key: Key,
+    /// Share of the plaintext words which are in the dictionary, from 0 to 1, like
+    /// [`Solution::score`]
+    pub score: f64,
+    /// How well the key fits the dictionary and how long it took to find
+    pub stats: CandidateStats,
+}
+
+/// Counts of a [`Candidate`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CandidateStats {
+    /// Number of plaintext words which are in the dictionary
+    pub matched_words: usize,
+    /// Distinct plaintext words which aren't in the dictionary, like [`Solution::skipped_words`]
+    pub skipped_words: Vec<String>,
+    /// Guesses which the search had tested when it found the key, or 0 if the candidate is a
+    /// [`Solution`]
+    pub nodes: usize,
+}
+
+impl From<Solution> for Candidate {
+    fn from(solution: Solution) -> Self {
+        Self {
+            plaintext: solution.plaintext,
+            key: solution.key,
+            score: solution.score,
+            stats: CandidateStats {
+                matched_words: solution.matched_words,
+                skipped_words: solution.skipped_words,
+                nodes: 0,
+            },
+        }
+    }
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl Eq for Candidate {}
+
+impl std::hash::Hash for Candidate {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.key.hash(state);
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.score
+            .total_cmp(&other.score)
+            .then_with(|| self.key.table.cmp(&other.key.table))
+            .then_with(|| {
+                self.key
+                    .alphabet
+                    .letters()
+                    .cmp(other.key.alphabet.letters())
+            })
+    }
+}
+
+/// Deciphers the string `input` like [`decrypt_with_config`], but returns the key and score of
+/// the solution along with the plaintext.
+///
+/// # Errors
+///
+/// See [`enum@Error`].
+pub fn decrypt_full(
+    input: &str,
+    dict: impl BufRead,
+    config: &SolverConfig,
+) -> Result<Solution, Error> {
+    let (dict, _) = load_dict(dict, config.lenient_dictionary, &config.alphabet)?;
+    Dictionary::new(dict, config.alphabet.clone()).decrypt_full(input, config)
+}
+
+/// Deciphers the string `input` like [`decrypt`] with a dictionary which has already been
+/// loaded, so it can be reused for many inputs.
+///
+/// # Errors
+///
+/// See [`enum@Error`].
+pub fn decrypt_with(dict: &Dictionary, input: &str) -> Result<String, Error> {
+    dict.decrypt_full(input, &SolverConfig::default())
+        .map(|solution| solution.plaintext)
+}
+
+/// Words which deciphered text is checked against, loaded once and reusable for any number of
+/// inputs
+///
+/// Deciphering only reads the dictionary, without locks or interior mutability, so one
+/// dictionary can be shared by any number of threads deciphering at the same time, by reference
+/// or in an [`Arc`](std::sync::Arc).
+///
+/// ```
+/// use substitution::{Dictionary, SolverConfig};
+///
+/// let dict = Dictionary::from_reader("hello\nworld\n".as_bytes()).unwrap();
+/// std::thread::scope(|scope| {
+///     for input in ["ifmmp xpsme", "jgnnq yqtnf"] {
+///         let dict = &dict;
+///         scope.spawn(move || dict.decrypt_full(input, &SolverConfig::new()).unwrap());
+///     }
+/// });
+/// ```
+pub struct Dictionary {
+    words: trie::Set<R, { START as usize }>,
+    patterns: pattern::PatternIndex,
+    alphabet: Alphabet,
+}
+
+impl Dictionary {
+    /// Index loaded `words` of `alphabet`
+    fn new(words: trie::Set<R, { START as usize }>, alphabet: Alphabet) -> Self {
+        Self {
+            patterns: pattern::PatternIndex::new(&words),
+            words,
+            alphabet,
+        }
+    }
+
+    /// Load the words of a wordlist, normalized like the input of [`decrypt`]
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LoadDictionary`] if reading fails or a line is not valid UTF-8, and
+    /// [`Error::DictionaryEmpty`] if no words were found.
+    pub fn from_reader(reader: impl BufRead) -> Result<Self, Error> {
+        Self::from_reader_in(reader, &Alphabet::english())
+    }
+
+    /// Load the words of a wordlist like [`Dictionary::from_reader`], keeping the letters of
+    /// `alphabet`. Text which is deciphered with the dictionary is in the same alphabet.
+    ///
+    /// # Errors
+    ///
+    /// See [`Dictionary::from_reader`].
+    pub fn from_reader_in(reader: impl BufRead, alphabet: &Alphabet) -> Result<Self, Error> {
+        let (words, _) = load_dict(reader, false, alphabet)?;
+        Ok(Self::new(words, alphabet.clone()))
+    }
+
+    /// Load the words of a wordlist like [`Dictionary::from_reader_in`], but leave out lines
+    /// which are not valid UTF-8 and return them
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LoadDictionary`] if reading fails and [`Error::DictionaryEmpty`] if no
+    /// words were found.
+    pub fn from_reader_lenient(
+        reader: impl BufRead,
+        alphabet: &Alphabet,
+    ) -> Result<(Self, Vec<SkippedLine>), Error> {
+        let (words, skipped) = load_dict(reader, true, alphabet)?;
+        Ok((Self::new(words, alphabet.clone()), skipped))
+    }
+
+    /// The alphabet which the words are in
+    #[must_use]
+    pub fn alphabet(&self) -> &Alphabet {
+        &self.alphabet
+    }
+
+    /// Checks that every word of `text` is in the dictionary, so that a cryptogram of `text` can
+    /// be deciphered without skipping words. `text` is normalized like the input of [`decrypt`].
+    ///
+    /// Passing the check doesn't rule out another key which deciphers the cryptogram into
+    /// other dictionary words, which is more likely with short texts.
+    ///
+    /// ```
+    /// use substitution::Dictionary;
+    ///
+    /// let dict = Dictionary::from_reader("hello\nworld\n".as_bytes()).unwrap();
+    /// assert!(dict.covers("Hello, world!").is_ok());
+    /// assert_eq!(dict.covers("Hello, cruel world!").unwrap_err().words, ["cruel"]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MissingWords`] if some words are not in the dictionary.
+    pub fn covers(&self, text: &str) -> Result<(), MissingWords> {
+        let filtered = filter_input_with(text, None, &self.alphabet);
+        let mut words: Vec<String> = Vec::new();
+        for word in filtered
+            .split(u8::is_ascii_whitespace)
+            .filter(|word| !word.is_empty() && !self.has_word(word))
+        {
+            let word = self.alphabet.decode(word);
+            if !words.contains(&word) {
+                words.push(word);
+            }
+        }
+        if words.is_empty() {
+            Ok(())
+        } else {
+            Err(MissingWords { words })
+        }
+    }
+
+    /// Returns true if `word` is in the dictionary, normalized like the input of [`decrypt`], so
+    /// case and punctuation don't matter. Text of more or less than one word is never in it.
+    ///
+    /// ```
+    /// use substitution::Dictionary;
+    ///
+    /// let dict = Dictionary::from_reader("hello\ndon't\n".as_bytes()).unwrap();
+    /// assert!(dict.contains("Hello"));
+    /// assert!(dict.contains("DONT!"));
+    /// assert!(!dict.contains("hell"));
+    /// assert!(!dict.contains("hello hello"));
+    /// ```
+    #[must_use]
+    pub fn contains(&self, word: &str) -> bool {
+        let filtered = filter_input_with(word, None, &self.alphabet);
+        let mut words = filtered
+            .split(u8::is_ascii_whitespace)
+            .filter(|word| !word.is_empty());
+        match (words.next(), words.next()) {
+            (Some(word), None) => self.has_word(word),
+            _ => false,
+        }
+    }
+
+    /// Returns true if the letter codes of `word` are a whole word of the dictionary
+    pub(crate) fn has_word(&self, word: &[u8]) -> bool {
+        self.words
+            .prefix_score(word)
+            .is_ok_and(|score| score == word.len() + 1)
+    }
+
+    /// Number of distinct words
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    /// How many words share each letter [`Pattern`], most shared first. Ciphertext words of the
+    /// most shared patterns are the most ambiguous to decipher, so short texts made of them may
+    /// fit many keys.
+    ///
+    /// ```
+    /// use substitution::{pattern_of, Dictionary};
+    ///
+    /// let dict = Dictionary::from_reader("bar\ncar\nnoon\n".as_bytes()).unwrap();
+    /// assert_eq!(dict.pattern_histogram(), [(pattern_of("abc"), 2), (pattern_of("abba"), 1)]);
+    /// ```
+    #[must_use]
+    pub fn pattern_histogram(&self) -> Vec<(Pattern, usize)> {
+        self.patterns.counts()
+    }
+
+    /// Write the trie which the words are stored in as a Graphviz DOT graph, down to words of
+    /// `max_depth` letters. Edges are labeled with letters, words end at double circles, and
+    /// the longer words which are left out continue from dashed circles.
+    ///
+    /// ```
+    /// let dict = substitution::Dictionary::from_reader("a\nan\nat\n".as_bytes()).unwrap();
+    /// let mut dot = Vec::new();
+    /// dict.to_dot(&mut dot, 1).unwrap();
+    /// let dot = String::from_utf8(dot).unwrap();
+    /// assert!(dot.contains("n0 -> n1 [label=\"a\"]"));
+    /// assert!(dot.contains("n1 [shape=doublecircle, style=dashed]"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of `writer` if writing fails.
+    pub fn to_dot(&self, writer: impl std::io::Write, max_depth: usize) -> std::io::Result<()> {
+        self.words.to_dot(writer, max_depth, |code| {
+            u8::try_from(code)
+                .ok()
+                .and_then(|code| self.alphabet.letter(code))
+                .unwrap_or(char::REPLACEMENT_CHARACTER)
+        })
+    }
+
+    /// Returns true if there are no words, which loading never allows
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Deciphers the string `input` like [`decrypt_full`] with this dictionary. The
+    /// dictionary and alphabet settings of `config` don't apply because the words are already
+    /// loaded.
+    ///
+    /// # Errors
+    ///
+    /// See [`enum@Error`].
+    pub fn decrypt_full(&self, input: &str, config: &SolverConfig) -> Result<Solution, Error> {
+        match &config.strategy {
+            Some(strategy) => strategy.solve(input, self, config),
+            None => config.algorithm.solve(input, self, config),
+        }
+    }
+
+    /// Deciphers the string `input` by hill climbing instead of searching for a key which
+    /// deciphers every word into a dictionary word. Starting from the key which matches the
+    /// frequency ranks of letters to the language's, pairs of letters are swapped while that
+    /// makes the plaintext score better with `quadgrams`. This works for texts with many words
+    /// which the dictionary doesn't have, like names, but the key may be off by a few letters,
+    /// and it rarely works for texts shorter than a few sentences.
+    ///
+    /// The dictionary only scores the solution. Time and work budgets of `config` don't apply.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnsupportedInput`] if `input` has no words to decipher, and
+    /// [`Error::InvalidNgrams`] if `quadgrams` are in another alphabet than the dictionary.
+    pub fn hill_climb(
+        &self,
+        input: &str,
+        quadgrams: &Quadgrams,
+        config: &SolverConfig,
+    ) -> Result<Solution, Error> {
+        quadgrams.check_alphabet(&self.alphabet)?;
+        Ok(Solver::new(self, input, config)?.climb(quadgrams))
+    }
+
+    /// Picks up to `k` letters to give away as hints for solving `ciphertext`, which `key`
+    /// enciphers into it, as pairs of a ciphertext letter and its plaintext letter. Hints are
+    /// picked one at a time: the letter with which the search finds the plaintext in the fewest
+    /// guesses, given the hints picked before it, and of those the most common one.
+    ///
+    /// ```
+    /// use substitution::{Dictionary, Key};
+    ///
+    /// let dict = Dictionary::from_reader("dog\ncat\n".as_bytes()).unwrap();
+    /// let key: Key = "bcdefghijklmnopqrstuvwxyza".parse().unwrap();
+    /// let hints = dict.suggest_hints("dbu", &key, 1).unwrap();
+    /// // Any letter of "cat" tells it apart from "dog"
+    /// assert_eq!(hints.len(), 1);
+    /// assert_eq!(dict.suggest_hints("dbu", &key, 5).unwrap().len(), 3);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnsupportedInput`] if `ciphertext` has no words to decipher, and
+    /// [`Error::InvalidKey`] if `key` is in another alphabet than the dictionary.
+    pub fn suggest_hints(
+        &self,
+        ciphertext: &str,
+        key: &Key,
+        k: usize,
+    ) -> Result<Vec<(char, char)>, Error> {
+        let config = SolverConfig::new()
+            .alphabet(self.alphabet.clone())
+            .algorithm(Algorithm::Backtracking)
+            .max_nodes(HINT_NODES);
+        let plaintext = Solution::from_key(ciphertext, key, self, &config)?.plaintext;
+        let mut letters: Vec<(char, usize)> = Vec::new();
+        for c in plaintext.chars().filter(|c| self.alphabet.contains(*c)) {
+            match letters.iter_mut().find(|(letter, _)| *letter == c) {
+                Some((_, count)) => *count += 1,
+                None => letters.push((c, 1)),
+            }
+        }
+
+        // Guesses which the search needs to find the plaintext with the pins, or more than the
+        // budget if it doesn't
+        let guesses = |pins: &[(char, char)]| -> Result<usize, Error> {
+            let config = pins.iter().fold(config.clone(), |config, (cipher, plain)| {
+                config.pin_letter(*plain, *cipher)
+            });
+            let mut solver = Solver::new(self, ciphertext, &config)?;
+            Ok(match solver.step(Duration::MAX) {
+                StepResult::Solved(solution) if solution.plaintext == plaintext => solver.nodes(),
+                _ => HINT_NODES + 1,
+            })
+        };
+        let mut hints: Vec<(char, char)> = Vec::new();
+        while hints.len() < k && !letters.is_empty() {
+            let mut best = None;
+            for (i, (plain, count)) in letters.iter().enumerate() {
+                let cipher = key
+                    .encipher(&plain.to_string())
+                    .chars()
+                    .next()
+                    .unwrap_or(*plain);
+                hints.push((cipher, *plain));
+                let rank = (guesses(&hints)?, std::cmp::Reverse(*count));
+                hints.pop();
+                if best
+                    .as_ref()
+                    .is_none_or(|(_, best_rank, _)| rank < *best_rank)
+                {
+                    best = Some((i, rank, cipher));
+                }
+            }
+            let Some((i, _, cipher)) = best else {
+                break;
+            };
+            let (plain, _) = letters.remove(i);
+            hints.push((cipher, plain));
+        }
+        Ok(hints)
+    }
+
+    /// Finds the `k` keys which decipher `input` into dictionary words with the best scores, best
+    /// first. Every key which a [`KeySearch`] finds is ranked, within the time and work budgets
+    /// of `config`.
+    ///
+    /// ```
+    /// use substitution::{Dictionary, SolverConfig};
+    ///
+    /// let dict = Dictionary::from_reader("cat\ncot\nhat\n".as_bytes()).unwrap();
+    /// let best = dict.top_candidates("dpu ibu", &SolverConfig::new(), 3).unwrap();
+    /// // "cot hat" and "hat cot" place both words, the rest skip one
+    /// assert_eq!(best.len(), 3);
+    /// assert_eq!(best[1].score, 1.);
+    /// assert_eq!(best[2].score, 0.5);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`KeySearch::new`], and if no key was found,
+    /// [`Error::SearchExhausted`] or the error of the budget which ran out, see
+    /// [`KeySearch::error`].
+    pub fn top_candidates(
+        &self,
+        input: &str,
+        config: &SolverConfig,
+        k: usize,
+    ) -> Result<Vec<Candidate>, Error> {
+        let mut search = KeySearch::new(input, self, config)?;
+        if k == 0 {
+            return Ok(Vec::new());
+        }
+        let mut best = std::collections::BinaryHeap::new();
+        for candidate in search.by_ref() {
+            best.push(std::cmp::Reverse(candidate));
+            if best.len() > k {
+                best.pop();
+            }
+        }
+        if best.is_empty() {
+            return Err(search.take_error().unwrap_or(Error::SearchExhausted));
+        }
+        Ok(best
+            .into_sorted_vec()
+            .into_iter()
+            .map(|std::cmp::Reverse(candidate)| candidate)
+            .collect())
+    }
+}
+
+/// Guesses which [`Dictionary::suggest_hints`] lets each search with hints test, beyond which
+/// the hints don't help enough
+const HINT_NODES: usize = 20_000;
+
+// Fail to compile if something is added to the types of deciphering which stops sharing them
+// between threads, like a `Cell` or an `Rc`
+const _: () = {
+    const fn shared<T: Send + Sync>() {}
+    const fn sent<T: Send>() {}
+    shared::<Dictionary>();
+    shared::<trie::Set<R, { START as usize }>>();
+    shared::<Quadgrams>();
+    shared::<SolverConfig>();
+    shared::<Solution>();
+    shared::<Key>();
+    shared::<Alphabet>();
+    shared::<Language>();
+    shared::<CancelToken>();
+    sent::<Solver<'static>>();
+};
+
+/// Deciphers a byte string which has been encrypted with a substitution cipher, for example by
+/// [`encrypt_bytes`].
+///
+/// Only ASCII letters are substituted, keeping their case. Every other byte is passed through
+/// untouched, including bytes which are not valid UTF-8.
+///
+/// # Errors
+///
+/// See [`decrypt`].
+pub fn decrypt_bytes(input: &[u8], dict: impl BufRead) -> Result<Vec<u8>, Error> {
+    // Create a dictionary of valid words
+    let alphabet = Alphabet::english();
+    let (dict, _) = load_dict(dict, false, &alphabet)?;
+    let dict = Dictionary::new(dict, alphabet.clone());
+
+    // Solve the key from the letters and apply it to the original bytes
+    let text = String::from_utf8_lossy(input);
+    let free_skips = SolverConfig::default().free_skips(&text, &alphabet);
+    let key = solve(
+        &filter_input(&text),
+        &dict,
+        &alphabet,
+        &Language::English,
+        &free_skips,
+        None,
+    )?;
+    let mut output = input.to_vec();
+    key.translate_ascii(&mut output);
+    Ok(output)
+}
+
+/// Searches for a key which deciphers every word in filtered `input` into a dictionary word,
+/// allowing some words to be skipped. Letters are guessed in the frequency order of `language`.
+///
+/// Words in `free_skips` may be skipped without using up the skip budget, unless every word is one
+/// of them. Words are deciphered in order of how close their number of distinct letters is to
+/// `word_order_target`, or to the tuned target if it's `None`.
+fn solve(
+    input: &[u8],
+    dict: &Dictionary,
+    alphabet: &Alphabet,
+    language: &Language,
+    free_skips: &[String],
+    word_order_target: Option<usize>,
+) -> Result<Key, Error> {
+    let freqs = LanguageFreqs::new(language.frequency_order(), language, alphabet);
+    let mut search = solver::Search::new(
+        input,
+        alphabet,
+        freqs,
+        free_skips,
+        word_order_target,
+        WordOrdering::default(),
+        Objective::default(),
+    )?;
+    search.max_skip = search.weigh_skips(dict, SHORT_WORD_LEN).saturating_sub(1);
+    search.run(dict, None).unwrap_or(Err(Error::Timeout))
+}
+
+#[cfg(test)]
+// The tests from before these clippy lints are kept as they were written
+#[allow(
+    clippy::useless_conversion,
+    clippy::needless_pass_by_value,
+    clippy::unreadable_literal
+)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+    use std::hash::Hash;
+
+    #[test]
+    fn filter_input_keeps_ascii_alphabetic_and_whitespace() {
+        assert_eq!(filter_input("hello, world! 😊".into()), b"hello world ");
+    }
+
+    #[test]
+    fn filter_input_transforms_to_lowercase() {
+        assert_eq!(filter_input("Hello WORLD".into()), b"hello world");
+    }
+
+    #[test]
+    fn covers_reports_missing_words_once() {
+        let dict =
+            Dictionary::from_reader_in("hyvää\nyötä\n".as_bytes(), &Alphabet::finnish()).unwrap();
+        assert_eq!(dict.covers("Hyvää yötä!"), Ok(()));
+        let missing = dict.covers("Hyvää yötä, kuu! Yötä, kuu-ukko").unwrap_err();
+        assert_eq!(missing.words, ["kuu", "ukko"]);
+        assert_eq!(
+            missing.to_string(),
+            "Dictionary doesn't have the words: kuu, ukko"
+        );
+    }
+
+    #[test]
+    fn contains_normalizes_words_in_the_alphabet() {
+        let dict =
+            Dictionary::from_reader_in("hyvää\nyötä\n".as_bytes(), &Alphabet::finnish()).unwrap();
+        assert!(dict.contains("YÖTÄ"));
+        assert!(dict.contains(" hyvää,\n"));
+        assert!(!dict.contains("yt"));
+        assert!(!dict.contains(""));
+        let english = Dictionary::from_reader("yt\n".as_bytes()).unwrap();
+        assert!(english.contains("yötä"));
     }
 
     #[test]
-    fn filter_input_transforms_to_lowercase() {
-        assert_eq!(filter_input("Hello WORLD".into()), b"hello world");
+    fn threads_share_a_dictionary() {
+        let dict =
+            std::sync::Arc::new(Dictionary::from_reader(testgen::CORPUS.as_bytes()).unwrap());
+        let ciphertexts: Vec<String> = ["the old harbor", "the small school", "fresh fish"]
+            .iter()
+            .zip(1..)
+            .map(|(plaintext, seed)| {
+                Key::random_with(&mut StdRng::seed_from_u64(seed)).encipher(plaintext)
+            })
+            .collect();
+        let threads: Vec<_> = ciphertexts
+            .iter()
+            .map(|ciphertext| {
+                let dict = std::sync::Arc::clone(&dict);
+                let ciphertext = ciphertext.clone();
+                std::thread::spawn(move || dict.decrypt_full(&ciphertext, &SolverConfig::new()))
+            })
+            .collect();
+        for (thread, ciphertext) in threads.into_iter().zip(&ciphertexts) {
+            let alone = dict.decrypt_full(ciphertext, &SolverConfig::new()).unwrap();
+            assert_eq!(thread.join().unwrap().unwrap(), alone);
+        }
     }
 
     #[test]
@@ -479,6 +3461,82 @@ mod test {
         assert_eq!(out.len(), input.len() - 1);
     }
 
+    #[test]
+    fn encrypt_with_seed_is_reproducible() {
+        let options = EncryptOptions::new().seed(1234);
+        let first = encrypt_with("Hello world!", &options);
+        assert_eq!(first, encrypt_with("Hello world!", &options));
+        assert_eq!(first.len(), 11);
+    }
+
+    #[test]
+    fn encrypt_with_rng_matches_seed_option() {
+        let input = "Hello world!";
+        let seeded = encrypt_with(input, &EncryptOptions::new().seed(99));
+        assert_eq!(
+            encrypt_with_rng(input, &mut StdRng::seed_from_u64(99)),
+            seeded
+        );
+        assert_ne!(
+            encrypt_with_rng(input, &mut StdRng::seed_from_u64(100)),
+            seeded
+        );
+    }
+
+    #[test]
+    fn encrypt_with_groups_and_uppercase() {
+        let options = EncryptOptions::new().group(4).uppercase(true);
+        let out = encrypt_with("Moikka tiraprojekti!", &options);
+        let groups: Vec<&str> = out.split(' ').collect();
+        assert_eq!(
+            groups.iter().map(|g| g.len()).collect::<Vec<_>>(),
+            [4, 4, 4, 4, 2]
+        );
+        assert!(out.bytes().all(|c| c == b' ' || c.is_ascii_uppercase()));
+    }
+
+    #[test]
+    fn case_follows_the_letters_of_the_input() {
+        let alphabet = Alphabet::english();
+        let input = "Moikka, TIRA-projekti!";
+        assert_eq!(
+            Case::MatchInput.apply("moikka tira projekti", input, &alphabet),
+            "Moikka TIRA projekti"
+        );
+        assert_eq!(
+            Case::MatchInput.apply("moik kati rapr ojek ti", input, &alphabet),
+            "Moik kaTI RApr ojek ti"
+        );
+        assert_eq!(
+            Case::MatchInput.apply("m_ikka ____", "Moikka TIRA", &alphabet),
+            "M_ikka ____"
+        );
+        assert_eq!(Case::Upper.apply("moi", input, &alphabet), "MOI");
+        assert_eq!(Case::Lower.apply("MOI", input, &alphabet), "moi");
+
+        let key: Key = "bcdefghijklmnopqrstuvwxyza".parse().unwrap();
+        let options = EncryptOptions::new()
+            .key(key)
+            .group(4)
+            .case(Case::MatchInput);
+        assert_eq!(encrypt_with(input, &options), "Npjl lbUJ SBqs pkfl uj");
+    }
+
+    #[test]
+    fn encrypt_with_key_option_matches_encrypt_with_key() {
+        let finnish = Alphabet::finnish();
+        let key = Key::from_letters("öabcdefghijklmnopqrstuvwxyzåä", &finnish).unwrap();
+        let input = "Hyvää yötä, maailma!";
+        // The key's alphabet wins over the alphabet and seed options
+        let options = EncryptOptions::new().seed(1).key(key.clone());
+        let options = options.alphabet(Alphabet::english()).key(key.clone());
+        assert_eq!(encrypt_with(input, &options), encrypt_with_key(input, &key));
+        assert_eq!(
+            decrypt_with_key(&encrypt_with(input, &options), &key),
+            "hyvää yötä maailma"
+        );
+    }
+
     /// Counts how many times each possible value occurs in `of`.
     fn stats<'a, T: Eq + Hash>(stats: &mut HashMap<&'a T, usize>, of: impl Iterator<Item = &'a T>) {
         for c in of {
@@ -579,6 +3637,499 @@ mod test {
         assert_eq!(&decrypted, "hello all worlds");
     }
 
+    #[test]
+    fn decrypt_skips_unknown_word() {
+        let input: String = "Hello all, zz!".into();
+        let encrypted = encrypt(&input);
+        dbg!(&input);
+        dbg!(&encrypted);
+        let decrypted = decrypt(
+            &encrypted,
+            std::io::BufReader::new("hello\nworld\nall\n".as_bytes()),
+        )
+        .unwrap();
+        assert!(decrypted.starts_with("hello all "));
+    }
+
+    #[test]
+    fn proper_nouns_are_capitalized_mid_sentence() {
+        assert_eq!(
+            proper_nouns(
+                "Then Alice met Bob-Smith. Alice said (\"hi.\") Then I left, ALL done.",
+                &Alphabet::english()
+            ),
+            ["alice", "bob", "smith"]
+        );
+        assert_eq!(
+            proper_nouns("We saw Rose and a rose.", &Alphabet::english()),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn decrypt_skips_proper_noun_for_free() {
+        let dict = "the\nold\nman\nsat\nby\nsea\n";
+        let input = b"The old man Ahab sat by the sea.";
+        let encrypted = String::from_utf8(encrypt_bytes(input)).unwrap();
+        let decrypted = decrypt_with_config(&encrypted, dict.as_bytes(), &SolverConfig::new());
+        assert_eq!(decrypted.unwrap(), "the old man ahab sat by the sea");
+    }
+
+    #[test]
+    fn decrypt_skips_proper_noun_in_other_alphabet() {
+        let finnish = Alphabet::finnish();
+        let dict = "hyvää\nyötä\ntyö\n";
+        let config = SolverConfig::new()
+            .alphabet(finnish.clone())
+            .skipping(false);
+        for seed in 0..5 {
+            let key = Key::random_in(&finnish, &mut StdRng::seed_from_u64(seed));
+            let encrypted = key.encipher("Hyvää yötä, Åke, työ.");
+            let decrypted = decrypt_with_config(&encrypted, dict.as_bytes(), &config).unwrap();
+            assert!(decrypted.starts_with("hyvää yötä "));
+            assert!(decrypted.ends_with(" työ"));
+        }
+    }
+
+    #[test]
+    fn decrypt_full_returns_key_and_score() {
+        let key = Key::random();
+        let encrypted = encrypt_with_key("hello all zz", &key);
+        let dict = "hello\nworld\nall\n";
+        let solution = decrypt_full(&encrypted, dict.as_bytes(), &SolverConfig::new()).unwrap();
+        assert!(solution.plaintext.starts_with("hello all "));
+        assert!((solution.score - 2. / 3.).abs() < f64::EPSILON);
+        assert_eq!(
+            encrypt_with_key("hello all", &solution.key),
+            encrypted[.."hello all".len()]
+        );
+    }
+
+    #[test]
+    fn decrypt_keeps_formatting() {
+        let key = Key::random();
+        let plaintext = "Hello, World! It's all \"hello\" -- well...\n";
+        let config = SolverConfig::new().keep_formatting(true);
+        let dict = "hello\nworld\nits\nall\nwell\n".as_bytes();
+        let solution = decrypt_full(&key.encipher(plaintext), dict, &config).unwrap();
+        assert_eq!(solution.plaintext, plaintext);
+    }
+
+    #[test]
+    fn dictionary_is_reusable() {
+        let dict = Dictionary::from_reader("hello\nworld\nold\nhello\n".as_bytes()).unwrap();
+        assert_eq!(dict.len(), 3);
+        for plaintext in ["hello world", "old world", "hello old world"] {
+            let encrypted = encrypt(plaintext);
+            assert_eq!(decrypt_with(&dict, &encrypted).unwrap(), plaintext);
+        }
+    }
+
+    #[test]
+    fn encrypt_and_decrypt_with_key() {
+        let key = Key::random();
+        let encrypted = encrypt_with_key("The same key, twice!", &key);
+        assert_eq!(encrypt_with_key("the SAME key twice", &key), encrypted);
+        assert_eq!(decrypt_with_key(&encrypted, &key), "the same key twice");
+        assert_eq!(encrypt_with_key("abc xyz", &Key::identity()), "abc xyz");
+    }
+
+    #[test]
+    fn lint_key_finds_swapped_letters() {
+        let dict =
+            Dictionary::from_reader("the quick brown fox jumps over a lazy dog\n".as_bytes())
+                .unwrap();
+        let key: Key = "qwertyuiopasdfghjklzxcvbnm".parse().unwrap();
+        let ciphertext = encrypt_with_key("The quick brown fox jumps over a lazy dog", &key);
+        assert_eq!(lint_key(&key, &ciphertext, &dict).unwrap(), []);
+
+        // The ciphertext letters of 'j' and 'o' swapped
+        let typo: Key = "qwertyuiogasdfphjklzxcvbnm".parse().unwrap();
+        let lints = lint_key(&typo, &ciphertext, &dict).unwrap();
+        let lint = lints.iter().find(|lint| lint.cipher == 'g').unwrap();
+        assert_eq!(lint.plain, 'j');
+        assert!(lint.unmatched * 2 > lint.words);
+        assert_eq!(lint.suggestion, Some('o'));
+
+        let finnish = Key::identity_in(&Alphabet::finnish());
+        assert!(matches!(
+            lint_key(&finnish, &ciphertext, &dict),
+            Err(Error::InvalidKey)
+        ));
+    }
+
+    #[test]
+    fn explain_word_finds_blocking_letters() {
+        let dict = Dictionary::from_reader("cat\ncot\ncut\ndog\nhello\n".as_bytes()).unwrap();
+        let key = Key::identity();
+        let explanation = explain_word("Cat!", &key, &dict).unwrap();
+        assert!(explanation.matched && explanation.blocking.is_empty());
+        assert_eq!(explanation.to_string(), "\"cat\" is in the dictionary");
+
+        // The first or the last letter alone could change, but not the vowel
+        let explanation = explain_word("cog", &key, &dict).unwrap();
+        assert_eq!(explanation.candidates, 4);
+        let blocking: Vec<(char, Option<char>, String)> = explanation
+            .blocking
+            .iter()
+            .map(|letter| {
+                (
+                    letter.cipher,
+                    letter.plain,
+                    letter.alternatives.iter().collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            blocking,
+            [
+                ('c', Some('c'), "d".to_owned()),
+                ('g', Some('g'), "t".to_owned())
+            ]
+        );
+        assert_eq!(
+            explanation.to_string(),
+            "\"cog\" is not in the dictionary\n\
+             'c' deciphered to 'c' blocks it, 'd' would unblock it\n\
+             'g' deciphered to 'g' blocks it, 't' would unblock it"
+        );
+
+        // Unmapped letters block too
+        let key: Key = "abcdefghijklmnopqrs_uvwxyz".parse().unwrap();
+        let explanation = explain_word("cut", &key, &dict).unwrap();
+        assert_eq!(explanation.deciphered, "cu_");
+        assert_eq!(explanation.blocking.len(), 1);
+        assert_eq!(explanation.blocking[0].plain, None);
+        assert_eq!(explanation.blocking[0].alternatives, ['t']);
+
+        let explanation = explain_word("xyz", &Key::identity(), &dict).unwrap();
+        assert!(explanation.blocking.is_empty());
+        assert!(explanation.to_string().ends_with(
+            "more than one letter has to change to make one of the 4 words of its pattern"
+        ));
+        let explanation = explain_word("xx", &Key::identity(), &dict).unwrap();
+        assert_eq!(explanation.candidates, 0);
+        assert!(explanation
+            .to_string()
+            .ends_with("which has no words of its pattern"));
+
+        for word in ["", "two words"] {
+            assert!(matches!(
+                explain_word(word, &Key::identity(), &dict),
+                Err(Error::UnsupportedInput)
+            ));
+        }
+        let finnish = Key::identity_in(&Alphabet::finnish());
+        assert!(matches!(
+            explain_word("cat", &finnish, &dict),
+            Err(Error::InvalidKey)
+        ));
+    }
+
+    #[test]
+    fn key_directions() {
+        let text = "Grüße, World! 123";
+        let key = Key::random();
+        assert_eq!(key.decipher(&key.encipher(text)), text);
+        assert_eq!(key.encipher(&key.decipher(text)), text);
+        assert_eq!(key.inverse().encipher(text), key.decipher(text));
+        assert_eq!(key.inverse().inverse(), key);
+
+        let partial: Key = "b_________________________".parse().unwrap();
+        assert_eq!(partial.encipher("abc"), "bbc");
+        assert_eq!(partial.decipher("abc"), "aac");
+    }
+
+    #[test]
+    fn decrypt_in_other_alphabet() {
+        let finnish = Alphabet::finnish();
+        let plaintext = "hyvää yötä äiti öljy työ";
+        let dict =
+            Dictionary::from_reader_in("hyvää\nyötä\näiti\nöljy\ntyö\n".as_bytes(), &finnish)
+                .unwrap();
+        for seed in 0..5 {
+            let key = Key::random_in(&finnish, &mut StdRng::seed_from_u64(seed));
+            let encrypted = encrypt_with_key(plaintext, &key);
+            let solution = dict.decrypt_full(&encrypted, &SolverConfig::new()).unwrap();
+            assert_eq!(solution.plaintext, plaintext);
+            assert_eq!(solution.key.to_string().chars().count(), finnish.len());
+        }
+        assert!(matches!(
+            Key::from_letters(&Key::identity().to_string(), &finnish),
+            Err(Error::InvalidKey)
+        ));
+    }
+
+    #[test]
+    fn key_parse_and_display() {
+        let key = Key::random();
+        assert_eq!(key.to_string().parse::<Key>().unwrap(), key);
+        assert_eq!(
+            "BA________________________"
+                .parse::<Key>()
+                .unwrap()
+                .to_string(),
+            "ba________________________"
+        );
+        for invalid in [
+            "abc",
+            "aacdefghijklmnopqrstuvwxyz",
+            "1bcdefghijklmnopqrstuvwxyz",
+        ] {
+            assert!(matches!(invalid.parse::<Key>(), Err(Error::InvalidKey)));
+        }
+    }
+
+    #[test]
+    fn key_csv_round_trip() {
+        let finnish = Alphabet::finnish();
+        let key = Key::random_in(&finnish, &mut StdRng::seed_from_u64(7));
+        let mut csv = Vec::new();
+        key.to_csv(&mut csv).unwrap();
+        assert_eq!(Key::from_csv_in(csv.as_slice(), &finnish).unwrap(), key);
+
+        let partial: Key = "_x_______________________a".parse().unwrap();
+        let mut csv = Vec::new();
+        partial.to_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv.clone()).unwrap(),
+            "cipher,plain\nx,b\na,z\n"
+        );
+        assert_eq!(Key::from_csv(csv.as_slice()).unwrap(), partial);
+    }
+
+    #[test]
+    fn key_metadata_is_kept() {
+        let metadata = Metadata::new()
+            .label(" puzzle 12 ")
+            .note("From the Sunday paper\nRated hard");
+        assert_eq!(metadata.notes, ["From the Sunday paper", "Rated hard"]);
+        let key = Key::identity().with_metadata(metadata.clone());
+        let mut csv = Vec::new();
+        key.to_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with(
+            "# label: puzzle 12\n# note: From the Sunday paper\n# note: Rated hard\ncipher,plain\n"
+        ));
+        let read = Key::from_csv(csv.as_bytes()).unwrap();
+        assert_eq!(read.metadata(), &metadata);
+        assert_eq!(read.inverse().metadata(), &metadata);
+        // Other comments aren't metadata
+        let read = Key::from_csv("# a comment\nb,a\n".as_bytes()).unwrap();
+        assert!(read.metadata().is_empty());
+        assert_eq!(read.metadata().to_string(), "");
+
+        let config = SolverConfig::new().metadata(metadata.clone());
+        let dict = Dictionary::from_reader("hello\nworld\n".as_bytes()).unwrap();
+        for algorithm in [Algorithm::Backtracking, Algorithm::Beam] {
+            let config = config.clone().algorithm(algorithm);
+            let solution = dict.decrypt_full("ifmmp xpsme", &config).unwrap();
+            assert_eq!(solution.key.metadata(), &metadata);
+        }
+    }
+
+    #[test]
+    fn key_csv_rejects_malformed_rows() {
+        for (csv, row) in [
+            ("x,a\nx;b\n", "line 2: \"x;b\""),
+            ("# Key\nx,ab\n", "line 2: \"x,ab\""),
+            ("x,1\n", "line 1: \"x,1\""),
+            ("x,a,\n", "line 1: \"x,a,\""),
+        ] {
+            match Key::from_csv(csv.as_bytes()) {
+                Err(Error::InvalidKeyCsv(message)) => assert_eq!(message, row),
+                other => panic!("{csv:?} gave {other:?}"),
+            }
+        }
+        assert!(matches!(
+            Key::from_csv("x,a\ny,a\n".as_bytes()),
+            Err(Error::KeyConflict {
+                cipher: 'y',
+                plain: 'a'
+            })
+        ));
+        assert!(matches!(
+            Key::from_csv("x,a\nx,b\n".as_bytes()),
+            Err(Error::KeyConflict {
+                cipher: 'x',
+                plain: 'b'
+            })
+        ));
+    }
+
+    #[test]
+    fn key_csv_read_errors_arent_dictionary_errors() {
+        struct Failing;
+        impl std::io::Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("unplugged"))
+            }
+        }
+        let result = Key::from_csv(std::io::BufReader::new(Failing));
+        assert!(matches!(result, Err(Error::ReadKey(_))));
+        assert_eq!(result.unwrap_err().to_string(), "Failed to read key");
+    }
+
+    #[test]
+    fn confusion_compares_deciphered_letters() {
+        let truth = Key::random_with(&mut StdRng::seed_from_u64(3));
+        assert!(truth
+            .confusion(&truth)
+            .iter()
+            .all(LetterConfusion::is_correct));
+
+        // A solution which only maps the letters of its ciphertext
+        let plaintext = "hello";
+        let letters: String = truth
+            .to_string()
+            .chars()
+            .zip('a'..='z')
+            .map(|(cipher, plain)| {
+                if plaintext.contains(plain) {
+                    cipher
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let found: Key = letters.parse().unwrap();
+        let confusion = found.confusion(&truth);
+        let correct: String = confusion
+            .iter()
+            .filter(|letter| letter.is_correct())
+            .map(|letter| letter.plain)
+            .collect();
+        assert_eq!(correct, "ehlo");
+        assert_eq!(
+            confusion
+                .iter()
+                .filter(|letter| letter.deciphered.is_none())
+                .count(),
+            22
+        );
+
+        // Keys of another alphabet are compared by letter
+        let finnish = Key::from_letters(&format!("{truth}___"), &Alphabet::finnish()).unwrap();
+        assert_eq!(truth.confusion(&finnish).len(), 26);
+        assert!(truth
+            .confusion(&finnish)
+            .iter()
+            .all(LetterConfusion::is_correct));
+    }
+
+    #[test]
+    fn encrypt_bytes_passes_non_letters_through() {
+        let input = b"Log 0x1f: \xff\xfe\x00 caf\xc3\xa9 [OK]\r\n";
+        let encrypted = encrypt_bytes(input);
+        assert_eq!(encrypted.len(), input.len());
+        for (i, e) in input.iter().zip(&encrypted) {
+            if i.is_ascii_alphabetic() {
+                assert_eq!(i.is_ascii_uppercase(), e.is_ascii_uppercase());
+            } else {
+                assert_eq!(i, e);
+            }
+        }
+    }
+
+    #[test]
+    fn decrypt_bytes_round_trip() {
+        let input = b"\xffHello, World!\x00 \xc3(hello world)\n";
+        let encrypted = encrypt_bytes(input);
+        let decrypted = decrypt_bytes(&encrypted, "hello\nworld\n".as_bytes()).unwrap();
+        assert_eq!(decrypted, input);
+    }
+
+    #[test]
+    fn estimate_counts_without_searching() {
+        let dict = "hello\nworld\nhello\n".as_bytes();
+        let english = estimate("Ifmmp, xpsme!", dict, &SolverConfig::new()).unwrap();
+        assert_eq!(english.words, 2);
+        assert_eq!(english.distinct_letters, 7);
+        assert_eq!(english.dictionary_words, 2);
+        assert_eq!(english.rounds, 2);
+        assert!(
+            (english.keys_log10 - (20..=26).map(f64::from).map(f64::log10).sum::<f64>()).abs()
+                < 1e-9
+        );
+        assert_eq!(english.warnings.len(), 1);
+
+        // The alphabet and the skip budget of the configuration count, and so do its errors
+        let config = SolverConfig::new()
+            .alphabet(Alphabet::finnish())
+            .max_skip_cost(0);
+        let finnish = estimate("Ifmmp, xpsme!", dict, &config).unwrap();
+        assert_eq!(finnish.rounds, 1);
+        assert!(
+            (finnish.keys_log10 - (23..=29).map(f64::from).map(f64::log10).sum::<f64>()).abs()
+                < 1e-9
+        );
+        let config = SolverConfig::new().max_words(1);
+        assert!(matches!(
+            estimate("Ifmmp, xpsme!", dict, &config),
+            Err(Error::InputTooLong { words: 2, max: 1 })
+        ));
+    }
+
+    #[test]
+    fn lenient_dictionary_skips_invalid_lines() {
+        let dict: &[u8] = b"hello\r\nw\xf6rld\n\xff\nworld";
+        assert!(matches!(
+            load_dict(dict, false, &Alphabet::english()),
+            Err(Error::LoadDictionary(_))
+        ));
+        assert_eq!(
+            check_dictionary(dict, &Alphabet::english()).unwrap(),
+            [2, 3].map(|line| SkippedLine {
+                line,
+                problem: LineProblem::InvalidUtf8
+            })
+        );
+        let config = SolverConfig::new().lenient_dictionary(true);
+        assert_eq!(
+            decrypt_with_config("hello world", dict, &config).unwrap(),
+            "hello world"
+        );
+
+        // Letters of other alphabets are kept
+        let dict = ["hyvää\n".as_bytes(), b"\xff\n", "yötä".as_bytes()].concat();
+        let finnish = Alphabet::finnish();
+        let (words, skipped) = Dictionary::from_reader_lenient(dict.as_slice(), &finnish).unwrap();
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(words.covers("Hyvää yötä"), Ok(()));
+        assert_eq!(
+            check_dictionary(dict.as_slice(), &finnish).unwrap(),
+            skipped
+        );
+    }
+
+    #[test]
+    fn skip_budget_is_capped_unless_raised() {
+        assert_eq!(SolverConfig::new().max_skip(100), DEFAULT_MAX_SKIP_COST);
+        assert_eq!(SolverConfig::new().max_skip(2), 1);
+        assert_eq!(SolverConfig::new().max_skip_cost(10).max_skip(100), 10);
+        assert_eq!(SolverConfig::new().max_skip_ratio(0.5).max_skip(100), 50);
+        assert_eq!(SolverConfig::new().skipping(false).max_skip(100), 0);
+    }
+
+    #[test]
+    fn skip_cost_prefers_short_words() {
+        assert_eq!(skip_cost(b"a", SHORT_WORD_LEN, 100), 1);
+        assert_eq!(skip_cost(b"abc", SHORT_WORD_LEN, 100), 1);
+        assert_eq!(skip_cost(b"abcd", SHORT_WORD_LEN, 100), 2);
+        assert_eq!(skip_cost(b"abcdefghijkl", SHORT_WORD_LEN, 100), 2);
+        assert_eq!(skip_cost(b"abcd", 4, 100), 1);
+    }
+
+    #[test]
+    fn skip_cost_prefers_rare_patterns() {
+        assert_eq!(skip_cost(b"abcd", SHORT_WORD_LEN, 0), 1);
+        assert_eq!(skip_cost(b"abcd", SHORT_WORD_LEN, RARE_PATTERN_WORDS), 1);
+        assert_eq!(
+            skip_cost(b"abcd", SHORT_WORD_LEN, RARE_PATTERN_WORDS + 1),
+            2
+        );
+    }
+
     #[test]
     fn decrypt_10_simple_words() {
         let input: String = "HHHH aaa aaaaa ii t uuuuuu aaa ggggggg t yyyyyyyy".into();
@@ -596,10 +4147,256 @@ mod test {
         );
     }
 
+    #[test]
+    fn decrypt_empty_dictionary() {
+        let encrypted = encrypt("Hello world!");
+        let result = decrypt(&encrypted, std::io::BufReader::new(" \n\n".as_bytes()));
+        assert!(matches!(result, Err(Error::DictionaryEmpty)));
+    }
+
+    #[test]
+    fn decrypt_no_letters() {
+        let result = decrypt("1234 !?", std::io::BufReader::new("hello\n".as_bytes()));
+        assert!(matches!(result, Err(Error::UnsupportedInput)));
+    }
+
+    #[test]
+    fn decrypt_empty_input() {
+        let result = decrypt("", std::io::BufReader::new("hello\n".as_bytes()));
+        assert!(matches!(result, Err(Error::UnsupportedInput)));
+    }
+
+    #[test]
+    fn error_source_chaining() {
+        use std::error::Error as _;
+        let err = Error::from(std::io::Error::other("oops"));
+        assert_eq!(err.source().unwrap().to_string(), "oops");
+        let err = Error::from(TrieError::KeyNotInAlphabet {
+            value: 30,
+            size: 26,
+        });
+        assert!(err.source().is_some());
+    }
+
+    #[test]
+    fn diagnose_short_input() {
+        assert_eq!(
+            diagnose("Hello, world!"),
+            [Warning::InputTooShort {
+                distinct_letters: 7,
+                words: 2
+            }]
+        );
+        assert!(diagnose("The quick brown fox jumps over the lazy dog").is_empty());
+    }
+
+    #[test]
+    fn candidates_are_ranked_by_score_and_equal_by_key() {
+        let dict = Dictionary::from_reader("hello\nworld\n".as_bytes()).unwrap();
+        let config = SolverConfig::new();
+        let candidate = |key: &str| -> Candidate {
+            Solution::from_key("ifmmp xpsme", &key.parse().unwrap(), &dict, &config)
+                .unwrap()
+                .into()
+        };
+        let right = candidate("___ef__i___m__p__s____x___");
+        let wrong = candidate("___fe__i___m__p__s____x___");
+        assert!(right > wrong);
+        assert_ne!(right, wrong);
+        let mut changed = right.clone();
+        changed.plaintext.clear();
+        assert_eq!(changed, right);
+        let set: std::collections::HashSet<Candidate> = [right.clone(), changed].into();
+        assert_eq!(set.len(), 1);
+
+        // Solutions of every algorithm are candidates
+        for algorithm in [Algorithm::Auto, Algorithm::Beam, Algorithm::Backtracking] {
+            let config = SolverConfig::new().algorithm(algorithm).anytime(true);
+            let solution = dict.decrypt_full("ifmmp xpsme", &config).unwrap();
+            assert_eq!(Candidate::from(solution), right);
+        }
+
+        assert!(dict
+            .top_candidates("ifmmp xpsme", &config, 0)
+            .unwrap()
+            .is_empty());
+        // Then the keys which skip either word
+        let best = dict.top_candidates("ifmmp xpsme", &config, 5).unwrap();
+        assert_eq!(best.len(), 3);
+        assert_eq!(best[0], right);
+        assert!(best[1] > best[2] && best[2].stats.matched_words == 1);
+        assert!(matches!(
+            dict.top_candidates("qqqqqqq", &config, 5),
+            Err(Error::SearchExhausted)
+        ));
+        assert!(matches!(
+            dict.top_candidates("ifmmp xpsme", &config.max_nodes(0), 5),
+            Err(Error::BudgetExceeded)
+        ));
+    }
+
+    #[test]
+    fn hints_are_letters_of_the_key_which_help() {
+        let dict = Dictionary::from_reader("dog\ncat\ncot\n".as_bytes()).unwrap();
+        let key: Key = "bcdefghijklmnopqrstuvwxyza".parse().unwrap();
+        let ciphertext = key.encipher("cot");
+        // Only 'o' tells "cot" apart from both "cat" and "dog"
+        assert_eq!(
+            dict.suggest_hints(&ciphertext, &key, 1).unwrap(),
+            [('p', 'o')]
+        );
+        let hints = dict.suggest_hints(&ciphertext, &key, 3).unwrap();
+        assert_eq!(hints.len(), 3);
+        assert!(hints
+            .iter()
+            .all(|(cipher, plain)| key.encipher(&plain.to_string()) == cipher.to_string()));
+        assert!(dict.suggest_hints(&ciphertext, &key, 0).unwrap().is_empty());
+
+        let cryptogram = testgen::cryptograms(testgen::CORPUS, 3, 1, 6..=6).remove(0);
+        let key = Key::from_letters(&cryptogram.key, &Alphabet::english()).unwrap();
+        let dict = Dictionary::from_reader(testgen::CORPUS.as_bytes()).unwrap();
+        let hints = dict.suggest_hints(&cryptogram.ciphertext, &key, 2).unwrap();
+        let config = hints
+            .iter()
+            .fold(SolverConfig::new(), |config, (cipher, plain)| {
+                config.pin_letter(*plain, *cipher)
+            });
+        let solution = dict.decrypt_full(&cryptogram.ciphertext, &config).unwrap();
+        assert_eq!(solution.plaintext, cryptogram.plaintext);
+        assert!(matches!(
+            Dictionary::from_reader_in("dog\n".as_bytes(), &Alphabet::finnish())
+                .unwrap()
+                .suggest_hints(&ciphertext, &Key::identity(), 1),
+            Err(Error::InvalidKey)
+        ));
+    }
+
+    #[test]
+    fn diagnose_unusual_letter_frequencies() {
+        let english = encrypt_with_rng(testgen::CORPUS, &mut StdRng::seed_from_u64(5));
+        assert!(diagnose(&english).is_empty());
+
+        // Every letter as common as the others, like a cipher which hides letter frequencies
+        let flat = "abcdefghijklm nopqrstuvwxyz ".repeat(10);
+        let warnings = diagnose(&flat);
+        assert!(matches!(
+            warnings[..],
+            [Warning::UnusualLetterFrequencies { similarity }] if similarity < 75
+        ));
+        assert!(diagnose_in(&flat, &Language::Finnish).is_empty());
+        assert!(diagnose(&flat[..100]).is_empty());
+    }
+
+    #[test]
+    fn overlong_words_are_left_out() {
+        let long = "xpsme".repeat(MAX_WORD_LEN);
+        let input = format!("ifmmp {long} xpsme");
+        assert_eq!(
+            diagnose(&input)[1],
+            Warning::OverlongWords {
+                words: 1,
+                longest: long.len()
+            }
+        );
+
+        let dict = Dictionary::from_reader("hello\nworld\n".as_bytes()).unwrap();
+        let solution = dict.decrypt_full(&input, &SolverConfig::new()).unwrap();
+        assert_eq!(
+            solution.plaintext,
+            format!("hello {} world", "world".repeat(MAX_WORD_LEN))
+        );
+        assert_eq!(solution.matched_words, 2);
+    }
+
+    /// Encrypts like [`encrypt`], but with a key generated from a fixed seed
+    fn encrypt_seeded(input: &str, seed: u64) -> String {
+        encrypt_with_rng(input, &mut StdRng::seed_from_u64(seed))
+    }
+
+    #[test]
+    fn detect_separator_symbol() {
+        assert_eq!(detect_separator("ifmmp.xpsme.ipx.bsf.zpv"), Some('.'));
+        assert_eq!(detect_separator("IFMMP/XPSME/IPX/BSF/ZPV"), Some('/'));
+        assert_eq!(detect_separator("ifmmp xpsme ipx bsf zpv"), None);
+    }
+
+    #[test]
+    fn detect_separator_letter() {
+        assert_eq!(detect_separator("THEXOLDXMANXSATXBYXTHEXSEA"), Some('X'));
+        assert_eq!(detect_separator("thequickbrownfoxjumpedover"), None);
+        // 's' is the most common letter, but 'X' is written in a case of its own
+        assert_eq!(detect_separator("wjsXwsrzjsaXplrysv"), Some('X'));
+        // No letter stands out, so there's too little to go on
+        assert_eq!(detect_separator("wjsxwjrzpqaxplrysv"), None);
+        assert_eq!(
+            separate("THEXOLDXMANXSATXBYXTHEXSEA", 'x'),
+            "THE OLD MAN SAT BY THE SEA"
+        );
+    }
+
+    #[test]
+    fn uniform_free_char_only_for_repeated_letter() {
+        assert_eq!(uniform_free_char(b"aaa", b"a"), Some(b'a'));
+        assert_eq!(uniform_free_char(b"t", b"t"), Some(b't'));
+        assert_eq!(uniform_free_char(b"aab", b"b"), None);
+        assert_eq!(uniform_free_char(b"aaa", b""), None);
+    }
+
+    #[test]
+    fn decrypt_10_simple_words_fixed_keys() {
+        for seed in 0..20 {
+            let encrypted =
+                encrypt_seeded("HHHH aaa aaaaa ii t uuuuuu aaa ggggggg t yyyyyyyy", seed);
+            dbg!(seed, &encrypted);
+            let decrypted = decrypt(
+                &encrypted,
+                std::io::BufReader::new("hhhh\naaa\nii\nt\nuuuuuu\nggggggg\nyyyyyyyy\n".as_bytes()),
+            )
+            .unwrap();
+            assert_eq!(
+                &decrypted,
+                "hhhh aaa aaaaa ii t uuuuuu aaa ggggggg t yyyyyyyy"
+            );
+        }
+    }
+
+    /// Creates a key for searching the key of `input` in English
+    fn english_key(input: &[u8]) -> Key {
+        let english = Alphabet::english();
+        Key::new(
+            input,
+            &english,
+            &LanguageFreqs::new(
+                Language::English.frequency_order(),
+                &Language::English,
+                &english,
+            ),
+        )
+    }
+
+    #[cfg(feature = "debug-invariants")]
+    #[test]
+    #[should_panic(expected = "Key maps two letters to a")]
+    fn invariants_catch_a_key_which_isnt_bijective() {
+        let mut key = Key::identity();
+        key.table[1] = b'a';
+        key.check_invariants();
+    }
+
+    #[test]
+    fn custom_frequency_order_has_every_letter_once() {
+        let alphabet = Alphabet::new("abz").unwrap();
+        let freqs = LanguageFreqs::new("Zz a! zq", &Language::English, &alphabet);
+        let [z, a, b] = [b'c', b'a', b'b'];
+        assert_eq!(freqs.order[..4], [z, a, b, 0]);
+        assert_eq!(freqs.initial[Key::index(z)], 0);
+        assert_eq!(freqs.initial[Key::index(b)], 44);
+    }
+
     #[test]
     fn key_input_frequency_order() {
         let input = filter_input("aaaaa bbvvvbb oo e");
-        let key = Key::new(&input, ENGLISH_FREQ_ORDER);
+        let key = english_key(&input);
 
         assert_eq!(key.input_freq_index[usize::from(b'a' - START)], 0);
         assert_eq!(key.input_freq_index[usize::from(b'b' - START)], 1);
@@ -608,25 +4405,37 @@ mod test {
         assert_eq!(key.input_freq_index[usize::from(b'e' - START)], 4);
     }
 
+    #[test]
+    fn key_first_guess_prefers_word_initial_letter() {
+        let input = filter_input("xa xb xc xd xe");
+        let key = english_key(&input);
+        assert_eq!(key.first_guesses[usize::from(b'x' - START)], b't');
+
+        // Too few words for statistics, use the frequency rank only
+        let input = filter_input("xa xb");
+        let key = english_key(&input);
+        assert_eq!(key.first_guesses[usize::from(b'x' - START)], b'e');
+    }
+
     #[test]
     fn key_next_in_freq_order_covers_all_for_all() {
         for start_from in START..=END {
-            let mut values_got = [0; R];
+            let mut values_got = [0; ASCII_LETTERS];
             let mut current = start_from;
-            let dummy = Key::new(b"", ENGLISH_FREQ_ORDER);
+            let dummy = english_key(b"");
             while {
                 println!("Got '{}'", char::from(current));
                 values_got[usize::from(current - START)] += 1;
                 current = dummy.next_in_freq_order(start_from, current);
                 current != 0
             } {}
-            assert_eq!(values_got, [1; R]);
+            assert_eq!(values_got, [1; ASCII_LETTERS]);
         }
     }
 
     fn assert_key_next_in_freq_order(start: u8, expected: &[u8]) {
         let mut current = start;
-        let dummy = Key::new(b"", ENGLISH_FREQ_ORDER);
+        let dummy = english_key(b"");
         for chr in expected {
             match dummy.next_in_freq_order(start, current) {
                 0 => {
@@ -11,7 +11,11 @@
 // Don't measure the CLI binary's coverage in tarpaulin
 #![cfg(not(tarpaulin_include))]
 
-// "Include" src/io.rs in the main CLI here
+// "Include" src/io.rs, src/failure.rs, src/bench.rs, src/demo.rs and src/puzzle.rs in the main
+// CLI here
+mod bench;
+mod demo;
+mod failure;
 mod io;
 
 use clap::{ArgGroup, Parser};
@@ -19,16 +23,42 @@ use color_eyre::{
     eyre::{eyre, Context},
     Result,
 };
+use rand::{rngs::StdRng, SeedableRng};
 use std::{
-    io::{BufReader, BufWriter, Read, Write},
-    path::PathBuf,
+    fmt::Write as _,
+    io::{BufRead, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock, PoisonError},
+    time::Instant,
 };
 
+/// When the program started, for the elapsed time of progress events
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
+/// Progress which the search reported last, for a failure bundle
+static LAST_PROGRESS: Mutex<Option<substitution::SearchProgress>> = Mutex::new(None);
+
+/// Write a progress event to stderr as a line of JSON, for --progress-json
+fn emit(event: &substitution::ProgressEvent) {
+    match serde_json::to_string(event) {
+        Ok(line) => eprintln!("{line}"),
+        Err(e) => eprintln!("Cannot write progress event: {e}"),
+    }
+}
+
+/// Progress event of `phase` without counts, timed from the start of the program
+fn progress_event(phase: &'static str) -> substitution::ProgressEvent {
+    let elapsed = STARTED.get_or_init(Instant::now).elapsed().as_secs_f64();
+    substitution::ProgressEvent::new(phase, elapsed)
+}
+
 /// Main command line argument structure
 #[derive(Parser)]
 #[clap(author, version, about)]
 // Deny using -i and -o at the same time
 #[clap(group(ArgGroup::new("output").args(&["in-place", "output-file"])))]
+// Command line flags are naturally booleans
+#[allow(clippy::struct_excessive_bools)]
 struct Cli {
     /// Overwrite the contents of the input file
     #[clap(long, short)]
@@ -36,21 +66,269 @@ struct Cli {
     /// File to write output to. Defaults to stdout if omitted
     #[clap(long, short)]
     output_file: Option<PathBuf>,
-    /// Dictionary text file to use
-    #[clap(long, short, default_value = "/usr/share/dict/words")]
-    dictionary: PathBuf,
-    /// Perform encrypt or decrypt
+    /// Dictionary text file to use, can be given many times to combine wordlists.
+    /// Common wordlist locations are searched if omitted
+    #[clap(long, short, multiple_occurrences = true)]
+    dictionary: Vec<PathBuf>,
+    /// Directory to save a reproducible bundle to when decryption fails
+    #[clap(long)]
+    failure_dir: Option<PathBuf>,
+    /// Read input from the system clipboard
+    #[cfg(feature = "clipboard")]
+    #[clap(long, conflicts_with = "path")]
+    paste: bool,
+    /// Write output to the system clipboard
+    #[cfg(feature = "clipboard")]
+    #[clap(long, conflicts_with_all = &["in-place", "output-file"])]
+    copy: bool,
+    /// Split words at Unicode word boundaries, so that non-breaking and other non-ASCII spaces
+    /// separate words too
+    #[cfg(feature = "unicode-segmentation")]
+    #[clap(long)]
+    unicode_words: bool,
+    /// Character which separates words in the ciphertext. Detected if omitted
+    #[clap(long)]
+    separator: Option<char>,
+    /// Require capitalized words in the middle of sentences to be in the dictionary too
+    #[clap(long)]
+    no_proper_nouns: bool,
+    /// Leave out dictionary lines which can't be decoded instead of failing
+    #[clap(long)]
+    lenient_dictionary: bool,
+    /// Write deciphered text with the case, punctuation and spacing of the input
+    #[clap(long)]
+    keep_formatting: bool,
+    /// Print the found key, its label and notes, and the share of words in the dictionary to
+    /// stderr. In puzzle and apply-key mode, print the key which is used
+    #[clap(long)]
+    show_key: bool,
+    /// With --show-key, print the key as a table of each letter and its substitute too, in the
+    /// order which the language sorts letters in
+    #[clap(long, requires = "show-key")]
+    key_table: bool,
+    /// If no key fits the dictionary, write a low-confidence guess from letter frequencies
+    /// instead of failing
+    #[clap(long)]
+    fallback: bool,
+    /// If no key fits the dictionary or the search gives up, write the text deciphered with the
+    /// guesses which placed the most words instead of failing
+    #[clap(long)]
+    anytime: bool,
+    /// What the key should make the most of: all-words, which deciphers every word it can into a
+    /// dictionary word, matched-words, which places as many words as possible and writes the best
+    /// guesses found if the search gives up, or matched-letters, which counts long words for more
+    #[clap(long, default_value = "all-words")]
+    objective: substitution::Objective,
+    /// Require every word to be in the dictionary, except names and words shorter than
+    /// --min-word-len
+    #[clap(long, conflicts_with_all = &["max-skip-cost", "max-skip-ratio"])]
+    no_skipping: bool,
+    /// Most that the skipped words may cost in total, counting 1 for words of up to three letters
+    /// and 2 for longer ones with the default objective. Defaults to 3, unless --max-skip-ratio is
+    /// given
+    #[clap(long)]
+    max_skip_cost: Option<usize>,
+    /// Most that the skipped words may cost as a share (0 to 1) of skipping every word
+    #[clap(long)]
+    max_skip_ratio: Option<f64>,
+    /// Letter which is already known, as the plaintext letter and the ciphertext letter which it
+    /// is enciphered as, like e=x. Give it again for each known letter
+    #[clap(
+        long,
+        value_name = "PLAIN=CIPHER",
+        multiple_occurrences = true,
+        parse(try_from_str = parse_mapping)
+    )]
+    map: Vec<(char, char)>,
+    /// Word which is known to be in the plaintext, like a name. Give it again for each word
+    #[clap(long, value_name = "WORD", multiple_occurrences = true)]
+    crib: Vec<String>,
+    /// Label the key, like with the ID of a puzzle. It is kept in the answer key of a puzzle and
+    /// printed with --show-key
+    #[clap(long, value_name = "TEXT")]
+    label: Option<String>,
+    /// Add a note to the key, like where the text came from. Give it again for each note
+    #[clap(long, value_name = "TEXT", multiple_occurrences = true)]
+    note: Vec<String>,
+    /// Go on from the letters, cribs, label and notes of a TOML worksheet. Give it again for each
+    /// worksheet to merge them, which fails if they contradict each other
+    #[clap(long, value_name = "PATH", multiple_occurrences = true)]
+    worksheet: Vec<PathBuf>,
+    /// Write the found key and the cribs to a TOML worksheet for people to work on
+    #[clap(long, value_name = "PATH")]
+    save_worksheet: Option<PathBuf>,
+    /// Let words shorter than this many letters be skipped for free
+    #[clap(long, default_value_t = 0)]
+    min_word_len: usize,
+    /// Give up deciphering after this many seconds
+    #[clap(long)]
+    max_duration: Option<f64>,
+    /// Give up deciphering after testing this many guesses against the dictionary
+    #[clap(long)]
+    max_nodes: Option<usize>,
+    /// Give up deciphering after words have run out of guesses this many times
+    #[clap(long)]
+    max_backtracks: Option<usize>,
+    /// Refuse to decipher inputs of more words than this
+    #[clap(long)]
+    max_words: Option<usize>,
+    /// How to search for the key: backtracking, which finds a key that fits the dictionary,
+    /// beam, which keeps the best --beam-width partial keys instead of going back,
+    /// hill-climbing or annealing with quadgram statistics, for texts with many words which the
+    /// dictionary doesn't have, or auto, which anneals if backtracking fails
+    #[clap(long, default_value = "auto")]
+    strategy: substitution::Algorithm,
+    /// Order to decipher words in: overlap, which takes words sharing most letters with the words
+    /// before them first, or target, which only goes by their number of distinct letters
+    #[clap(long, default_value = "overlap")]
+    word_ordering: substitution::WordOrdering,
+    /// Partial keys to keep after each word with --strategy beam
+    #[clap(long, default_value_t = 100)]
+    beam_width: usize,
+    /// With --max-duration, fewest partial keys to keep with --strategy beam, which adapts its
+    /// width to the time left between this and --max-beam-width
+    #[clap(long, requires = "max-beam-width")]
+    min_beam_width: Option<usize>,
+    /// With --max-duration, most partial keys to keep with --strategy beam
+    #[clap(long, requires = "min-beam-width")]
+    max_beam_width: Option<usize>,
+    /// Table of quadgram counts for hill-climbing and annealing, with a quadgram and its count on
+    /// each line. Defaults to the table of the language pack, or counting quadgrams of the
+    /// dictionary
+    #[clap(long)]
+    ngrams: Option<PathBuf>,
+    /// Print how far deciphering has got to stderr every now and then
+    #[clap(long)]
+    progress: bool,
+    /// Print progress to stderr as JSON, an event on each line, for other programs to follow
+    #[clap(long, conflicts_with = "progress")]
+    progress_json: bool,
+    /// Load the dictionary and estimate the search without deciphering
+    #[clap(long)]
+    dry_run: bool,
+    /// In puzzle mode, fail if some words of the input are not in the dictionary
+    #[clap(long)]
+    check: bool,
+    /// In puzzle mode, give away the ciphertext letter of this plaintext letter as a hint
+    #[clap(long)]
+    hint: Option<char>,
+    /// In puzzle mode, give away this many letters which help the most to solve the puzzle with
+    /// the dictionary
+    #[clap(long, value_name = "K", conflicts_with = "hint")]
+    hints: Option<usize>,
+    /// In puzzle mode, write the solution and the key to this file
+    #[clap(long)]
+    answer_key: Option<PathBuf>,
+    /// Seed for generating the encryption key, to get reproducible ciphertext.
+    /// In bench and tune modes, seed for picking the passages and keys
+    #[clap(long)]
+    seed: Option<u64>,
+    /// Write encrypted text in groups of this many letters, hiding word boundaries
+    #[clap(long)]
+    group: Option<usize>,
+    /// Key for apply-key mode, as the ciphertext letters of each plaintext letter of the alphabet,
+    /// or a path to a CSV file of cipher,plain rows.
+    /// In encrypt mode, encrypt with it instead of a random key
+    #[clap(long)]
+    key: Option<String>,
+    /// Whether apply-key enciphers or deciphers the input
+    #[clap(long, default_value = "encipher")]
+    direction: Direction,
+    /// Write the output in lowercase letters
+    #[clap(long, conflicts_with_all = &["upper", "match-input"])]
+    lower: bool,
+    /// Write the output in uppercase letters
+    #[clap(long, alias = "uppercase", conflicts_with = "match-input")]
+    upper: bool,
+    /// Write each letter of the output in the case of the input's letter in the same place
+    #[clap(long)]
+    match_input: bool,
+    /// Letters to encrypt and decipher: english, finnish, german or the letters themselves.
+    /// Defaults to the alphabet of the language
+    #[clap(long)]
+    alphabet: Option<substitution::Alphabet>,
+    /// Language of the plaintext, for its letter frequencies and alphabet:
+    /// english, finnish, german, french, spanish or a language pack file
+    #[clap(long, default_value = "english", parse(try_from_str = parse_language))]
+    language: substitution::Language,
+    /// File listing letters from the most to the least common, to guess letters in instead of
+    /// the language's order. Lines starting with '#' are comments
+    #[clap(long)]
+    freq_file: Option<PathBuf>,
+    /// Longest words to draw in dict-viz mode
+    #[clap(long, default_value_t = 3)]
+    max_depth: usize,
+    /// Number of cryptograms to solve per length in bench and tune modes
+    #[clap(long, default_value_t = 10)]
+    samples: usize,
+    /// In bench mode, write the results as a JSON snapshot to compare with bench-diff
+    #[clap(long)]
+    json: bool,
+    /// In bench-diff mode, percent by which a solve time may grow before it's a regression
+    #[clap(long, default_value_t = 10.)]
+    threshold: f64,
+    /// Perform encrypt, puzzle, decrypt, apply-key, replay, bench, bench-diff, tune, analyze,
+    /// dict-viz or demo
     mode: Mode,
-    /// File to read as input. Defaults to stdin if omitted
+    /// File to read as input. Defaults to stdin if omitted.
+    /// Replay reads a bundle directory saved with --failure-dir.
+    /// Bench and tune read a plaintext corpus and default to a built-in one.
+    /// Bench-diff reads the old snapshot
     path: Option<PathBuf>,
+    /// In bench-diff mode, the new snapshot to compare with the old one
+    new_snapshot: Option<PathBuf>,
+}
+
+/// Parse the name of a built-in language, or load a language pack from a file
+fn parse_language(s: &str) -> Result<substitution::Language> {
+    if Path::new(s).is_file() {
+        Ok(substitution::Language::from_path(s)?)
+    } else {
+        Ok(s.parse()?)
+    }
+}
+
+/// Parse a known letter, written as its plaintext letter and ciphertext letter like e=x
+fn parse_mapping(s: &str) -> Result<(char, char)> {
+    let mut letters = s.chars();
+    match (
+        letters.next(),
+        letters.next(),
+        letters.next(),
+        letters.next(),
+    ) {
+        (Some(plain), Some('='), Some(cipher), None) => Ok((plain, cipher)),
+        _ => Err(eyre!(
+            "Expected a plaintext and a ciphertext letter like e=x"
+        )),
+    }
 }
 
 /// Modes that the program can run in
 enum Mode {
     /// Encrypt the input with a randomly generated key
     Encrypt,
+    /// Encipher the input with a randomly generated key as a printable puzzle with answer rows
+    Puzzle,
     /// Decipher the input without a key
     Decrypt,
+    /// Encipher or decipher the input with a known key
+    ApplyKey,
+    /// Decipher a failure bundle again
+    Replay,
+    /// Measure decryption accuracy and speed on generated cryptograms
+    Bench,
+    /// Compare two snapshots of bench results and fail if the new one regressed
+    BenchDiff,
+    /// Find the fastest solver heuristics on generated cryptograms and print them as src/tuning.rs
+    Tune,
+    /// Count letters and bigrams of the input without reading it all into memory, and with
+    /// --dictionary, list the word patterns which the most dictionary words share
+    Analyze,
+    /// Write the dictionary as a Graphviz graph of its trie
+    DictViz,
+    /// Decipher a built-in puzzle, explaining each step
+    Demo,
 }
 
 /// String value conversion for modes
@@ -62,60 +340,1059 @@ impl std::str::FromStr for Mode {
         // Convert input to lowercase to be case-insensitive
         match s.to_ascii_lowercase().as_ref() {
             "encrypt" | "e" => Ok(Self::Encrypt),
+            "puzzle" | "p" => Ok(Self::Puzzle),
             "decrypt" | "d" => Ok(Self::Decrypt),
+            "apply-key" | "k" => Ok(Self::ApplyKey),
+            "replay" | "r" => Ok(Self::Replay),
+            "bench" | "b" => Ok(Self::Bench),
+            "bench-diff" | "f" => Ok(Self::BenchDiff),
+            "tune" | "t" => Ok(Self::Tune),
+            "analyze" | "a" => Ok(Self::Analyze),
+            "dict-viz" | "v" => Ok(Self::DictViz),
+            "demo" | "m" => Ok(Self::Demo),
             _ => Err(eyre!(
-                "Unknown mode.\nTry one of 'e', 'encrypt', 'd', 'decrypt'."
+                "Unknown mode.\nTry one of 'e', 'encrypt', 'p', 'puzzle', 'd', 'decrypt', 'k', 'apply-key', 'r', 'replay', 'b', 'bench', 'f', 'bench-diff', 't', 'tune', 'a', 'analyze', 'v', 'dict-viz', 'm', 'demo'."
             )),
         }
     }
 }
 
-/// Read everything from stdin/file specified in CLI options
+/// Directions in which a key can be applied
+enum Direction {
+    /// Substitute plaintext letters with ciphertext letters
+    Encipher,
+    /// Substitute ciphertext letters with plaintext letters
+    Decipher,
+}
+
+/// String value conversion for directions
+impl std::str::FromStr for Direction {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_ref() {
+            "encipher" => Ok(Self::Encipher),
+            "decipher" => Ok(Self::Decipher),
+            _ => Err(eyre!("Unknown direction.\nTry 'encipher' or 'decipher'.")),
+        }
+    }
+}
+
+/// Determine input from CLI options
+fn open_input(opts: &Cli) -> Result<io::Input> {
+    #[cfg(feature = "clipboard")]
+    if opts.paste {
+        return io::Input::clipboard();
+    }
+    opts.path.clone().try_into()
+}
+
+/// Determine output from CLI options
+fn open_output(opts: &Cli) -> Result<io::Output> {
+    #[cfg(feature = "clipboard")]
+    if opts.copy {
+        return Ok(io::Output::Clipboard(Vec::new()));
+    }
+    if opts.in_place {
+        opts.path.clone()
+    } else {
+        opts.output_file.clone()
+    }
+    .try_into()
+}
+
+/// Read everything from stdin/file/clipboard specified in CLI options
 fn read_input(opts: &Cli) -> Result<String> {
-    let mut text = String::with_capacity(4096);
-    let mut input: io::Input = opts.path.clone().try_into()?;
+    let mut bytes = Vec::with_capacity(4096);
+    let mut input = open_input(opts)?;
     BufReader::new(input.as_mut())
-        .read_to_string(&mut text)
-        .wrap_err_with(|| format!("Cannot read from {}", input))?;
+        .read_to_end(&mut bytes)
+        .wrap_err_with(|| format!("Cannot read from {input}"))?;
+    let (text, transcoded_from) =
+        io::decode(bytes).wrap_err_with(|| format!("Cannot read from {input}"))?;
+    if let Some(encoding) = transcoded_from {
+        eprintln!("Warning: Converted {input} from {encoding} to UTF-8");
+    }
     Ok(text)
 }
 
+/// Stream the input specified in CLI options through letter statistics and report them as tables,
+/// along with the word patterns which most words of the dictionaries share if they are given
+#[allow(clippy::cast_precision_loss)]
+fn analyze(opts: &Cli) -> Result<String> {
+    /// Number of most common bigrams to list
+    const BIGRAMS: usize = 20;
+    /// Number of most ambiguous dictionary word patterns to list
+    const PATTERNS: usize = 20;
+
+    let mut input = open_input(opts)?;
+    let stats = substitution::LetterStats::from_reader(input.as_mut())
+        .wrap_err_with(|| format!("Cannot read from {input}"))?;
+
+    let letters = stats.total_letters().max(1) as f64;
+    let mut report = String::from("letter     count  percent\n");
+    for (letter, count) in stats.letters() {
+        let _ = writeln!(
+            report,
+            "{letter:>6} {count:>9} {:>7.2}%",
+            count as f64 * 100. / letters
+        );
+    }
+
+    let bigrams = stats.bigrams();
+    let total: u64 = bigrams.iter().map(|(_, count)| count).sum();
+    let _ = writeln!(report, "\nbigram     count  percent");
+    for ([first, second], count) in bigrams.into_iter().take(BIGRAMS) {
+        let _ = writeln!(
+            report,
+            "{first:>5}{second} {count:>9} {:>7.2}%",
+            count as f64 * 100. / total as f64
+        );
+    }
+
+    // The patterns which most words share are the hardest to decipher
+    if !opts.dictionary.is_empty() {
+        let dict = substitution::Dictionary::from_reader_in(
+            open_dictionaries(&opts.dictionary)?,
+            &alphabet(opts),
+        )
+        .wrap_err("Cannot load dictionary")?;
+        let _ = writeln!(report, "\npattern        words  percent");
+        for (pattern, count) in dict.pattern_histogram().into_iter().take(PATTERNS) {
+            let _ = writeln!(
+                report,
+                "{:<9} {count:>10} {:>7.2}%",
+                pattern.to_string(),
+                count as f64 * 100. / dict.len() as f64
+            );
+        }
+    }
+    Ok(report.trim_end().to_owned())
+}
+
+/// Parse --key as the letters of a key, or read it from the CSV file which it names
+fn parse_key(key: &str, opts: &Cli) -> Result<substitution::Key> {
+    let path = Path::new(key);
+    if path.is_file() {
+        let file = std::fs::File::open(path)
+            .wrap_err_with(|| format!("Cannot open key file {}", path.display()))?;
+        substitution::Key::from_csv_in(BufReader::new(file), &alphabet(opts))
+            .wrap_err_with(|| format!("Invalid key file {}", path.display()))
+    } else {
+        substitution::Key::from_letters(key, &alphabet(opts)).wrap_err("Invalid --key")
+    }
+}
+
+/// Label and notes of the key which the CLI options give
+fn metadata(opts: &Cli) -> substitution::Metadata {
+    let metadata = opts
+        .note
+        .iter()
+        .fold(substitution::Metadata::new(), |metadata, note| {
+            metadata.note(note)
+        });
+    match &opts.label {
+        Some(label) => metadata.label(label),
+        None => metadata,
+    }
+}
+
+/// Worksheets given in CLI options, merged into one
+fn worksheet(opts: &Cli) -> Result<Option<substitution::Worksheet>> {
+    let mut merged: Option<substitution::Worksheet> = None;
+    for path in &opts.worksheet {
+        let text = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("Cannot read worksheet {}", path.display()))?;
+        let sheet = substitution::Worksheet::from_toml(&text)
+            .wrap_err_with(|| format!("Cannot load worksheet {}", path.display()))?;
+        merged = Some(match merged {
+            Some(merged) => merged
+                .merge(&sheet)
+                .wrap_err_with(|| format!("Cannot merge worksheet {}", path.display()))?,
+            None => sheet,
+        });
+    }
+    Ok(merged)
+}
+
+/// Write the key of `solution` and the cribs of the CLI options and worksheets to the worksheet
+/// file which the CLI options give, if any
+fn save_worksheet(solution: &substitution::Solution, opts: &Cli) -> Result<()> {
+    let Some(path) = &opts.save_worksheet else {
+        return Ok(());
+    };
+    let mut sheet = substitution::Worksheet::from_key(&solution.key);
+    sheet.cribs = worksheet(opts)?
+        .map(|sheet| sheet.cribs)
+        .unwrap_or_default();
+    for crib in &opts.crib {
+        if !sheet.cribs.contains(crib) {
+            sheet.cribs.push(crib.clone());
+        }
+    }
+    std::fs::write(path, sheet.to_toml())
+        .wrap_err_with(|| format!("Cannot write the worksheet to {}", path.display()))
+}
+
+/// Encipher or decipher text with the key given in CLI options, keeping case and punctuation
+fn apply_key(
+    text: &str,
+    dictionaries: impl FnOnce() -> Result<Vec<PathBuf>>,
+    opts: &Cli,
+) -> Result<String> {
+    let key = opts
+        .key
+        .as_ref()
+        .ok_or_else(|| eyre!("apply-key needs a key, give one with --key"))?;
+    let mut key = parse_key(key, opts)?;
+    if !metadata(opts).is_empty() {
+        key = key.with_metadata(metadata(opts));
+    }
+    if opts.show_key {
+        eprintln!("Key: {key}");
+        print_key_details(&key, opts);
+    }
+    let output = match opts.direction {
+        Direction::Encipher => key.encipher(text),
+        Direction::Decipher => {
+            // Point out likely mistakes in the key when there's a dictionary to check it with
+            if let Ok(dictionaries) = dictionaries() {
+                let dict = substitution::Dictionary::from_reader_in(
+                    open_dictionaries(&dictionaries)?,
+                    &alphabet(opts),
+                )
+                .wrap_err("Cannot load dictionary")?;
+                for lint in substitution::lint_key(&key, text, &dict)? {
+                    eprintln!("Warning: {lint}");
+                }
+            }
+            key.decipher(text)
+        }
+    };
+    Ok(recase(output, opts))
+}
+
+/// Collect encryption options from CLI options
+fn encrypt_options(opts: &Cli) -> Result<substitution::EncryptOptions> {
+    let mut options = substitution::EncryptOptions::new().alphabet(alphabet(opts));
+    if let Some(seed) = opts.seed {
+        options = options.seed(seed);
+    }
+    if let Some(size) = opts.group {
+        options = options.group(size);
+    }
+    if let Some(case) = case(opts) {
+        options = options.case(case);
+    }
+    if let Some(key) = &opts.key {
+        options = options.key(parse_key(key, opts)?);
+    }
+    #[cfg(feature = "unicode-segmentation")]
+    if opts.unicode_words {
+        options = options.normalizer(unicode_normalizer());
+    }
+    Ok(options)
+}
+
+/// Normalizer which splits words at Unicode word boundaries
+#[cfg(feature = "unicode-segmentation")]
+fn unicode_normalizer() -> substitution::normalize::Normalizer {
+    use substitution::normalize::{Lowercase, Normalizer, SegmentWords};
+    Normalizer::builder()
+        .stage(Lowercase)
+        .stage(SegmentWords)
+        .build()
+}
+
+/// Letters in the frequency order file at `path`, without comment lines
+fn frequency_order(path: &Path) -> Result<String> {
+    let order = std::fs::read_to_string(path)
+        .wrap_err_with(|| format!("Cannot read frequency order from {}", path.display()))?;
+    Ok(order
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .collect())
+}
+
+/// Collect decryption options from CLI options
+fn solver_config(opts: &Cli) -> Result<substitution::SolverConfig> {
+    let mut config = substitution::SolverConfig::new()
+        .proper_nouns(!opts.no_proper_nouns)
+        .lenient_dictionary(opts.lenient_dictionary)
+        .keep_formatting(opts.keep_formatting)
+        .fallback(opts.fallback)
+        .anytime(opts.anytime)
+        .objective(opts.objective)
+        .skipping(!opts.no_skipping)
+        .min_word_len(opts.min_word_len)
+        .algorithm(opts.strategy)
+        .beam_width(opts.beam_width)
+        .word_ordering(opts.word_ordering)
+        .language(opts.language.clone())
+        .alphabet(alphabet(opts));
+    if let Some(cost) = opts.max_skip_cost {
+        config = config.max_skip_cost(cost);
+    }
+    if let Some(ratio) = opts.max_skip_ratio {
+        config = config.max_skip_ratio(ratio);
+    }
+    for (plain, cipher) in &opts.map {
+        config = config.pin_letter(*plain, *cipher);
+    }
+    for crib in &opts.crib {
+        config = config.crib(crib);
+    }
+    if let Some(sheet) = worksheet(opts)? {
+        config = sheet.apply(config);
+    }
+    if !metadata(opts).is_empty() {
+        config = config.metadata(metadata(opts));
+    }
+    let table = match &opts.language {
+        _ if opts.ngrams.is_some() => opts.ngrams.as_deref(),
+        substitution::Language::Custom(pack) => pack.ngrams(),
+        _ => None,
+    };
+    if let (Some(path), false) = (
+        table,
+        opts.strategy == substitution::Algorithm::Backtracking,
+    ) {
+        let file = std::fs::File::open(path)
+            .wrap_err_with(|| format!("Cannot open {}", path.display()))?;
+        let quadgrams = substitution::Quadgrams::from_table(BufReader::new(file), &alphabet(opts))
+            .wrap_err("Cannot load quadgrams")?;
+        config = config.quadgrams(quadgrams);
+    }
+    if let Some(path) = &opts.freq_file {
+        config = config.frequency_order(&frequency_order(path)?);
+    }
+    if let (Some(min), Some(max)) = (opts.min_beam_width, opts.max_beam_width) {
+        config = config.adaptive_beam_width(min, max);
+    }
+    if let Some(case) = case(opts) {
+        config = config.case(case);
+    }
+    if let Some(seconds) = opts.max_duration {
+        let duration =
+            std::time::Duration::try_from_secs_f64(seconds).wrap_err("Invalid --max-duration")?;
+        config = config.max_duration(duration);
+    }
+    if opts.progress || opts.progress_json || opts.failure_dir.is_some() {
+        let (progress_text, progress_json) = (opts.progress, opts.progress_json);
+        config = config.on_progress(move |progress| {
+            if progress_text {
+                eprintln!(
+                    "Placed {} of {} words at best, tested {} guesses, backtracked {} times",
+                    progress.best_words_placed, progress.words, progress.nodes, progress.backtracks
+                );
+            }
+            if progress_json {
+                emit(&substitution::ProgressEvent {
+                    words_solved: Some(progress.best_words_placed),
+                    words: Some(progress.words),
+                    nodes: Some(progress.nodes),
+                    ..progress_event("search")
+                });
+            }
+            *LAST_PROGRESS.lock().unwrap_or_else(PoisonError::into_inner) = Some(progress.clone());
+        });
+    }
+    if let Some(nodes) = opts.max_nodes {
+        config = config.max_nodes(nodes);
+    }
+    if let Some(backtracks) = opts.max_backtracks {
+        config = config.max_backtracks(backtracks);
+    }
+    if let Some(words) = opts.max_words {
+        config = config.max_words(words);
+    }
+    #[cfg(feature = "unicode-segmentation")]
+    if opts.unicode_words {
+        config = config.normalizer(unicode_normalizer());
+    }
+    Ok(config)
+}
+
+/// Options given in CLI options which change how text is deciphered, as command line arguments
+/// for a failure bundle. Files are only named, see [`failure::redact`].
+fn search_options(opts: &Cli) -> Vec<String> {
+    let mut options = vec![
+        format!("--strategy={}", opts.strategy),
+        format!("--objective={}", opts.objective),
+        format!("--word-ordering={}", opts.word_ordering),
+        format!("--language={}", opts.language),
+        format!("--beam-width={}", opts.beam_width),
+        format!("--min-word-len={}", opts.min_word_len),
+    ];
+    let flags = [
+        ("--no-proper-nouns", opts.no_proper_nouns),
+        ("--lenient-dictionary", opts.lenient_dictionary),
+        ("--fallback", opts.fallback),
+        ("--anytime", opts.anytime),
+        ("--no-skipping", opts.no_skipping),
+    ];
+    options.extend(
+        flags
+            .iter()
+            .filter(|(_, given)| *given)
+            .map(|(flag, _)| (*flag).to_owned()),
+    );
+    let file = |path: &Option<PathBuf>| {
+        path.as_deref()
+            .map(|path| failure::redact(path).display().to_string())
+    };
+    let values = [
+        (
+            "--alphabet",
+            opts.alphabet.as_ref().map(ToString::to_string),
+        ),
+        ("--separator", opts.separator.map(String::from)),
+        (
+            "--max-skip-cost",
+            opts.max_skip_cost.map(|cost| cost.to_string()),
+        ),
+        (
+            "--max-skip-ratio",
+            opts.max_skip_ratio.map(|ratio| ratio.to_string()),
+        ),
+        (
+            "--max-duration",
+            opts.max_duration.map(|seconds| seconds.to_string()),
+        ),
+        ("--max-nodes", opts.max_nodes.map(|nodes| nodes.to_string())),
+        (
+            "--max-backtracks",
+            opts.max_backtracks.map(|backtracks| backtracks.to_string()),
+        ),
+        ("--max-words", opts.max_words.map(|words| words.to_string())),
+        (
+            "--min-beam-width",
+            opts.min_beam_width.map(|width| width.to_string()),
+        ),
+        (
+            "--max-beam-width",
+            opts.max_beam_width.map(|width| width.to_string()),
+        ),
+        ("--ngrams", file(&opts.ngrams)),
+        ("--freq-file", file(&opts.freq_file)),
+    ];
+    options.extend(
+        values
+            .into_iter()
+            .filter_map(|(option, value)| Some(format!("{option}={}", value?))),
+    );
+    options.extend(
+        opts.map
+            .iter()
+            .map(|(plain, cipher)| format!("--map={plain}={cipher}")),
+    );
+    options.extend(opts.crib.iter().map(|word| format!("--crib={word}")));
+    options
+}
+
+/// Alphabet given in CLI options, or the alphabet of the language
+fn alphabet(opts: &Cli) -> substitution::Alphabet {
+    opts.alphabet
+        .clone()
+        .unwrap_or_else(|| opts.language.alphabet())
+}
+
+/// Read the snapshots of bench-diff mode and compare them, returning the table of changes and
+/// whether the new snapshot regressed
+fn bench_diff(opts: &Cli) -> Result<(String, bool)> {
+    let (Some(old), Some(new)) = (&opts.path, &opts.new_snapshot) else {
+        return Err(eyre!("Bench-diff needs the old and the new snapshot"));
+    };
+    let read = |path: &PathBuf| -> Result<bench::Snapshot> {
+        let json = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("Cannot read {}", path.display()))?;
+        serde_json::from_str(&json)
+            .wrap_err_with(|| format!("Cannot parse snapshot {}", path.display()))
+    };
+    bench::diff(&read(old)?, &read(new)?, opts.threshold)
+}
+
+/// Read a failure bundle from the directory specified in CLI options, and tell how the failure
+/// was recorded. Returns the bundle and the dictionaries to replay it with. The bundle only names
+/// its dictionaries, so they are the ones given in CLI options or else the one which is found.
+fn read_bundle(opts: &Cli) -> Result<(failure::Bundle, Vec<PathBuf>)> {
+    let path = opts
+        .path
+        .as_deref()
+        .ok_or_else(|| eyre!("Replay needs a bundle directory"))?;
+    let bundle = failure::Bundle::load(path)?;
+    eprintln!(
+        "Recorded with dictionaries {}",
+        display_dictionaries(&bundle.dictionaries)
+    );
+    let dictionaries = find_dictionaries(&opts.dictionary)?;
+    for dictionary in bundle.changed_dictionaries(&dictionaries)? {
+        eprintln!(
+            "Warning: {} has changed since the failure was recorded",
+            dictionary.display()
+        );
+    }
+
+    eprintln!("Recorded with options {}", bundle.options.join(" "));
+    let stats = &bundle.stats;
+    eprintln!(
+        "The search placed {} of {} words at best, tested {} guesses and backtracked {} times \
+         in {:.1} s",
+        stats.words_placed, stats.words, stats.nodes, stats.backtracks, stats.seconds
+    );
+    if let Some(key) = &stats.partial_key {
+        eprintln!("Partial key: {key}");
+    }
+    Ok((bundle, dictionaries))
+}
+
+/// Split words at the separator given in CLI options or detected from the text
+fn separate_words(text: &str, opts: &Cli) -> String {
+    let detected = || {
+        let separator = substitution::detect_separator(text)?;
+        eprintln!("Detected '{separator}' as the word separator");
+        Some(separator)
+    };
+    match opts.separator.or_else(detected) {
+        Some(separator) => substitution::separate(text, separator),
+        None => text.to_owned(),
+    }
+}
+
+/// Use the dictionary files given in CLI options, or find one if none were given
+fn find_dictionaries(dictionaries: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    if dictionaries.is_empty() {
+        Ok(vec![io::find_dictionary()?])
+    } else {
+        Ok(dictionaries.to_vec())
+    }
+}
+
+/// List dictionary file paths for messages
+fn display_dictionaries(dictionaries: &[PathBuf]) -> String {
+    let paths: Vec<String> = dictionaries
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect();
+    paths.join(", ")
+}
+
+/// Open a dictionary file for reading
+fn open_dictionary(dictionary: &Path) -> Result<BufReader<std::fs::File>> {
+    Ok(BufReader::new(
+        std::fs::File::open(dictionary)
+            .wrap_err_with(|| format!("Cannot open {}", dictionary.display()))?,
+    ))
+}
+
+/// Open dictionary files for reading as one wordlist, one after another
+fn open_dictionaries(dictionaries: &[PathBuf]) -> Result<Box<dyn BufRead>> {
+    let mut reader: Box<dyn BufRead> = Box::new(std::io::empty());
+    for dictionary in dictionaries {
+        // Separate the files in case one doesn't end in a newline
+        reader = Box::new(reader.chain(&b"\n"[..]).chain(open_dictionary(dictionary)?));
+    }
+    Ok(reader)
+}
+
+/// Draw the trie of the dictionaries as a Graphviz graph
+fn dict_viz(dictionaries: &[PathBuf], opts: &Cli) -> Result<String> {
+    let dict =
+        substitution::Dictionary::from_reader_in(open_dictionaries(dictionaries)?, &alphabet(opts))
+            .wrap_err("Cannot load dictionary")?;
+    let mut dot = Vec::new();
+    dict.to_dot(&mut dot, opts.max_depth)?;
+    // The output gets its last newline when it's written
+    let dot = String::from_utf8(dot)?;
+    Ok(dot.trim_end().to_owned())
+}
+
+/// Read dictionary files into one wordlist
+fn read_dictionaries(dictionaries: &[PathBuf]) -> Result<String> {
+    let mut words = String::new();
+    for dictionary in dictionaries {
+        let file = std::fs::read_to_string(dictionary)
+            .wrap_err_with(|| format!("Cannot read {}", dictionary.display()))?;
+        words.push_str(&file);
+        words.push('\n');
+    }
+    Ok(words)
+}
+
+/// Print the lines of dictionary files which are left out when loading them leniently in
+/// `alphabet`
+fn report_skipped_lines(dictionaries: &[PathBuf], alphabet: &substitution::Alphabet) -> Result<()> {
+    /// Number of skipped lines to list before only counting the rest
+    const MAX_LISTED: usize = 10;
+
+    for dictionary in dictionaries {
+        let skipped = substitution::check_dictionary(open_dictionary(dictionary)?, alphabet)
+            .wrap_err_with(|| format!("Cannot load {}", dictionary.display()))?;
+        if skipped.is_empty() {
+            continue;
+        }
+        eprintln!(
+            "Warning: {} has {} lines which were left out",
+            dictionary.display(),
+            skipped.len(),
+        );
+        for line in skipped.iter().take(MAX_LISTED) {
+            eprintln!("  line {}: {}", line.line, line.problem);
+        }
+        if skipped.len() > MAX_LISTED {
+            eprintln!("  and {} more", skipped.len() - MAX_LISTED);
+        }
+    }
+    Ok(())
+}
+
+/// Print the label and notes of `key`, and `key` as a table in the order of the language's
+/// letters if the CLI options ask for it
+fn print_key_details(key: &substitution::Key, opts: &Cli) {
+    if !key.metadata().is_empty() {
+        eprintln!("{}", key.metadata());
+    }
+    if opts.key_table {
+        eprintln!("{}", substitution::key_table(key, &opts.language));
+    }
+}
+
+/// Encipher text as a printable puzzle, first checking that the dictionaries have its words if
+/// requested, and write its answer key if requested
+fn puzzle(
+    text: &str,
+    dictionaries: impl FnOnce() -> Result<Vec<PathBuf>>,
+    opts: &Cli,
+) -> Result<String> {
+    let alphabet = alphabet(opts);
+    let dict = if opts.check || opts.hints.is_some() {
+        let dictionaries = dictionaries()?;
+        let dict =
+            substitution::Dictionary::from_reader_in(open_dictionaries(&dictionaries)?, &alphabet)
+                .wrap_err("Cannot load dictionary")?;
+        Some(dict)
+    } else {
+        None
+    };
+    if let (true, Some(dict)) = (opts.check, &dict) {
+        dict.covers(text)
+            .wrap_err("The puzzle can't be solved with the dictionary")?;
+    }
+    let key = match opts.seed {
+        Some(seed) => substitution::Key::random_in(&alphabet, &mut StdRng::seed_from_u64(seed)),
+        None => substitution::Key::random_in(&alphabet, &mut rand::thread_rng()),
+    }
+    .with_metadata(metadata(opts));
+    if opts.show_key {
+        eprintln!("Key: {key}");
+        print_key_details(&key, opts);
+    }
+    let ciphertext = recase(key.encipher(text), opts);
+    let hints: Vec<(char, char)> = match opts.hint.and_then(|plain| plain.to_lowercase().next()) {
+        Some(plain) if alphabet.contains(plain) && text.to_lowercase().contains(plain) => key
+            .encipher(&plain.to_string())
+            .chars()
+            .next()
+            .map(|cipher| (cipher, plain))
+            .into_iter()
+            .collect(),
+        Some(plain) => return Err(eyre!("The hint '{plain}' is not a letter of the puzzle")),
+        None => match (opts.hints, &dict) {
+            (Some(k), Some(dict)) => dict
+                .suggest_hints(&ciphertext, &key, k)
+                .wrap_err("Cannot pick hints")?,
+            _ => Vec::new(),
+        },
+    };
+    if let Some(path) = &opts.answer_key {
+        std::fs::write(path, substitution::answer_key(text, &key, &opts.language))
+            .wrap_err_with(|| format!("Cannot write the answer key to {}", path.display()))?;
+    }
+    Ok(substitution::puzzle_layout(
+        &ciphertext,
+        &alphabet,
+        opts.group,
+        &hints,
+    ))
+}
+
+/// Letter case of the output which the CLI options ask for, if any
+fn case(opts: &Cli) -> Option<substitution::Case> {
+    if opts.lower {
+        Some(substitution::Case::Lower)
+    } else if opts.upper {
+        Some(substitution::Case::Upper)
+    } else if opts.match_input {
+        Some(substitution::Case::MatchInput)
+    } else {
+        None
+    }
+}
+
+/// Change the case of text which was written in the case of the input, like by applying a key
+fn recase(text: String, opts: &Cli) -> String {
+    match case(opts) {
+        Some(substitution::Case::Lower) => text.to_lowercase(),
+        Some(substitution::Case::Upper) => text.to_uppercase(),
+        _ => text,
+    }
+}
+
+/// Report what deciphering text with dictionary files and the CLI options would involve
+fn dry_run(text: &str, dictionaries: &[PathBuf], opts: &Cli) -> Result<String> {
+    let config = solver_config(opts)?;
+    let estimate = substitution::estimate(text, open_dictionaries(dictionaries)?, &config)
+        .wrap_err("Cannot decrypt")?;
+    Ok(substitution::estimate_report(
+        &display_dictionaries(dictionaries),
+        &estimate,
+    ))
+}
+
+/// Decipher text with dictionary files, saving a failure bundle if the search is exhausted
+fn decrypt(text: &str, dictionaries: &[PathBuf], opts: &Cli) -> Result<String> {
+    for warning in substitution::diagnose_in(text, &opts.language) {
+        eprintln!("Warning: {warning}");
+        match warning {
+            substitution::Warning::InputTooShort { .. } => eprintln!(
+                "The result may be one of several plausible decryptions, or take long to find."
+            ),
+            substitution::Warning::OverlongWords { .. } if opts.separator.is_none() => eprintln!(
+                "If the words are separated by a symbol or a letter, give it with --separator."
+            ),
+            substitution::Warning::UnusualLetterFrequencies { .. } => eprintln!(
+                "If the plaintext is in another language than English, give it with --language."
+            ),
+            _ => {}
+        }
+    }
+    if !opts.no_proper_nouns {
+        for word in substitution::proper_nouns(text, &alphabet(opts)) {
+            eprintln!("Allowing \"{word}\" to be a proper noun outside the dictionary");
+        }
+    }
+    if opts.lenient_dictionary {
+        report_skipped_lines(dictionaries, &alphabet(opts))?;
+    }
+    let config = solver_config(opts)?;
+    if opts.progress_json {
+        emit(&progress_event("load"));
+    }
+    let result = if opts.dictionary.is_empty() && opts.strategy == substitution::Algorithm::Auto {
+        // By default, decipher like the library's crack, with its time limit and fallback
+        substitution::crack_with(text, &config.crack_limits())
+    } else {
+        substitution::decrypt_full(text, open_dictionaries(dictionaries)?, &config)
+    };
+    if opts.progress_json {
+        emit(&match &result {
+            Ok(solution) => substitution::ProgressEvent {
+                words_solved: Some(solution.matched_words),
+                ..progress_event("done")
+            },
+            Err(_) => progress_event("failed"),
+        });
+    }
+    // The automatic strategy anneals when backtracking fails
+    let annealed = |solution: &substitution::Solution| {
+        opts.strategy == substitution::Algorithm::Auto
+            && solution.algorithm != Some(substitution::Algorithm::Backtracking)
+    };
+    let exhausted = match &result {
+        Ok(solution) => solution.fallback || solution.partial || annealed(solution),
+        Err(e) => matches!(e, substitution::Error::SearchExhausted),
+    };
+    if let (true, Some(dir)) = (exhausted, opts.failure_dir.as_deref()) {
+        let progress = LAST_PROGRESS
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        let stats = failure::Stats {
+            // The key of a solution which isn't certain is the best guess, even if partial
+            partial_key: match (&result, &progress) {
+                (Ok(solution), _) => Some(solution.key.to_string()),
+                (Err(_), Some(progress)) => Some(progress.best_key.to_string()),
+                (Err(_), None) => None,
+            },
+            words: progress.as_ref().map_or(0, |progress| progress.words),
+            words_placed: match &result {
+                Ok(solution) => solution.matched_words,
+                Err(_) => progress
+                    .as_ref()
+                    .map_or(0, |progress| progress.best_words_placed),
+            },
+            nodes: progress.as_ref().map_or(0, |progress| progress.nodes),
+            backtracks: progress.as_ref().map_or(0, |progress| progress.backtracks),
+            seconds: STARTED.get_or_init(Instant::now).elapsed().as_secs_f64(),
+        };
+        let path =
+            failure::Bundle::new(text, dictionaries, search_options(opts), stats)?.save(dir)?;
+        eprintln!("Saved failure bundle to {}", path.display());
+    }
+    let solution = result.wrap_err("Cannot decrypt")?;
+    warn_about(&solution, annealed(&solution));
+    save_worksheet(&solution, opts)?;
+    if opts.show_key {
+        eprintln!("Key: {}", solution.key);
+        print_key_details(&solution.key, opts);
+        eprintln!("Words in dictionary: {:.0}%", solution.score * 100.);
+    }
+    Ok(solution.plaintext)
+}
+
+/// Warn about how certain a solution is and list the words it skips
+fn warn_about(solution: &substitution::Solution, annealed: bool) {
+    if solution.fallback {
+        eprintln!("Warning: No key was found, guessing from letter frequencies alone.");
+        eprintln!(
+            "The result has low confidence and is only a starting point for solving by hand."
+        );
+    }
+    if annealed {
+        eprintln!("Warning: No key fits the dictionary, guessing it by annealing.");
+        eprintln!("Some letters may be wrong.");
+    }
+    if solution.partial {
+        eprintln!(
+            "Warning: No key was found, only {} words are deciphered into dictionary words.",
+            solution.matched_words
+        );
+        eprintln!("Letters which the best guesses don't cover are written as '_'.");
+    }
+    if !solution.fallback && !solution.skipped_words.is_empty() {
+        eprintln!(
+            "Skipped words which aren't in the dictionary: {}",
+            solution.skipped_words.join(", ")
+        );
+    }
+}
+
+/// Reject CLI options which don't apply to the mode
+fn check_options(opts: &Cli) -> Result<()> {
+    if opts.dry_run && !matches!(opts.mode, Mode::Decrypt | Mode::Replay) {
+        return Err(eyre!("--dry-run only applies to decrypt and replay"));
+    }
+    if !opts.map.is_empty() && !matches!(opts.mode, Mode::Decrypt | Mode::Replay) {
+        return Err(eyre!("--map only applies to decrypt and replay"));
+    }
+    if !opts.crib.is_empty() && !matches!(opts.mode, Mode::Decrypt | Mode::Replay) {
+        return Err(eyre!("--crib only applies to decrypt and replay"));
+    }
+    if (!opts.worksheet.is_empty() || opts.save_worksheet.is_some())
+        && !matches!(opts.mode, Mode::Decrypt)
+    {
+        return Err(eyre!(
+            "--worksheet and --save-worksheet only apply to decrypt"
+        ));
+    }
+    if opts.in_place && matches!(opts.mode, Mode::Replay) {
+        return Err(eyre!(
+            "--in-place doesn't apply to replay, whose path is a bundle directory"
+        ));
+    }
+    if (opts.check || opts.hint.is_some() || opts.hints.is_some() || opts.answer_key.is_some())
+        && !matches!(opts.mode, Mode::Puzzle)
+    {
+        return Err(eyre!(
+            "--check, --hint, --hints and --answer-key only apply to puzzle"
+        ));
+    }
+    if opts.json && !matches!(opts.mode, Mode::Bench) {
+        return Err(eyre!("--json only applies to bench"));
+    }
+    if opts.new_snapshot.is_some() && !matches!(opts.mode, Mode::BenchDiff) {
+        return Err(eyre!("Only bench-diff takes a second path"));
+    }
+    Ok(())
+}
+
 fn main() -> Result<()> {
     // Install color_eyre's panic- and error report handlers
     color_eyre::install()?;
 
     // Parse CLI arguments and read the input
+    STARTED.get_or_init(Instant::now);
     let opts = Cli::parse();
 
-    // Read input
-    let text = read_input(&opts)?;
+    // Reject options which don't apply to the mode before doing any work
+    check_options(&opts)?;
+
+    // Read input, or the ciphertext and dictionaries of a failure bundle when replaying
+    let (text, dictionaries) = match opts.mode {
+        Mode::Replay => {
+            let (bundle, dictionaries) = read_bundle(&opts)?;
+            (bundle.ciphertext, dictionaries)
+        }
+        // Analyze streams the input by itself
+        Mode::Analyze | Mode::BenchDiff | Mode::Demo => (String::new(), Vec::new()),
+        Mode::DictViz => (String::new(), opts.dictionary.clone()),
+        Mode::Bench | Mode::Tune if opts.path.is_none() => (
+            substitution::testgen::CORPUS.to_owned(),
+            opts.dictionary.clone(),
+        ),
+        _ => (read_input(&opts)?, opts.dictionary.clone()),
+    };
+    let dictionaries = || find_dictionaries(&dictionaries);
 
     // Determine output from CLI
-    let mut output: io::Output = if opts.in_place {
-        opts.path
-    } else {
-        opts.output_file
-    }
-    .try_into()?;
+    let mut output = open_output(&opts)?;
 
     // Run and write the result out
+    let mut regressed = false;
     {
         let mut writer = BufWriter::new(output.as_mut());
         writeln!(
             writer,
             "{}",
             match opts.mode {
-                Mode::Decrypt => {
-                    let dict =
-                        BufReader::new(std::fs::File::open(&opts.dictionary).wrap_err_with(
-                            || format!("Cannor open {}", opts.dictionary.display()),
-                        )?);
-                    substitution::decrypt(&text, dict).wrap_err("Cannot decrypt")?
+                Mode::Decrypt | Mode::Replay if opts.dry_run =>
+                    dry_run(&separate_words(&text, &opts), &dictionaries()?, &opts)?,
+                Mode::Decrypt | Mode::Replay =>
+                    decrypt(&separate_words(&text, &opts), &dictionaries()?, &opts)?,
+                Mode::Bench | Mode::Tune => {
+                    let words = read_dictionaries(&dictionaries()?)?;
+                    let seed = opts.seed.unwrap_or_else(rand::random);
+                    if let Mode::Tune = opts.mode {
+                        eprintln!("Tuning with seed {seed}");
+                        bench::tune(&text, &words, opts.samples, seed)?
+                    } else {
+                        eprintln!("Benchmarking with seed {seed}");
+                        bench::run(&text, &words, opts.samples, seed, opts.json)?
+                    }
                 }
-
-                Mode::Encrypt => substitution::encrypt(&text),
+                Mode::BenchDiff => {
+                    let (table, bench_regressed) = bench_diff(&opts)?;
+                    regressed = bench_regressed;
+                    table
+                }
+                Mode::Encrypt => substitution::encrypt_with(&text, &encrypt_options(&opts)?),
+                Mode::Puzzle => puzzle(&text, dictionaries, &opts)?,
+                Mode::ApplyKey => apply_key(&text, dictionaries, &opts)?,
+                Mode::Analyze => analyze(&opts)?,
+                Mode::DictViz => dict_viz(&dictionaries()?, &opts)?,
+                Mode::Demo => demo::run(opts.seed)?,
             }
         )
     }
-    .wrap_err_with(|| format!("Cannot write to {}", output))
+    .wrap_err_with(|| format!("Cannot write to {output}"))?;
+    output.finish()?;
+
+    if regressed {
+        return Err(eyre!(
+            "The new snapshot regressed, see the rows marked REGRESSED"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Parse command line arguments, without the program name
+    fn cli(args: &[&str]) -> Cli {
+        Cli::try_parse_from(std::iter::once("substitution").chain(args.iter().copied())).unwrap()
+    }
+
+    #[test]
+    fn options_apply_to_their_modes() {
+        assert!(check_options(&cli(&["decrypt", "--map", "e=x", "--crib", "hello"])).is_ok());
+        assert!(check_options(&cli(&["replay", "--map", "e=x", "--crib", "hello"])).is_ok());
+        assert!(check_options(&cli(&["decrypt", "--dry-run"])).is_ok());
+        assert!(check_options(&cli(&["decrypt", "--worksheet", "a.toml"])).is_ok());
+        assert!(check_options(&cli(&["puzzle", "--check", "--hints", "2"])).is_ok());
+        assert!(check_options(&cli(&["puzzle", "--hint", "e", "--answer-key", "key.txt"])).is_ok());
+        assert!(check_options(&cli(&["bench", "--json"])).is_ok());
+        assert!(check_options(&cli(&["bench-diff", "old.json", "new.json"])).is_ok());
+        assert!(check_options(&cli(&["decrypt", "--in-place", "input.txt"])).is_ok());
+    }
+
+    #[test]
+    fn options_are_rejected_in_other_modes() {
+        let error = |args: &[&str]| check_options(&cli(args)).unwrap_err().to_string();
+        assert_eq!(
+            error(&["encrypt", "--map", "e=x"]),
+            "--map only applies to decrypt and replay"
+        );
+        assert_eq!(
+            error(&["puzzle", "--crib", "hello"]),
+            "--crib only applies to decrypt and replay"
+        );
+        assert_eq!(
+            error(&["encrypt", "--save-worksheet", "a.toml"]),
+            "--worksheet and --save-worksheet only apply to decrypt"
+        );
+        assert_eq!(
+            error(&["bench", "--dry-run"]),
+            "--dry-run only applies to decrypt and replay"
+        );
+        assert_eq!(
+            error(&["replay", "--in-place", "bundle"]),
+            "--in-place doesn't apply to replay, whose path is a bundle directory"
+        );
+        assert_eq!(
+            error(&["decrypt", "--check"]),
+            "--check, --hint, --hints and --answer-key only apply to puzzle"
+        );
+        assert_eq!(
+            error(&["decrypt", "--hints", "2"]),
+            "--check, --hint, --hints and --answer-key only apply to puzzle"
+        );
+        assert_eq!(
+            error(&["encrypt", "--answer-key", "key.txt"]),
+            "--check, --hint, --hints and --answer-key only apply to puzzle"
+        );
+        assert_eq!(error(&["tune", "--json"]), "--json only applies to bench");
+        assert_eq!(
+            error(&["decrypt", "input.txt", "more.txt"]),
+            "Only bench-diff takes a second path"
+        );
+    }
+
+    #[test]
+    fn labels_and_notes_make_metadata() {
+        let opts = cli(&["puzzle", "--label", "12", "--note", "a", "--note", "b"]);
+        assert_eq!(
+            metadata(&opts),
+            substitution::Metadata::new()
+                .label("12")
+                .note("a")
+                .note("b")
+        );
+        assert!(metadata(&cli(&["puzzle"])).is_empty());
+    }
+
+    #[test]
+    fn mappings_are_letter_pairs() {
+        assert_eq!(parse_mapping("e=x").unwrap(), ('e', 'x'));
+        assert!(parse_mapping("ex").is_err());
+        assert!(parse_mapping("e=xy").is_err());
+        assert!(Cli::try_parse_from(["substitution", "decrypt", "--map", "e"]).is_err());
+    }
+
+    #[test]
+    fn maps_and_cribs_pick_the_key() {
+        // Both words fit the ciphertext, so only the hints tell them apart
+        let decrypt = |args: &[&str]| {
+            let config = solver_config(&cli(args)).unwrap();
+            substitution::decrypt_full("abc", "dog\ncat\n".as_bytes(), &config)
+                .map(|solution| solution.plaintext)
+        };
+        assert_eq!(decrypt(&["decrypt", "--map", "c=a"]).unwrap(), "cat");
+        assert_eq!(decrypt(&["decrypt", "--map", "d=a"]).unwrap(), "dog");
+        assert_eq!(decrypt(&["decrypt", "--crib", "dog"]).unwrap(), "dog");
+        assert_eq!(decrypt(&["decrypt", "--crib", "cat"]).unwrap(), "cat");
+        assert!(matches!(
+            decrypt(&["decrypt", "--map", "c=a", "--map", "d=a"]),
+            Err(substitution::Error::KeyConflict { .. })
+        ));
+    }
+
+    #[test]
+    fn demo_solves_its_puzzles() {
+        for seed in [None, Some(2)] {
+            let report = demo::run(seed).unwrap();
+            assert!(report.ends_with("Try another puzzle with --seed."));
+        }
+    }
 }
@@ -0,0 +1,270 @@
+//! Text normalization before encryption and decryption.
+//!
+//! A [`Normalizer`] runs its input through a pipeline of [`Stage`]s in order. The default
+//! pipeline lowercases text, turns dashes into spaces and keeps only ASCII letters and
+//! whitespace, which is what the solver works with. Custom stages, including plain closures,
+//! can be inserted for cleanup such as OCR fixes or transliteration.
+//!
+//! The solver only works with lowercase ASCII letters and whitespace, so whatever a custom
+//! pipeline leaves in besides them is lowercased or removed before encrypting or deciphering.
+//!
+//! ```
+//! use substitution::normalize::{KeepLetters, Lowercase, Normalizer};
+//!
+//! let normalizer = Normalizer::builder()
+//!     .stage(|text: &str| text.replace('0', "o"))
+//!     .stage(Lowercase)
+//!     .stage(KeepLetters)
+//!     .build();
+//! assert_eq!(normalizer.normalize("G00D Morning!"), "good morning");
+//! ```
+
+use std::sync::Arc;
+
+/// A step in a normalization pipeline
+pub trait Stage {
+    /// Transform `text` into the input of the next stage
+    fn apply(&self, text: &str) -> String;
+}
+
+/// Any function from text to text can be used as a stage
+impl<F: Fn(&str) -> String> Stage for F {
+    fn apply(&self, text: &str) -> String {
+        self(text)
+    }
+}
+
+/// Converts text to lowercase
+#[derive(Debug, Clone, Copy)]
+pub struct Lowercase;
+
+impl Stage for Lowercase {
+    fn apply(&self, text: &str) -> String {
+        text.to_lowercase()
+    }
+}
+
+/// Replaces dashes with spaces, so that hyphenated words are split in two
+#[derive(Debug, Clone, Copy)]
+pub struct MapDashToSpace;
+
+impl Stage for MapDashToSpace {
+    fn apply(&self, text: &str) -> String {
+        text.replace('-', " ")
+    }
+}
+
+/// Leaves out everything except ASCII letters and whitespace
+#[derive(Debug, Clone, Copy)]
+pub struct KeepLetters;
+
+impl Stage for KeepLetters {
+    fn apply(&self, text: &str) -> String {
+        text.chars()
+            .filter(|c| c.is_ascii_alphabetic() || c.is_ascii_whitespace())
+            .collect()
+    }
+}
+
+/// Replaces punctuation, except apostrophes, with spaces. Words joined like "either/or" or
+/// "end.start" are split, but contractions like "it's" stay in one piece.
+#[derive(Debug, Clone, Copy)]
+pub struct MapPunctuationToSpace;
+
+impl Stage for MapPunctuationToSpace {
+    fn apply(&self, text: &str) -> String {
+        text.chars()
+            .map(|c| {
+                if c.is_ascii_punctuation() && c != '\'' {
+                    ' '
+                } else {
+                    c
+                }
+            })
+            .collect()
+    }
+}
+
+/// Spells out digits as English words, so that "2 cats" becomes "two cats". Every digit is a word
+/// of its own, "42" becomes "four two".
+#[derive(Debug, Clone, Copy)]
+pub struct SpellDigits;
+
+impl Stage for SpellDigits {
+    fn apply(&self, text: &str) -> String {
+        /// Names of digits from 0 to 9
+        const DIGITS: [&str; 10] = [
+            "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+        ];
+
+        let mut spelled = String::with_capacity(text.len());
+        let mut previous_digit = false;
+        for c in text.chars() {
+            if let Some(digit) = c.to_digit(10) {
+                if previous_digit {
+                    spelled.push(' ');
+                }
+                spelled.push_str(DIGITS[digit as usize]);
+            } else {
+                spelled.push(c);
+            }
+            previous_digit = c.is_ascii_digit();
+        }
+        spelled
+    }
+}
+
+/// Splits text into words at Unicode word boundaries ([UAX #29]) and joins them with single
+/// spaces, leaving out everything between words. Words separated by any kind of space, like the
+/// non-breaking and ideographic spaces of text pasted from web pages, or by punctuation are
+/// split, but contractions like "it's" stay in one piece.
+///
+/// ```
+/// use substitution::normalize::{Normalizer, SegmentWords};
+///
+/// let normalizer = Normalizer::builder().stage(SegmentWords).build();
+/// assert_eq!(normalizer.normalize("Hello\u{a0}world,\u{3000}it's me!"), "Hello world it's me");
+/// ```
+///
+/// [UAX #29]: https://www.unicode.org/reports/tr29/
+#[cfg(feature = "unicode-segmentation")]
+#[derive(Debug, Clone, Copy)]
+pub struct SegmentWords;
+
+#[cfg(feature = "unicode-segmentation")]
+impl Stage for SegmentWords {
+    fn apply(&self, text: &str) -> String {
+        use unicode_segmentation::UnicodeSegmentation;
+        // UAX #29 joins words at a narrow no-break space, so split at every space first
+        text.split(char::is_whitespace)
+            .flat_map(UnicodeSegmentation::unicode_words)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Pipeline of stages which text is passed through in order
+#[derive(Clone)]
+pub struct Normalizer {
+    stages: Vec<Arc<dyn Stage + Send + Sync>>,
+}
+
+impl Normalizer {
+    /// Start building a normalizer without any stages
+    #[must_use]
+    pub fn builder() -> NormalizerBuilder {
+        NormalizerBuilder { stages: Vec::new() }
+    }
+
+    /// Run `text` through every stage
+    #[must_use]
+    pub fn normalize(&self, text: &str) -> String {
+        let mut text = text.to_owned();
+        for stage in &self.stages {
+            text = stage.apply(&text);
+        }
+        text
+    }
+}
+
+impl std::fmt::Debug for Normalizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Normalizer")
+            .field("stages", &self.stages.len())
+            .finish()
+    }
+}
+
+/// The pipeline which the solver expects: [`Lowercase`], [`MapDashToSpace`] and [`KeepLetters`]
+impl Default for Normalizer {
+    fn default() -> Self {
+        Self::builder()
+            .stage(Lowercase)
+            .stage(MapDashToSpace)
+            .stage(KeepLetters)
+            .build()
+    }
+}
+
+/// Builder for a [`Normalizer`], created with [`Normalizer::builder`]
+pub struct NormalizerBuilder {
+    stages: Vec<Arc<dyn Stage + Send + Sync>>,
+}
+
+impl NormalizerBuilder {
+    /// Append a stage to the end of the pipeline
+    #[must_use]
+    pub fn stage(mut self, stage: impl Stage + Send + Sync + 'static) -> Self {
+        self.stages.push(Arc::new(stage));
+        self
+    }
+
+    /// Finish the pipeline
+    #[must_use]
+    pub fn build(self) -> Normalizer {
+        Normalizer {
+            stages: self.stages,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn default_keeps_lowercase_letters_and_whitespace() {
+        assert_eq!(
+            Normalizer::default().normalize("Well-known, WORLD! 😊\n"),
+            "well known world \n"
+        );
+    }
+
+    #[test]
+    fn stages_run_in_order() {
+        let dash_first = Normalizer::builder()
+            .stage(MapDashToSpace)
+            .stage(KeepLetters)
+            .build();
+        let dash_last = Normalizer::builder()
+            .stage(KeepLetters)
+            .stage(MapDashToSpace)
+            .build();
+        assert_eq!(dash_first.normalize("a-b"), "a b");
+        assert_eq!(dash_last.normalize("a-b"), "ab");
+    }
+
+    #[test]
+    fn digits_and_punctuation() {
+        let normalizer = Normalizer::builder()
+            .stage(SpellDigits)
+            .stage(MapPunctuationToSpace)
+            .stage(KeepLetters)
+            .build();
+        assert_eq!(normalizer.normalize("It's 42 km/h."), "Its four two km h ");
+        assert_eq!(normalizer.normalize("Room 7b"), "Room sevenb");
+    }
+
+    #[cfg(feature = "unicode-segmentation")]
+    #[test]
+    fn segmenting_splits_at_unicode_spaces() {
+        let normalizer = Normalizer::builder()
+            .stage(Lowercase)
+            .stage(SegmentWords)
+            .build();
+        assert_eq!(
+            normalizer.normalize("Well-known\u{202f}WORLD!\n\u{a0}Bye"),
+            "well known world bye"
+        );
+        assert_eq!(Normalizer::default().normalize("a\u{a0}b"), "ab");
+    }
+
+    #[test]
+    fn closure_stage() {
+        let normalizer = Normalizer::builder()
+            .stage(|text: &str| text.replace('ä', "a"))
+            .stage(KeepLetters)
+            .build();
+        assert_eq!(normalizer.normalize("Hätä"), "Hata");
+    }
+}
@@ -0,0 +1,187 @@
+use crate::{trie, R, START};
+use std::collections::HashMap;
+
+/// Shape of a word's letters, which a substitution cipher doesn't change. Words with the same
+/// pattern, like "hello" and "jeffu", are equal and hash the same.
+///
+/// Displayed with a capital letter for each distinct letter in order of appearance, so the
+/// pattern of "hello" is `ABCCD`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Pattern(Vec<u8>);
+
+impl Pattern {
+    /// Number of letters in the pattern
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns true if the pattern has no letters
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl std::fmt::Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for code in &self.0 {
+            write!(f, "{}", char::from(b'A' + code))?;
+        }
+        Ok(())
+    }
+}
+
+/// Canonical pattern of the ASCII letters in `word`, ignoring case. Other characters are left out.
+///
+/// ```
+/// use substitution::pattern_of;
+///
+/// assert_eq!(pattern_of("Hello").to_string(), "ABCCD");
+/// assert_eq!(pattern_of("hello"), pattern_of("jeffu"));
+/// assert_ne!(pattern_of("hello"), pattern_of("world"));
+/// ```
+#[must_use]
+pub fn pattern_of(word: &str) -> Pattern {
+    Pattern::of_letters(
+        word.bytes()
+            .filter(u8::is_ascii_alphabetic)
+            .map(|c| c.to_ascii_lowercase()),
+    )
+}
+
+impl Pattern {
+    /// Pattern of a word of letter codes, or of any bytes which are letters
+    pub(crate) fn of_letters(letters: impl IntoIterator<Item = u8>) -> Self {
+        // Letters seen so far and their codes
+        let mut seen: Vec<(u8, u8)> = Vec::new();
+        Self(
+            letters
+                .into_iter()
+                .map(|c| {
+                    if let Some((_, code)) = seen.iter().find(|(letter, _)| *letter == c) {
+                        return *code;
+                    }
+                    // Alphabets have at most 32 letters, so the codes fit
+                    let code = seen.last().map_or(0, |(_, code)| code + 1);
+                    seen.push((c, code));
+                    code
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Words of a dictionary by their patterns, so that the words which a ciphertext word can be
+/// deciphered into are looked up at once instead of guessing letters until they make one
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PatternIndex {
+    /// Letter codes of the words of each pattern, one after another
+    words: HashMap<Pattern, Vec<u8>>,
+}
+
+impl PatternIndex {
+    /// Index the words of `dict`
+    pub(crate) fn new(dict: &trie::Set<R, { START as usize }>) -> Self {
+        let mut words: HashMap<Pattern, Vec<u8>> = HashMap::new();
+        let mut word = Vec::new();
+        dict.for_each(|key| {
+            word.clear();
+            word.extend(key.iter().filter_map(|c| u8::try_from(*c).ok()));
+            words
+                .entry(Pattern::of_letters(word.iter().copied()))
+                .or_default()
+                .extend_from_slice(&word);
+        });
+        Self { words }
+    }
+
+    /// Number of words of `pattern`
+    pub(crate) fn count(&self, pattern: &Pattern) -> usize {
+        self.words
+            .get(pattern)
+            .map_or(0, |words| words.len() / pattern.len().max(1))
+    }
+
+    /// Each pattern and its number of words, most words first and patterns in order on ties
+    pub(crate) fn counts(&self) -> Vec<(Pattern, usize)> {
+        let mut counts: Vec<(Pattern, usize)> = self
+            .words
+            .keys()
+            .map(|pattern| (pattern.clone(), self.count(pattern)))
+            .collect();
+        counts.sort_unstable_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+        counts
+    }
+
+    /// Letter codes of each word of `pattern`
+    pub(crate) fn words<'a>(&'a self, pattern: &Pattern) -> impl Iterator<Item = &'a [u8]> {
+        let len = pattern.len().max(1);
+        self.words
+            .get(pattern)
+            .into_iter()
+            .flat_map(move |words| words.chunks(len))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn pattern_ignores_case_and_non_letters() {
+        assert_eq!(pattern_of("O'Clock"), pattern_of("abcabd"));
+        assert_eq!(pattern_of("O'Clock").len(), 6);
+        assert!(pattern_of("123").is_empty());
+    }
+
+    #[test]
+    fn equal_patterns_hash_the_same() {
+        let patterns: HashSet<Pattern> = ["that", "high", "abca", "noon"]
+            .into_iter()
+            .map(pattern_of)
+            .collect();
+        assert_eq!(patterns.len(), 2);
+    }
+
+    #[test]
+    fn index_groups_words_by_pattern() {
+        let dict =
+            crate::Dictionary::from_reader("that\nhigh\nnoon\nsees\nfoo\n".as_bytes()).unwrap();
+        let index = PatternIndex::new(&dict.words);
+        let abca = Pattern::of_letters(*b"abca");
+        assert_eq!(index.count(&abca), 2);
+        let words: Vec<String> = index
+            .words(&abca)
+            .map(|word| dict.alphabet().decode(word))
+            .collect();
+        assert_eq!(words.len(), 2);
+        assert!(words.contains(&"that".to_owned()) && words.contains(&"high".to_owned()));
+        assert_eq!(index.count(&pattern_of("abba")), 2);
+        assert_eq!(index.count(&pattern_of("abcd")), 0);
+        assert_eq!(index.words(&pattern_of("abcd")).count(), 0);
+    }
+
+    #[test]
+    fn counts_list_the_most_shared_patterns_first() {
+        let dict = crate::Dictionary::from_reader(
+            "that\nhigh\nnoon\nsees\nfoo\nbar\ncar\nfar\n".as_bytes(),
+        )
+        .unwrap();
+        let counts: Vec<(String, usize)> = PatternIndex::new(&dict.words)
+            .counts()
+            .into_iter()
+            .map(|(pattern, count)| (pattern.to_string(), count))
+            .collect();
+        assert_eq!(
+            counts,
+            [
+                ("ABC".to_owned(), 3),
+                ("ABBA".to_owned(), 2),
+                ("ABCA".to_owned(), 2),
+                ("ABB".to_owned(), 1),
+            ]
+        );
+    }
+}
@@ -0,0 +1,444 @@
+use crate::{filter_input_with, verify, Alphabet, Dictionary, Error, Key, R, START};
+use rand::prelude::*;
+use std::io::BufRead;
+
+/// How many times hill climbing starts over from a shuffled version of the best key so far
+const RESTARTS: usize = 20;
+
+/// How many random swaps of letters a restart makes to the best key so far
+const RESTART_SWAPS: usize = 3;
+
+/// How many random swaps of letters simulated annealing tries
+const ANNEALING_STEPS: usize = 100_000;
+
+/// Temperature which simulated annealing starts from, per quadgram of the input. It falls
+/// linearly to zero.
+const ANNEALING_START_TEMPERATURE: f64 = 0.03;
+
+/// How likely four letters are to follow each other in a language, for scoring text which has
+/// words that no dictionary has.
+///
+/// The statistics are counted from text, like a wordlist, or read from a table of quadgram
+/// counts. Counted from text, the start and end of a word are counted like letters, so that
+/// short words are scored too.
+///
+/// ```
+/// use substitution::{Alphabet, Quadgrams};
+///
+/// let text = "the weather is nice and the mother is there";
+/// let quadgrams = Quadgrams::from_text(text.as_bytes(), &Alphabet::english()).unwrap();
+/// assert!(quadgrams.score("other") > quadgrams.score("rtohe"));
+/// ```
+#[derive(Clone)]
+pub struct Quadgrams {
+    alphabet: Alphabet,
+    /// Base 10 logarithm of the probability of each quadgram, indexed by [`Quadgrams::index`]
+    log_probs: Vec<f32>,
+}
+
+impl Quadgrams {
+    /// Count quadgrams of the words of `text`, normalized like the input of
+    /// [`decrypt`](crate::decrypt)
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LoadDictionary`] if reading fails or a line is not valid UTF-8, and
+    /// [`Error::DictionaryEmpty`] if there are no words.
+    pub fn from_text(text: impl BufRead, alphabet: &Alphabet) -> Result<Self, Error> {
+        let mut counts = vec![0; Self::symbols(alphabet).pow(4)];
+        for line in text.split(b'\n') {
+            let line = line?;
+            let line = std::str::from_utf8(&line)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            let line = filter_input_with(line, None, alphabet);
+            for word in line.split(u8::is_ascii_whitespace) {
+                Self::for_each(alphabet, word, |idx| counts[idx] += 1);
+            }
+        }
+        Self::from_counts(alphabet, &counts).ok_or(Error::DictionaryEmpty)
+    }
+
+    /// Count quadgrams of the words of `dict`, each word once
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::DictionaryEmpty`] if every word is a single letter, so there are no
+    /// quadgrams.
+    pub fn from_dictionary(dict: &Dictionary) -> Result<Self, Error> {
+        let alphabet = dict.alphabet();
+        let mut counts = vec![0; Self::symbols(alphabet).pow(4)];
+        let mut word = Vec::new();
+        dict.words.for_each(|key| {
+            word.clear();
+            word.extend(key.iter().filter_map(|c| u8::try_from(*c).ok()));
+            Self::for_each(alphabet, &word, |idx| counts[idx] += 1);
+        });
+        Self::from_counts(alphabet, &counts).ok_or(Error::DictionaryEmpty)
+    }
+
+    /// Read a table of quadgram counts, with a quadgram of letters of `alphabet` in either case
+    /// and its count on each line, like `TION 13168375`. Empty lines and lines starting with `#`
+    /// are skipped.
+    ///
+    /// ```
+    /// use substitution::{Alphabet, Quadgrams};
+    ///
+    /// let table = "# Quadgram counts\nTHER 90\ntion 10\n";
+    /// let quadgrams = Quadgrams::from_table(table.as_bytes(), &Alphabet::english()).unwrap();
+    /// assert!(quadgrams.score("there") > quadgrams.score("nation"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::LoadDictionary`] if reading fails, and [`Error::InvalidNgrams`] if a
+    /// line isn't a quadgram and a count or if there are no counts.
+    pub fn from_table(table: impl BufRead, alphabet: &Alphabet) -> Result<Self, Error> {
+        let symbols = Self::symbols(alphabet);
+        let mut counts = vec![0; symbols.pow(4)];
+        for (number, line) in (1..).zip(table.lines()) {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || Error::InvalidNgrams(format!("line {number}: \"{line}\""));
+            let (quadgram, count) = line.split_once(char::is_whitespace).ok_or_else(invalid)?;
+            let count: u64 = count.trim().parse().map_err(|_| invalid())?;
+            let codes: Vec<u8> = quadgram
+                .chars()
+                .map(|c| alphabet.code(c))
+                .collect::<Option<_>>()
+                .filter(|codes: &Vec<u8>| codes.len() == 4)
+                .ok_or_else(invalid)?;
+            counts[Self::index(symbols, codes.iter().map(|c| usize::from(c - START)))] += count;
+        }
+        Self::from_counts(alphabet, &counts)
+            .ok_or_else(|| Error::InvalidNgrams("no quadgrams".to_owned()))
+    }
+
+    /// Logarithms of the probabilities of `counts`, or `None` if there are none. Quadgrams which
+    /// weren't seen get a probability a lot smaller than any which were.
+    #[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+    fn from_counts(alphabet: &Alphabet, counts: &[u64]) -> Option<Self> {
+        let total = counts.iter().sum::<u64>() as f64;
+        if total == 0. {
+            return None;
+        }
+        let floor = (0.01 / total).log10() as f32;
+        Some(Self {
+            alphabet: alphabet.clone(),
+            log_probs: counts
+                .iter()
+                .map(|count| match count {
+                    0 => floor,
+                    count => (*count as f64 / total).log10() as f32,
+                })
+                .collect(),
+        })
+    }
+
+    /// The alphabet of the quadgrams
+    #[must_use]
+    pub fn alphabet(&self) -> &Alphabet {
+        &self.alphabet
+    }
+
+    /// Check that the quadgrams are in `alphabet`, the alphabet of a dictionary
+    pub(crate) fn check_alphabet(&self, alphabet: &Alphabet) -> Result<(), Error> {
+        if &self.alphabet == alphabet {
+            Ok(())
+        } else {
+            Err(Error::InvalidNgrams(format!(
+                "alphabet {} isn't the dictionary's {alphabet}",
+                self.alphabet
+            )))
+        }
+    }
+
+    /// Number of letters in `alphabet` and the word boundary
+    fn symbols(alphabet: &Alphabet) -> usize {
+        alphabet.len() + 1
+    }
+
+    /// Index of a quadgram of symbols in the table
+    fn index(symbols: usize, quadgram: impl Iterator<Item = usize>) -> usize {
+        quadgram.fold(0, |idx, symbol| idx * symbols + symbol)
+    }
+
+    /// Call `f` with the index of each quadgram of a word of letter codes, with the word boundary
+    /// around the word. Words with codes outside `alphabet` have no quadgrams.
+    fn for_each(alphabet: &Alphabet, word: &[u8], mut f: impl FnMut(usize)) {
+        if word.is_empty()
+            || word
+                .iter()
+                .any(|c| usize::from(c.wrapping_sub(START)) >= alphabet.len())
+        {
+            return;
+        }
+        let symbols = Self::symbols(alphabet);
+        let boundary = alphabet.len();
+        let padded: Vec<usize> = std::iter::once(boundary)
+            .chain(word.iter().map(|c| usize::from(c - START)))
+            .chain(std::iter::once(boundary))
+            .collect();
+        for quadgram in padded.windows(4) {
+            f(Self::index(symbols, quadgram.iter().copied()));
+        }
+    }
+
+    /// Sum of the logarithms of the probabilities of the quadgrams of the words of `text`,
+    /// normalized like the input of [`decrypt`](crate::decrypt). Higher is more like the
+    /// language.
+    #[must_use]
+    pub fn score(&self, text: &str) -> f64 {
+        let text = filter_input_with(text, None, &self.alphabet);
+        text.split(u8::is_ascii_whitespace)
+            .map(|word| self.score_word(word))
+            .sum()
+    }
+
+    /// Score of one word of letter codes, see [`Quadgrams::score`]
+    fn score_word(&self, word: &[u8]) -> f64 {
+        let mut score = 0.;
+        Self::for_each(&self.alphabet, word, |idx| {
+            score += f64::from(self.log_probs[idx]);
+        });
+        score
+    }
+}
+
+impl std::fmt::Debug for Quadgrams {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Quadgrams")
+            .field("alphabet", &self.alphabet)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Input words, and keys from ciphertext letters to plaintext letters which every letter of the
+/// alphabet is mapped in, scored with quadgrams
+struct Scorer<'a> {
+    quadgrams: &'a Quadgrams,
+    dict: &'a Dictionary,
+    /// Distinct words of the input and how many times each occurs
+    words: Vec<(&'a [u8], usize)>,
+    /// Indices of the ciphertext letters which the input has. Swapping the plaintext letters of
+    /// two letters which it doesn't have changes nothing.
+    present: Vec<usize>,
+    /// Indices of the ciphertext letters which aren't pinned, the only ones which are swapped
+    movable: Vec<usize>,
+}
+
+impl<'a> Scorer<'a> {
+    fn new(input: &'a [u8], start: &Key, quadgrams: &'a Quadgrams, dict: &'a Dictionary) -> Self {
+        let movable: Vec<u8> = quadgrams
+            .alphabet
+            .codes()
+            .filter(|c| !start.pinned.contains(c - START))
+            .collect();
+        Self {
+            quadgrams,
+            dict,
+            words: verify::count_words(input),
+            present: movable
+                .iter()
+                .filter(|c| input.contains(c))
+                .map(|c| Key::index(*c))
+                .collect(),
+            movable: movable.into_iter().map(Key::index).collect(),
+        }
+    }
+
+    /// Table of `key` with the letters which it leaves unmapped given the plaintext letters
+    /// which it doesn't use
+    fn complete(&self, key: &Key) -> [u8; R] {
+        let letters = self.quadgrams.alphabet.len();
+        let mut table = key.table;
+        let used = table;
+        let unused = (self.quadgrams.alphabet.codes()).filter(|c| !used[..letters].contains(c));
+        for (entry, plain) in table[..letters]
+            .iter_mut()
+            .filter(|entry| **entry == 0)
+            .zip(unused)
+        {
+            *entry = plain;
+        }
+        table
+    }
+
+    /// Score of the input deciphered with `table`, which maps every letter
+    #[allow(clippy::cast_precision_loss)]
+    fn score(&self, table: &[u8; R]) -> f64 {
+        verify::verify_key(table, &self.words, self.dict, |word, _| {
+            self.quadgrams.score_word(word.letters) * word.count as f64
+        })
+    }
+
+    /// A random pair of letters to swap, at least one of which the input has and neither of which
+    /// is pinned
+    fn random_swap(&self, rng: &mut impl Rng) -> Option<(usize, usize)> {
+        let a = *self.present.choose(rng)?;
+        let b = *self.movable.iter().filter(|b| **b != a).choose(rng)?;
+        Some((a, b))
+    }
+
+    /// Number of quadgrams which the input has
+    #[allow(clippy::cast_precision_loss)]
+    fn quadgram_count(&self) -> f64 {
+        self.words
+            .iter()
+            .map(|(word, count)| (word.len().saturating_sub(1) * count) as f64)
+            .sum()
+    }
+}
+
+/// Improve `start`, a key from ciphertext letters to plaintext letters, by swapping the
+/// plaintext letters of two ciphertext letters at a time while that makes `input` score better
+/// with `quadgrams`. The climb is started over a number of times from a shuffled version of the
+/// best key, because it can get stuck in a key which no single swap improves. Letters which
+/// `start` pins are never swapped. Keys are scored by deciphering the words of the input with
+/// [`verify::verify_key`], which `dict` is looked up through.
+pub(crate) fn hill_climb(
+    input: &[u8],
+    start: &Key,
+    quadgrams: &Quadgrams,
+    dict: &Dictionary,
+) -> Key {
+    let scorer = Scorer::new(input, start, quadgrams, dict);
+    let table = scorer.complete(start);
+    let (present, movable) = (scorer.present.clone(), scorer.movable.clone());
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut best = (scorer.score(&table), table);
+    for restart in 0..=RESTARTS {
+        let mut table = best.1;
+        if restart > 0 {
+            for _ in 0..RESTART_SWAPS {
+                let Some((a, b)) = scorer.random_swap(&mut rng) else {
+                    break;
+                };
+                table.swap(a, b);
+            }
+        }
+        let mut current = scorer.score(&table);
+        let mut improved = true;
+        while improved {
+            improved = false;
+            for a in &present {
+                for &b in movable
+                    .iter()
+                    .filter(|b| *b != a && !(present.contains(b) && *b < a))
+                {
+                    table.swap(*a, b);
+                    let swapped = scorer.score(&table);
+                    if swapped > current {
+                        current = swapped;
+                        improved = true;
+                    } else {
+                        table.swap(*a, b);
+                    }
+                }
+            }
+        }
+        if current > best.0 {
+            best = (current, table);
+        }
+    }
+    Key::from_table(best.1, quadgrams.alphabet.clone())
+}
+
+/// Improve `start` like [`hill_climb`], but by simulated annealing: random swaps of two letters
+/// are kept if they make the score better, and also if they make it worse with a chance which
+/// shrinks as the temperature falls, so that the search can get out of keys which no single
+/// swap improves early on. The best key seen is returned.
+pub(crate) fn anneal(input: &[u8], start: &Key, quadgrams: &Quadgrams, dict: &Dictionary) -> Key {
+    let scorer = Scorer::new(input, start, quadgrams, dict);
+    let mut table = scorer.complete(start);
+    let mut current = scorer.score(&table);
+    let mut best = (current, table);
+
+    // The score is a sum over quadgrams, so the temperature scales with their number
+    let start_temperature = ANNEALING_START_TEMPERATURE * scorer.quadgram_count().max(1.);
+    let mut rng = StdRng::seed_from_u64(0);
+    for step in 0..ANNEALING_STEPS {
+        let Some((a, b)) = scorer.random_swap(&mut rng) else {
+            break;
+        };
+        #[allow(clippy::cast_precision_loss)]
+        let temperature = start_temperature * (1. - step as f64 / ANNEALING_STEPS as f64);
+        table.swap(a, b);
+        let swapped = scorer.score(&table);
+        let delta = swapped - current;
+        if delta >= 0. || rng.gen::<f64>() < (delta / temperature).exp() {
+            current = swapped;
+            if current > best.0 {
+                best = (current, table);
+            }
+        } else {
+            table.swap(a, b);
+        }
+    }
+    Key::from_table(best.1, quadgrams.alphabet.clone())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{testgen, Algorithm, SolverConfig};
+
+    #[test]
+    fn climbing_deciphers_words_outside_dictionary() {
+        let english = Alphabet::english();
+        let dict = Dictionary::from_reader(testgen::CORPUS.as_bytes()).unwrap();
+        let quadgrams = Quadgrams::from_text(testgen::CORPUS.as_bytes(), &english).unwrap();
+        let plaintext = crate::normalize::Normalizer::default()
+            .normalize(testgen::CORPUS)
+            .replace("town", "qwertyville")
+            .replace("the room", "hobbes");
+        let key = Key::random_in(&english, &mut StdRng::seed_from_u64(1));
+        let ciphertext = key.encipher(&plaintext);
+
+        let solution = dict
+            .hill_climb(&ciphertext, &quadgrams, &SolverConfig::new())
+            .unwrap();
+        assert_eq!(solution.plaintext, plaintext);
+        assert!(solution.score < 1.);
+    }
+
+    #[test]
+    fn annealing_is_selected_by_config() {
+        let english = Alphabet::english();
+        let dict = Dictionary::from_reader(testgen::CORPUS.as_bytes()).unwrap();
+        let plaintext = crate::normalize::Normalizer::default()
+            .normalize(testgen::CORPUS)
+            .replace("town", "qwertyville");
+        let key = Key::random_in(&english, &mut StdRng::seed_from_u64(2));
+        let ciphertext = key.encipher(&plaintext);
+
+        let config = SolverConfig::new().algorithm(Algorithm::Annealing);
+        let solution = dict.decrypt_full(&ciphertext, &config).unwrap();
+        assert_eq!(solution.plaintext, plaintext);
+        assert!(solution.score < 1.);
+
+        let finnish = Quadgrams::from_text("hyvää yötä".as_bytes(), &Alphabet::finnish()).unwrap();
+        let result = dict.decrypt_full(&ciphertext, &config.quadgrams(finnish));
+        assert!(matches!(result, Err(Error::InvalidNgrams(_))));
+    }
+
+    #[test]
+    fn table_lines_are_checked() {
+        let english = Alphabet::english();
+        for (table, problem) in [
+            ("tion", "line 1: \"tion\""),
+            ("the 5", "line 1: \"the 5\""),
+            ("\nth1s 5", "line 2: \"th1s 5\""),
+            ("tion x", "line 1: \"tion x\""),
+            ("# Nothing", "no quadgrams"),
+        ] {
+            match Quadgrams::from_table(table.as_bytes(), &english) {
+                Err(Error::InvalidNgrams(message)) => assert_eq!(message, problem),
+                result => panic!("{table}: {result:?}"),
+            }
+        }
+    }
+}
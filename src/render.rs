@@ -0,0 +1,342 @@
+//! Output of the command line program, written out for people and programs to read. The formats
+//! are checked against the snapshots in `src/snapshots`, so that changes to them are deliberate.
+
+use crate::{Alphabet, Estimate, Key, Language};
+use std::fmt::Write;
+
+/// Characters of ciphertext on each row of a printed puzzle. They are spaced out, so a row takes
+/// about twice as many columns.
+const ROW_WIDTH: usize = 30;
+
+/// Letters of the alphabet of `key` in the collation order of `language`, see
+/// [`Language::collation`]. Letters which the language doesn't have come last, in the order of the
+/// alphabet.
+fn collated_letters(key: &Key, language: &Language) -> Vec<char> {
+    let letters = key.alphabet.letters();
+    let mut collated: Vec<char> = language
+        .collation()
+        .into_iter()
+        .filter(|c| letters.contains(c))
+        .collect();
+    let rest: Vec<char> = letters
+        .iter()
+        .filter(|c| !collated.contains(c))
+        .copied()
+        .collect();
+    collated.extend(rest);
+    collated
+}
+
+/// Writes `key` as a table of two rows: the plaintext letters in the collation order of
+/// `language`, and the ciphertext letter which each is substituted with under it. Letters which
+/// the key doesn't substitute are written as `_`.
+///
+/// ```
+/// use substitution::{Alphabet, Key, Language};
+///
+/// let key = Key::identity_in(&Alphabet::german());
+/// let table = substitution::key_table(&key, &Language::German);
+/// assert!(table.starts_with("plain  a ä b c"));
+/// assert!(table.lines().nth(1).unwrap().starts_with("cipher a ä b c"));
+/// ```
+#[must_use]
+pub fn key_table(key: &Key, language: &Language) -> String {
+    let plain = collated_letters(key, language);
+    let cipher: Vec<char> = plain
+        .iter()
+        .map(|letter| {
+            key.alphabet
+                .code(*letter)
+                .and_then(|code| key.alphabet.letter(key.table[Key::index(code)]))
+                .unwrap_or('_')
+        })
+        .collect();
+    let row = |letters: &[char]| {
+        letters
+            .iter()
+            .map(char::to_string)
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    format!("plain  {}\ncipher {}", row(&plain), row(&cipher))
+}
+
+/// Lays `ciphertext` out as a printable puzzle: rows of spaced out ciphertext, each with a blank
+/// answer row under it, where every letter of `alphabet` is a `_` to fill in. Rows are broken
+/// between words, keeping the lines of the ciphertext. With `group`, the letters are written in
+/// groups of that many instead of words, hiding word boundaries. `hints` of a ciphertext letter
+/// and its plaintext letter are stated above the puzzle and filled in on the answer rows, see
+/// [`Dictionary::suggest_hints`](crate::Dictionary::suggest_hints).
+///
+/// ```
+/// use substitution::Alphabet;
+///
+/// let puzzle = substitution::puzzle_layout("Ifmmp", &Alphabet::english(), None, &[]);
+/// assert_eq!(puzzle, "I f m m p\n_ _ _ _ _");
+/// ```
+#[must_use]
+pub fn puzzle_layout(
+    ciphertext: &str,
+    alphabet: &Alphabet,
+    group: Option<usize>,
+    hints: &[(char, char)],
+) -> String {
+    let lines: Vec<Vec<String>> = match group {
+        Some(size) => {
+            let letters: Vec<char> = ciphertext
+                .chars()
+                .filter(|c| alphabet.contains(*c))
+                .collect();
+            vec![letters
+                .chunks(size.max(1))
+                .map(|group| group.iter().collect())
+                .collect()]
+        }
+        None => ciphertext
+            .lines()
+            .map(|line| line.split_whitespace().map(str::to_owned).collect())
+            .collect(),
+    };
+
+    // Every other character is a space, so that letters can be written under each letter
+    let spaced = |row: &str| row.chars().map(String::from).collect::<Vec<_>>().join(" ");
+    let answer = |c: char| {
+        let hint = hints
+            .iter()
+            .find(|(cipher, _)| c.to_lowercase().eq(cipher.to_lowercase()));
+        match hint {
+            Some((_, plain)) if c.is_uppercase() => plain.to_uppercase().collect(),
+            Some((_, plain)) => plain.to_string(),
+            None if alphabet.contains(c) => "_".to_owned(),
+            None => c.to_string(),
+        }
+    };
+
+    let mut puzzle = Vec::new();
+    for (cipher, plain) in hints {
+        puzzle.push(format!("Hint: '{cipher}' deciphers to '{plain}'"));
+    }
+    if !hints.is_empty() {
+        puzzle.push(String::new());
+    }
+    for words in lines {
+        if words.is_empty() {
+            puzzle.push(String::new());
+            continue;
+        }
+        let mut rows: Vec<String> = Vec::new();
+        for word in words {
+            match rows.last_mut() {
+                Some(row) if row.chars().count() + 1 + word.chars().count() <= ROW_WIDTH => {
+                    row.push(' ');
+                    row.push_str(&word);
+                }
+                _ => rows.push(word),
+            }
+        }
+        for row in rows {
+            puzzle.push(spaced(&row));
+            puzzle.push(spaced(&row.chars().map(answer).collect::<String>()));
+            puzzle.push(String::new());
+        }
+    }
+    puzzle.join("\n").trim_end().to_owned()
+}
+
+/// The answer key of a puzzle of `plaintext` enciphered with `key`: the label and notes of the
+/// key, the plaintext, the key and the key as a table in the order which `language` sorts letters
+/// in
+#[must_use]
+pub fn answer_key(plaintext: &str, key: &Key, language: &Language) -> String {
+    let metadata = match key.metadata() {
+        metadata if metadata.is_empty() => String::new(),
+        metadata => format!("{metadata}\n\n"),
+    };
+    format!(
+        "{metadata}Solution:\n{}\n\nKey: {key}\n{}\n",
+        plaintext.trim_end(),
+        key_table(key, language)
+    )
+}
+
+/// Report of a dry run: the size of the job which `estimate` describes, deciphering with the
+/// dictionary named `dictionary`, followed by its warnings
+#[must_use]
+pub fn estimate_report(dictionary: &str, estimate: &Estimate) -> String {
+    let mut report = format!(
+        "Dictionary: {dictionary} ({} words)\n\
+         Input: {} words, {} distinct letters\n\
+         Search space: up to 10^{:.1} keys, in up to {} rounds",
+        estimate.dictionary_words,
+        estimate.words,
+        estimate.distinct_letters,
+        estimate.keys_log10,
+        estimate.rounds,
+    );
+    for warning in &estimate.warnings {
+        let _ = write!(report, "\nWarning: {warning}");
+    }
+    report
+}
+
+/// A progress event, which the command line program writes as a line of JSON for other programs
+/// to follow a decryption
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct ProgressEvent {
+    /// What the program is doing: "load", "search", "done" or "failed"
+    pub phase: &'static str,
+    /// Words placed so far at best, or in the result when done
+    pub words_solved: Option<usize>,
+    /// Words to place, if known
+    pub words: Option<usize>,
+    /// Guesses tested so far, if searching
+    pub nodes: Option<usize>,
+    /// Seconds since the program started
+    pub elapsed: f64,
+}
+
+impl ProgressEvent {
+    /// Event of `phase` without counts, `elapsed` seconds after the program started
+    #[must_use]
+    pub fn new(phase: &'static str, elapsed: f64) -> Self {
+        Self {
+            phase,
+            words_solved: None,
+            words: None,
+            nodes: None,
+            elapsed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Warning;
+
+    #[test]
+    fn letters_the_language_doesnt_sort_come_last() {
+        let key = Key::identity_in(&Alphabet::new("zäa").unwrap());
+        assert_eq!(collated_letters(&key, &Language::English), ['a', 'z', 'ä']);
+        assert_eq!(collated_letters(&key, &Language::German), ['a', 'ä', 'z']);
+
+        let key = Key::from_letters("b_", &Alphabet::new("ab").unwrap()).unwrap();
+        assert_eq!(
+            key_table(&key, &Language::English),
+            "plain  a b\ncipher b _"
+        );
+    }
+
+    #[test]
+    fn key_tables() {
+        let key: Key = "bcdefghijklmnopqrstuvwxyza".parse().unwrap();
+        insta::assert_snapshot!("key_table_english", key_table(&key, &Language::English));
+        let key = Key::identity_in(&Alphabet::finnish());
+        insta::assert_snapshot!("key_table_finnish", key_table(&key, &Language::Finnish));
+    }
+
+    #[test]
+    fn puzzles() {
+        let english = Alphabet::english();
+        let ciphertext = "Ifmmp, xpsme!\n\nUijt jt b qvaamf xjui mpoh mjoft pg xpset";
+        insta::assert_snapshot!(
+            "puzzle_words",
+            puzzle_layout(ciphertext, &english, None, &[])
+        );
+        insta::assert_snapshot!(
+            "puzzle_hint",
+            puzzle_layout(ciphertext, &english, None, &[('m', 'l'), ('x', 'w')])
+        );
+        insta::assert_snapshot!(
+            "puzzle_groups",
+            puzzle_layout(ciphertext, &english, Some(5), &[('i', 'h')])
+        );
+    }
+
+    #[test]
+    fn long_lines_are_broken_between_words() {
+        let line = "abcde ".repeat(10);
+        let puzzle = puzzle_layout(&format!("{line}\n\nxyz"), &Alphabet::english(), None, &[]);
+        let rows: Vec<&str> = puzzle.lines().collect();
+        // Five words fit a row, and the blank line of the ciphertext is kept
+        assert_eq!(rows.len(), 2 * 3 + 1 + 2);
+        assert_eq!(rows[0], spaced_words(5));
+        assert_eq!(rows[3], spaced_words(5));
+        assert_eq!(rows[6], "");
+        assert_eq!(rows[7], "x y z");
+        assert!(rows.iter().all(|row| row.chars().count() < 2 * ROW_WIDTH));
+    }
+
+    /// `words` words of "abcde" as a spaced out row
+    fn spaced_words(words: usize) -> String {
+        vec!["a b c d e"; words].join("   ")
+    }
+
+    #[test]
+    fn answer_keys() {
+        let key: Key = "bcdefghijklmnopqrstuvwxyza".parse().unwrap();
+        insta::assert_snapshot!(
+            "answer_key",
+            answer_key("Hello, world!\n", &key, &Language::English)
+        );
+        let key = key.with_metadata(
+            crate::Metadata::new()
+                .label("puzzle 12")
+                .note("From the Sunday paper\nRated hard"),
+        );
+        insta::assert_snapshot!(
+            "answer_key_metadata",
+            answer_key("Hello, world!\n", &key, &Language::English)
+        );
+    }
+
+    #[test]
+    fn estimate_reports() {
+        let estimate = Estimate {
+            words: 12,
+            distinct_letters: 15,
+            dictionary_words: 10_000,
+            keys_log10: 19.123,
+            rounds: 3,
+            warnings: vec![],
+        };
+        insta::assert_snapshot!("estimate", estimate_report("words.txt", &estimate));
+        let estimate = Estimate {
+            warnings: vec![
+                Warning::InputTooShort {
+                    distinct_letters: 9,
+                    words: 2,
+                },
+                Warning::UnusualLetterFrequencies { similarity: 40 },
+            ],
+            ..estimate
+        };
+        insta::assert_snapshot!(
+            "estimate_warnings",
+            estimate_report("English (built in)", &estimate)
+        );
+    }
+
+    #[test]
+    fn progress_events() {
+        let events = [
+            ProgressEvent::new("load", 0.25),
+            ProgressEvent {
+                words_solved: Some(3),
+                words: Some(8),
+                nodes: Some(1200),
+                ..ProgressEvent::new("search", 1.5)
+            },
+            ProgressEvent {
+                words_solved: Some(8),
+                ..ProgressEvent::new("done", 2.0)
+            },
+            ProgressEvent::new("failed", 2.0),
+        ];
+        let lines: Vec<String> = events
+            .iter()
+            .map(|event| serde_json::to_string(event).unwrap())
+            .collect();
+        insta::assert_snapshot!("progress_events", lines.join("\n"));
+    }
+}
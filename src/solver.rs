@@ -0,0 +1,2724 @@
+use crate::{
+    beam, bitset, filter_input_with, quadgrams, skip_cost, source::WordSource, tuning,
+    unique_chars, verify, Alphabet, Candidate, CandidateStats, Case, Dictionary, Error, Key,
+    LanguageFreqs, Metadata, Quadgrams, Solution, SolverConfig, R, START,
+};
+use std::collections::{HashMap, HashSet};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::time::{Duration, Instant};
+
+/// How many search steps, like testing a guess, are taken between looking at the clock
+const STEPS_PER_CLOCK_CHECK: usize = 1024;
+
+/// Most failed states which a [`Search`] remembers, after which it forgets them and starts over
+const MAX_FAILED_STATES: usize = 1 << 20;
+
+/// Most ways of placing the cribs which are searched with, see [`SolverConfig::crib`]
+const MAX_CRIB_PLACEMENTS: usize = 64;
+
+/// Least time between progress reports, see [`SolverConfig::on_progress`]
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long a branch of a parallel search is searched between checking if it can be stopped
+#[cfg(feature = "rayon")]
+const BRANCH_SLICE: Duration = Duration::from_millis(10);
+
+/// A way of deciphering text with a dictionary, which [`Dictionary::decrypt_full`] and the
+/// functions built on it use when it's given to [`SolverConfig::strategy`].
+///
+/// The built-in algorithms are strategies too, so a strategy of your own can try one of them
+/// first. Build its solutions from keys with [`Solution::from_key`].
+///
+/// ```
+/// use substitution::{Algorithm, Dictionary, Error, Key, Solution, SolverConfig, Strategy};
+///
+/// /// Tries the identity key before searching
+/// #[derive(Debug)]
+/// struct IdentityFirst;
+///
+/// impl Strategy for IdentityFirst {
+///     fn solve(&self, input: &str, dict: &Dictionary, config: &SolverConfig) -> Result<Solution, Error> {
+///         let solution = Solution::from_key(input, &Key::identity(), dict, config)?;
+///         if solution.score == 1. {
+///             return Ok(solution);
+///         }
+///         Algorithm::Backtracking.solve(input, dict, config)
+///     }
+/// }
+///
+/// let dict = Dictionary::from_reader("hello\nworld\n".as_bytes()).unwrap();
+/// let config = SolverConfig::new().strategy(IdentityFirst);
+/// let solution = dict.decrypt_full("Hello, world!", &config).unwrap();
+/// assert_eq!(solution.algorithm, None);
+/// assert_eq!(dict.decrypt_full("ifmmp xpsme", &config).unwrap().plaintext, "hello world");
+/// ```
+pub trait Strategy: std::fmt::Debug + Send + Sync {
+    /// Decipher `input` with `dict`, configured by `config`
+    ///
+    /// # Errors
+    ///
+    /// See [`enum@Error`].
+    fn solve(
+        &self,
+        input: &str,
+        dict: &Dictionary,
+        config: &SolverConfig,
+    ) -> Result<Solution, Error>;
+}
+
+/// Built-in algorithms for searching for the key, see [`SolverConfig::algorithm`]
+///
+/// ```
+/// use substitution::Algorithm;
+///
+/// assert_eq!("annealing".parse::<Algorithm>().unwrap(), Algorithm::Annealing);
+/// assert_eq!(Algorithm::default(), Algorithm::Backtracking);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum Algorithm {
+    /// Guess words one at a time and go back when a guess leaves no dictionary word for a later
+    /// word. Finds a key which deciphers every word into a dictionary word, if there is one.
+    #[default]
+    Backtracking,
+    /// Swap letters of the letter frequency key while the plaintext scores better with
+    /// quadgrams, like [`Dictionary::hill_climb`]
+    HillClimbing,
+    /// Swap random letters of the letter frequency key, keeping swaps which make the plaintext
+    /// score worse with quadgrams less and less often. Gets stuck less than hill climbing, but
+    /// isn't always as accurate either.
+    Annealing,
+    /// Search by backtracking, and if that fails or runs out of budget, anneal instead. Works
+    /// for any input, but the key is only certain when [`Solution::algorithm`] is
+    /// [`Algorithm::Backtracking`].
+    Auto,
+    /// Guess words one at a time like backtracking, but keep the best few partial keys after
+    /// each word instead of going back, see [`SolverConfig::beam_width`]. Takes about the same
+    /// time for every input of the same length, but can miss the key.
+    Beam,
+}
+
+impl Algorithm {
+    /// Every algorithm
+    pub const ALL: [Self; 5] = [
+        Self::Backtracking,
+        Self::HillClimbing,
+        Self::Annealing,
+        Self::Auto,
+        Self::Beam,
+    ];
+}
+
+impl Strategy for Algorithm {
+    fn solve(
+        &self,
+        input: &str,
+        dict: &Dictionary,
+        config: &SolverConfig,
+    ) -> Result<Solution, Error> {
+        Solver::with_algorithm(dict, input, config, *self)?.finish()
+    }
+}
+
+impl std::fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Backtracking => "backtracking",
+            Self::HillClimbing => "hill-climbing",
+            Self::Annealing => "annealing",
+            Self::Auto => "auto",
+            Self::Beam => "beam",
+        })
+    }
+}
+
+impl std::str::FromStr for Algorithm {
+    type Err = Error;
+
+    /// Parse the name of an algorithm, like `hill-climbing`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.to_string().eq_ignore_ascii_case(s))
+            .ok_or(Error::UnknownStrategy)
+    }
+}
+
+/// Order in which backtracking deciphers the words of the input, see
+/// [`SolverConfig::word_ordering`]
+///
+/// ```
+/// use substitution::WordOrdering;
+///
+/// assert_eq!("target".parse::<WordOrdering>().unwrap(), WordOrdering::Target);
+/// assert_eq!(WordOrdering::default(), WordOrdering::Overlap);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum WordOrdering {
+    /// Start from the word whose number of distinct letters is closest to the word order target,
+    /// and go on with the word which has the most letters that the words before it have, less its
+    /// distance from the target. The most constrained words come first, so that a wrong guess is
+    /// found out soon.
+    #[default]
+    Overlap,
+    /// Words in order of how close their number of distinct letters is to the word order
+    /// target, see [`SolverConfig::word_order_target`]
+    Target,
+}
+
+impl WordOrdering {
+    /// Every word ordering
+    pub const ALL: [Self; 2] = [Self::Overlap, Self::Target];
+
+    /// Sort `words`, which are paired with their number of distinct letters, into this order
+    fn sort(self, words: &mut Vec<(&[u8], usize)>, target: usize) {
+        words.sort_unstable_by_key(|(_, len)| len.abs_diff(target));
+        if self == Self::Target {
+            return;
+        }
+
+        // Order each distinct word once, and put its repeats right after it
+        let mut counts: HashMap<&[u8], usize> = HashMap::new();
+        let mut remaining = Vec::new();
+        for (word, len) in words.iter() {
+            let count = counts.entry(word).or_default();
+            if *count == 0 {
+                let mut letters = bitset::BitSet64::<1>::new();
+                for c in *word {
+                    letters.insert(c - START);
+                }
+                remaining.push((*word, *len, letters));
+            }
+            *count += 1;
+        }
+        let mut known = bitset::BitSet64::<1>::new();
+        words.clear();
+        while !remaining.is_empty() {
+            // The most letters known by the words before, less the distance from the target. Ties
+            // keep the earlier word, which is closer to the target.
+            let mut best = 0;
+            let mut best_score = None;
+            for (idx, (_, len, letters)) in remaining.iter().enumerate() {
+                let mut shared = *letters;
+                shared.intersect(&known);
+                let (shared, distance) = (shared.len() as usize, len.abs_diff(target));
+                if best_score.is_none_or(|(best_shared, best_distance)| {
+                    shared + best_distance > best_shared + distance
+                }) {
+                    best = idx;
+                    best_score = Some((shared, distance));
+                }
+            }
+            let (word, len, _) = remaining.remove(best);
+            for c in word {
+                known.insert(c - START);
+            }
+            words.extend(std::iter::repeat_n((word, len), counts[word]));
+        }
+    }
+}
+
+impl std::fmt::Display for WordOrdering {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Overlap => "overlap",
+            Self::Target => "target",
+        })
+    }
+}
+
+impl std::str::FromStr for WordOrdering {
+    type Err = Error;
+
+    /// Parse the name of a word ordering, like `overlap`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|ordering| ordering.to_string().eq_ignore_ascii_case(s))
+            .ok_or(Error::UnknownWordOrdering)
+    }
+}
+
+/// What the search for a key makes the most of, by how much skipping each word costs. The search
+/// skips words which the dictionary doesn't have with a growing budget, so the first key it finds
+/// skips the least.
+///
+/// With an objective other than [`Objective::AllWords`], the best guesses found so far are
+/// returned as a partial solution when the search is stopped or exhausted, like with
+/// [`SolverConfig::anytime`].
+///
+/// ```
+/// use substitution::{Objective, SolverConfig};
+///
+/// let config = SolverConfig::new().objective(Objective::MatchedWords).max_backtracks(0);
+/// let dict = "hello\nworld\n".as_bytes();
+/// let solution = substitution::decrypt_full("ifmmp xpsme zzzzy", dict, &config).unwrap();
+/// assert_eq!(solution.matched_words, 2);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum Objective {
+    /// Decipher every word into a dictionary word. Skipping short words, see
+    /// [`SolverConfig::short_word_len`], and words of a pattern which hardly any dictionary word
+    /// has costs half as much as skipping other words, and if the search is stopped before it
+    /// finds a key, it fails.
+    #[default]
+    AllWords,
+    /// Decipher as many words into dictionary words as possible, every word counting the same
+    MatchedWords,
+    /// Decipher as many letters of words into dictionary words as possible, so that long words
+    /// count for more than short ones
+    MatchedLetters,
+}
+
+impl Objective {
+    /// Every objective
+    pub const ALL: [Self; 3] = [Self::AllWords, Self::MatchedWords, Self::MatchedLetters];
+
+    /// How much skipping `word` costs from the skip budget, and how much matching it is worth,
+    /// before [`Search::weigh_skips`] looks at the dictionary
+    fn skip_cost(self, word: &[u8]) -> usize {
+        match self {
+            Self::AllWords => skip_cost(word, crate::SHORT_WORD_LEN, usize::MAX),
+            Self::MatchedWords => 1,
+            Self::MatchedLetters => word.len(),
+        }
+    }
+}
+
+impl std::fmt::Display for Objective {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::AllWords => "all-words",
+            Self::MatchedWords => "matched-words",
+            Self::MatchedLetters => "matched-letters",
+        })
+    }
+}
+
+impl std::str::FromStr for Objective {
+    type Err = Error;
+
+    /// Parse the name of an objective, like `matched-words`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|objective| objective.to_string().eq_ignore_ascii_case(s))
+            .ok_or(Error::UnknownObjective)
+    }
+}
+
+/// Deciphers one input with a [`Dictionary`] a slice of time at a time, so that the search can be
+/// interleaved with other work on the same thread, like updating a user interface.
+///
+/// ```
+/// use std::time::Duration;
+/// use substitution::{Dictionary, Solver, SolverConfig, StepResult};
+///
+/// let dict = Dictionary::from_reader("hello\nworld\n".as_bytes()).unwrap();
+/// let mut solver = Solver::new(&dict, "ifmmp xpsme", &SolverConfig::new()).unwrap();
+/// let solution = loop {
+///     match solver.step(Duration::from_millis(10)) {
+///         StepResult::Pending => { /* Redraw, handle events... */ }
+///         StepResult::Solved(solution) => break *solution,
+///         _ => panic!("No solution"),
+///     }
+/// };
+/// assert_eq!(solution.plaintext, "hello world");
+/// ```
+pub struct Solver<'a> {
+    dict: &'a Dictionary,
+    input: String,
+    filtered: Vec<u8>,
+    keep_formatting: bool,
+    case: Case,
+    fallback: Option<Key>,
+    anytime: bool,
+    algorithm: Algorithm,
+    beam_width: usize,
+    beam_width_bounds: Option<(usize, usize)>,
+    /// Quadgrams to score keys with, counted from the dictionary by the time they are needed
+    /// unless they were configured
+    quadgrams: Option<Arc<Quadgrams>>,
+    max_duration: Option<Duration>,
+    /// When the search times out, set on the first step
+    deadline: Option<Instant>,
+    /// Label and notes of the keys of the solutions
+    metadata: Metadata,
+    search: Search,
+}
+
+/// Shared flag which stops searches that were configured with it, see
+/// [`SolverConfig::cancel_token`]. Clones of a token share the flag, so one can be kept to cancel
+/// a search which runs on another thread.
+///
+/// ```
+/// use substitution::{CancelToken, Error, SolverConfig};
+///
+/// let token = CancelToken::new();
+/// let config = SolverConfig::new().cancel_token(token.clone());
+/// token.cancel();
+/// let result = substitution::decrypt_with_config("ifmmp xpsme", "hello\nworld\n".as_bytes(), &config);
+/// assert!(matches!(result, Err(Error::Cancelled)));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// Create a token which isn't cancelled
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stop the searches which use this token soon. They can't be continued afterwards.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true if [`CancelToken::cancel`] has been called
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// How far a search has got, reported to the hook of [`SolverConfig::on_progress`]
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct SearchProgress {
+    /// Number of words in the input
+    pub words: usize,
+    /// Words which the current guesses decipher into dictionary words
+    pub words_placed: usize,
+    /// Most words which any guesses so far have deciphered into dictionary words
+    pub best_words_placed: usize,
+    /// The guesses which placed the most words, as a partial key like [`Solution::key`]
+    pub best_key: Key,
+    /// Guesses tested against the dictionary so far
+    pub nodes: usize,
+    /// Times that a word has run out of guesses so far, making the search go back to earlier
+    /// words
+    pub backtracks: usize,
+}
+
+/// A function which is given the progress of a search, see [`SolverConfig::on_progress`]
+#[derive(Clone)]
+pub(crate) struct ProgressHook(pub(crate) Arc<dyn Fn(&SearchProgress) + Send + Sync>);
+
+impl std::fmt::Debug for ProgressHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ProgressHook")
+    }
+}
+
+/// State of a [`Solver`] after a slice of the search
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum StepResult {
+    /// The search isn't finished yet, step again to continue it
+    Pending,
+    /// The key was found. Stepping again gives the same solution.
+    Solved(Box<Solution>),
+    /// Every key was tried without finding one which fits the dictionary. With
+    /// [`SolverConfig::anytime`] or [`SolverConfig::fallback`], the partial or fallback solution
+    /// is given as [`StepResult::Solved`] instead.
+    Exhausted,
+    /// The search ran out of time or work budget of its [`SolverConfig`] before finding a key.
+    /// With [`SolverConfig::anytime`] or [`SolverConfig::fallback`], the partial or fallback
+    /// solution is given as [`StepResult::Solved`] instead.
+    OutOfBudget,
+    /// The search was stopped with the [`CancelToken`] of its [`SolverConfig`]
+    Cancelled,
+}
+
+impl<'a> Solver<'a> {
+    /// Prepare to decipher `input` with `dict`, configured like [`Dictionary::decrypt_full`]. No
+    /// searching is done before [`Solver::step`] is called.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnsupportedInput`] if `input` has no words to decipher,
+    /// [`Error::InputTooLong`] if it has more words than [`SolverConfig::max_words`],
+    /// [`Error::InvalidNgrams`] if the quadgrams of [`SolverConfig::quadgrams`] are in another
+    /// alphabet than `dict`, and [`Error::UnknownLetter`] or [`Error::KeyConflict`] if the letters
+    /// of [`SolverConfig::pin_letter`] aren't in its alphabet or contradict each other.
+    pub fn new(dict: &'a Dictionary, input: &str, config: &SolverConfig) -> Result<Self, Error> {
+        Self::with_algorithm(dict, input, config, config.algorithm)
+    }
+
+    /// Prepare to decipher like [`Solver::new`], with `algorithm` instead of the configured one
+    pub(crate) fn with_algorithm(
+        dict: &'a Dictionary,
+        input: &str,
+        config: &SolverConfig,
+        algorithm: Algorithm,
+    ) -> Result<Self, Error> {
+        let filtered = filter_input_with(input, config.normalizer.as_ref(), &dict.alphabet);
+        let freqs = config.language_freqs(&dict.alphabet);
+        let pins = config.pins(&dict.alphabet)?;
+        let cribs: Vec<Vec<u8>> = config
+            .cribs
+            .iter()
+            .flat_map(|crib| {
+                filter_input_with(crib, config.normalizer.as_ref(), &dict.alphabet)
+                    .split(u8::is_ascii_whitespace)
+                    .filter(|word| !word.is_empty())
+                    .map(<[u8]>::to_vec)
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        let placements = if cribs.is_empty() {
+            Vec::new()
+        } else {
+            place_cribs(&filtered, &cribs, &pins, &dict.alphabet)?
+        };
+        let fallback = config.fallback.then(|| {
+            let key = Key::frequency_rank(&filtered, &dict.alphabet, &freqs).with_pins(&pins);
+            match placements.first() {
+                Some(placement) => key.with_pins(&placement.pins),
+                None => key,
+            }
+        });
+        let mut search = Search::new(
+            &filtered,
+            &dict.alphabet,
+            freqs,
+            &config.free_skips(input, &dict.alphabet),
+            config.word_order_target,
+            config.word_ordering,
+            config.objective,
+        )?;
+        if let Some(max) = config.max_words.filter(|max| search.words.len() > *max) {
+            return Err(Error::InputTooLong {
+                words: search.words.len(),
+                max,
+            });
+        }
+        search.weigh_skips(dict, config.short_word_len);
+        let total_cost = search.free_short_words(config.min_word_len);
+        search.max_skip = config.max_skip(total_cost);
+        search.pins = pins;
+        if placements.is_empty() {
+            search.propagate(dict);
+        } else {
+            search.set_placements(placements);
+        }
+        search.budget = Budget {
+            max_nodes: config.max_nodes,
+            max_backtracks: config.max_backtracks,
+        };
+        search.cancel.clone_from(&config.cancel);
+        search.progress.clone_from(&config.progress);
+        let anytime = config.anytime || config.objective != Objective::AllWords;
+        search.track_best = anytime || config.progress.is_some();
+        let quadgrams = match (&config.quadgrams, algorithm) {
+            (Some(quadgrams), _) => Some(Arc::clone(quadgrams)),
+            (None, Algorithm::HillClimbing | Algorithm::Annealing) => {
+                Some(Arc::new(Quadgrams::from_dictionary(dict)?))
+            }
+            // The automatic algorithm counts them if backtracking fails
+            (None, _) => None,
+        };
+        if let Some(quadgrams) = &quadgrams {
+            quadgrams.check_alphabet(&dict.alphabet)?;
+        }
+        Ok(Self {
+            dict,
+            input: input.to_owned(),
+            filtered,
+            keep_formatting: config.keep_formatting,
+            case: config.case.unwrap_or(if config.keep_formatting {
+                Case::MatchInput
+            } else {
+                Case::Lower
+            }),
+            fallback,
+            anytime,
+            algorithm,
+            beam_width: config.beam_width,
+            beam_width_bounds: config.beam_width_bounds,
+            quadgrams,
+            max_duration: config.max_duration,
+            deadline: None,
+            metadata: config.metadata.clone(),
+            search,
+        })
+    }
+
+    /// Normalized input which is deciphered
+    pub(crate) fn filtered(&self) -> &[u8] {
+        &self.filtered
+    }
+
+    /// Guesses which the search has tested so far
+    pub(crate) fn nodes(&self) -> usize {
+        self.search.nodes
+    }
+
+    /// Most searches which backtracking makes, one for each skip budget and placement of the
+    /// cribs
+    pub(crate) fn rounds(&self) -> usize {
+        (self.search.max_skip + 1) * self.search.placements.len().max(1)
+    }
+
+    /// Search for about `budget` and return whether the search finished. A step may take
+    /// somewhat longer than `budget`, because the clock is only read every now and then.
+    /// With an algorithm other than [`Algorithm::Backtracking`], the first step runs the whole
+    /// search.
+    pub fn step(&mut self, budget: Duration) -> StepResult {
+        if let Some(result) = self.search_otherwise() {
+            return Self::step_result(result);
+        }
+        let timeout = self.deadline();
+        let result = if timeout.is_some_and(|timeout| Instant::now() >= timeout) {
+            Err(Error::Timeout)
+        } else {
+            let deadline = Instant::now().checked_add(budget);
+            let deadline = match (deadline, timeout) {
+                (Some(deadline), Some(timeout)) => Some(deadline.min(timeout)),
+                (deadline, timeout) => deadline.or(timeout),
+            };
+            match self.search.run(self.dict, deadline) {
+                None if timeout.is_some_and(|timeout| Instant::now() >= timeout) => {
+                    Err(Error::Timeout)
+                }
+                None => return StepResult::Pending,
+                Some(result) => result,
+            }
+        };
+        Self::step_result(self.outcome(result))
+    }
+
+    /// State of the solver after a step which finished with `result`
+    fn step_result(result: Result<Solution, Error>) -> StepResult {
+        match result {
+            Ok(solution) => StepResult::Solved(Box::new(solution)),
+            Err(Error::SearchExhausted) => StepResult::Exhausted,
+            Err(Error::Cancelled) => StepResult::Cancelled,
+            Err(_) => StepResult::OutOfBudget,
+        }
+    }
+
+    /// Search until finished
+    pub(crate) fn finish(mut self) -> Result<Solution, Error> {
+        if let Some(result) = self.search_otherwise() {
+            return result;
+        }
+        let deadline = self.deadline();
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return self.outcome(Err(Error::Timeout));
+        }
+        // The budgets and the best partial key are kept track of on one thread
+        #[cfg(feature = "rayon")]
+        if deadline.is_none() && self.search.budget == Budget::default() && !self.anytime {
+            let result = self.search.run_parallel(self.dict);
+            return self.outcome(result);
+        }
+        let result = self
+            .search
+            .run(self.dict, deadline)
+            .unwrap_or(Err(Error::Timeout));
+        self.outcome(result)
+    }
+
+    /// Decipher the input by hill climbing from the letter frequency key instead of searching, see
+    /// [`Dictionary::hill_climb`]
+    pub(crate) fn climb(&self, quadgrams: &Quadgrams) -> Solution {
+        self.improve(quadgrams::hill_climb, quadgrams, Algorithm::HillClimbing)
+    }
+
+    /// Decipher the input by simulated annealing from the letter frequency key, see
+    /// [`Algorithm::Annealing`]
+    pub(crate) fn anneal(&self, quadgrams: &Quadgrams) -> Solution {
+        self.improve(quadgrams::anneal, quadgrams, Algorithm::Annealing)
+    }
+
+    /// Decipher the input with the key which `improve` makes of the letter frequency key with the
+    /// pinned letters, or with the one which places the most words of the keys of each placement
+    /// of the cribs
+    fn improve(
+        &self,
+        improve: fn(&[u8], &Key, &Quadgrams, &Dictionary) -> Key,
+        quadgrams: &Quadgrams,
+        algorithm: Algorithm,
+    ) -> Solution {
+        let start = Key::frequency_rank(&self.filtered, &self.dict.alphabet, &self.search.freqs);
+        let solve = |pins: &[(u8, u8)]| {
+            let key = improve(
+                &self.filtered,
+                &start.clone().with_pins(pins),
+                quadgrams,
+                self.dict,
+            );
+            Solution {
+                algorithm: Some(algorithm),
+                ..self.solution(&key, false)
+            }
+        };
+        self.search
+            .seeds()
+            .iter()
+            .map(|Seed { pins, .. }| solve(pins))
+            .reduce(|best, solution| {
+                if solution.matched_words > best.matched_words {
+                    solution
+                } else {
+                    best
+                }
+            })
+            // Without seeds, only the given letters are pinned
+            .unwrap_or_else(|| solve(&self.search.pins))
+    }
+
+    /// Search with the algorithm in one go if it doesn't search by backtracking
+    fn search_otherwise(&mut self) -> Option<Result<Solution, Error>> {
+        match (self.algorithm, &self.quadgrams) {
+            (Algorithm::HillClimbing, Some(quadgrams)) => Some(Ok(self.climb(quadgrams))),
+            (Algorithm::Annealing, Some(quadgrams)) => Some(Ok(self.anneal(quadgrams))),
+            (Algorithm::Beam, _) => {
+                let deadline = self.deadline();
+                let mut result = Err(Error::SearchExhausted);
+                for Seed { pins, words } in self.search.seeds() {
+                    result = beam::search(
+                        &words,
+                        self.search.max_skip,
+                        &Key::from_table([0; R], self.dict.alphabet.clone()).with_pins(&pins),
+                        self.dict,
+                        self.beam_width,
+                        self.beam_width_bounds,
+                        deadline,
+                        self.search.cancel.as_ref(),
+                    );
+                    if !matches!(result, Err(Error::SearchExhausted)) {
+                        break;
+                    }
+                }
+                Some(self.outcome(result))
+            }
+            _ => None,
+        }
+    }
+
+    /// When the search times out, counting from the first call
+    fn deadline(&mut self) -> Option<Instant> {
+        if self.deadline.is_none() {
+            self.deadline = self
+                .max_duration
+                .and_then(|duration| Instant::now().checked_add(duration));
+        }
+        self.deadline
+    }
+
+    /// Solution of a finished search, or if the search failed, the annealed solution of the
+    /// automatic algorithm or the best partial or the fallback solution if there is one
+    fn outcome(&self, result: Result<Key, Error>) -> Result<Solution, Error> {
+        let (best_placed, best_key) = &self.search.best;
+        match (result, &self.fallback) {
+            (Ok(key), _) => Ok(self.solution(&key, false)),
+            (Err(e @ (Error::SearchExhausted | Error::Timeout | Error::BudgetExceeded)), _)
+                if self.algorithm == Algorithm::Auto =>
+            {
+                match &self.quadgrams {
+                    Some(quadgrams) => Ok(self.anneal(quadgrams)),
+                    None => Quadgrams::from_dictionary(self.dict)
+                        .map(|quadgrams| self.anneal(&quadgrams))
+                        .map_err(|_| e),
+                }
+            }
+            (Err(Error::SearchExhausted | Error::Timeout | Error::BudgetExceeded), _)
+                if self.anytime && *best_placed > 0 =>
+            {
+                Ok(Solution {
+                    partial: true,
+                    ..self.solution(best_key, true)
+                })
+            }
+            (Err(Error::SearchExhausted | Error::Timeout | Error::BudgetExceeded), Some(key)) => {
+                Ok(Solution {
+                    fallback: true,
+                    ..self.solution(key, false)
+                })
+            }
+            (Err(e), _) => Err(e),
+        }
+    }
+
+    /// Decipher the input with a found key and score it by how many of the deciphered words the
+    /// dictionary has. Letters which a `partial` key doesn't map are written as `_`.
+    #[allow(clippy::cast_precision_loss)]
+    pub(crate) fn solution(&self, key: &Key, partial: bool) -> Solution {
+        let mut filtered = self.filtered.clone();
+        if partial {
+            key.mask_unmapped(&mut filtered, b'_');
+        }
+        key.translate(&mut filtered);
+
+        // Letters which a partial key doesn't map leave their words unmatched
+        let words = verify::count_words(&self.filtered);
+        let matched = verify::verify_key(&key.table, &words, self.dict, |word, dict| {
+            usize::from(word.whole && dict.has_word(word.letters)) * word.count
+        });
+        let total: usize = words.iter().map(|(_, count)| count).sum();
+        let skipped_words = words
+            .iter()
+            .filter_map(|(word, _)| {
+                let mut plain = word.to_vec();
+                if partial {
+                    key.mask_unmapped(&mut plain, b'_');
+                }
+                key.translate(&mut plain);
+                (!self.dict.has_word(&plain)).then(|| self.dict.alphabet.decode(&plain))
+            })
+            .collect();
+
+        let plaintext = if self.keep_formatting && partial {
+            let alphabet = &self.dict.alphabet;
+            let masked: String = self
+                .input
+                .chars()
+                .map(|c| match alphabet.code(c) {
+                    Some(code) if key.table[Key::index(code)] == 0 => '_',
+                    _ => c,
+                })
+                .collect();
+            key.encipher(&masked)
+        } else if self.keep_formatting {
+            key.encipher(&self.input)
+        } else {
+            self.dict.alphabet.decode(&filtered)
+        };
+        Solution {
+            score: matched as f64 / total as f64,
+            matched_words: matched,
+            // Formatted text already has the case of the input
+            plaintext: match self.case {
+                Case::MatchInput if self.keep_formatting => plaintext,
+                case => case.apply(&plaintext, &self.input, &self.dict.alphabet),
+            },
+            key: key.inverse().with_metadata(self.metadata.clone()),
+            fallback: false,
+            partial: false,
+            algorithm: Some(match self.algorithm {
+                Algorithm::Auto => Algorithm::Backtracking,
+                algorithm => algorithm,
+            }),
+            skipped_words,
+        }
+    }
+}
+
+/// Keys which decipher text into dictionary words, found one at a time as the iterator is
+/// advanced, for applications which rank the keys or stop searching on their own terms. Every key
+/// which backtracking can find is given once, the ones which skip the fewest words first.
+///
+/// The search stops when every key has been found, or when it runs out of the time or work
+/// budget or is cancelled, see [`KeySearch::error`].
+///
+/// ```
+/// use substitution::{Dictionary, KeySearch, SolverConfig};
+///
+/// let dict = Dictionary::from_reader("cat\ncot\ndog\n".as_bytes()).unwrap();
+/// let search = KeySearch::new("dpu", &dict, &SolverConfig::new()).unwrap();
+/// let mut plaintexts: Vec<String> = search.map(|candidate| candidate.plaintext).collect();
+/// plaintexts.sort();
+/// assert_eq!(plaintexts, ["cat", "cot", "dog"]);
+/// ```
+pub struct KeySearch<'a> {
+    solver: Solver<'a>,
+    /// Tables of the keys found so far, because later rounds of the search find them again
+    found: HashSet<[u8; R]>,
+    /// Why the search stopped, or `None` if it hasn't
+    stopped: Option<Error>,
+}
+
+impl<'a> KeySearch<'a> {
+    /// Prepare to find keys which decipher `text` with `dict` by backtracking, configured like
+    /// [`Dictionary::decrypt_full`]. The algorithm, fallback and anytime settings of `config`
+    /// don't apply. No searching is done before the first key is asked for.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`Solver::new`].
+    pub fn new(text: &str, dict: &'a Dictionary, config: &SolverConfig) -> Result<Self, Error> {
+        Ok(Self {
+            solver: Solver::with_algorithm(dict, text, config, Algorithm::Backtracking)?,
+            found: HashSet::new(),
+            stopped: None,
+        })
+    }
+
+    /// Why the search stopped before finding every key: [`Error::Timeout`],
+    /// [`Error::BudgetExceeded`] or [`Error::Cancelled`]. `None` while keys are still being
+    /// found, or if every key was found.
+    #[must_use]
+    pub fn error(&self) -> Option<&Error> {
+        match &self.stopped {
+            Some(Error::SearchExhausted) | None => None,
+            stopped => stopped.as_ref(),
+        }
+    }
+
+    /// Take the error which stopped the search, for a search which isn't advanced anymore
+    pub(crate) fn take_error(&mut self) -> Option<Error> {
+        self.stopped.take()
+    }
+}
+
+impl Iterator for KeySearch<'_> {
+    type Item = Candidate;
+
+    fn next(&mut self) -> Option<Candidate> {
+        while self.stopped.is_none() {
+            let deadline = self.solver.deadline();
+            let key = match self.solver.search.run(self.solver.dict, deadline) {
+                Some(Ok(key)) => key,
+                Some(Err(e)) => {
+                    self.stopped = Some(e);
+                    break;
+                }
+                None => {
+                    self.stopped = Some(Error::Timeout);
+                    break;
+                }
+            };
+            self.solver.search.pass_found();
+            if self.found.insert(key.table) {
+                let solution = self.solver.solution(&key, false);
+                return Some(Candidate {
+                    plaintext: solution.plaintext,
+                    key: solution.key,
+                    score: solution.score,
+                    stats: CandidateStats {
+                        matched_words: solution.matched_words,
+                        skipped_words: solution.skipped_words,
+                        nodes: self.solver.nodes(),
+                    },
+                });
+            }
+        }
+        None
+    }
+}
+
+/// One way of placing the cribs on ciphertext words, see [`SolverConfig::crib`]
+#[derive(Clone)]
+pub(crate) struct Placement {
+    /// Ciphertext letters of the words which the cribs are placed on, and their plaintext
+    /// letters
+    pins: Vec<(u8, u8)>,
+    /// Ciphertext words which the cribs are placed on
+    words: Vec<Vec<u8>>,
+    /// Narrowed down letters with the cribs placed, see [`Search::propagate`], once the first
+    /// round with the placement has started
+    domains: Option<[bitset::BitSet64<1>; R]>,
+}
+
+/// Pinned letters, and the words with their skip costs, to decipher with one placement of the
+/// cribs
+struct Seed {
+    pins: Vec<(u8, u8)>,
+    words: Vec<(Vec<u8>, usize)>,
+}
+
+/// Ways of placing every one of `cribs` on a ciphertext word of `input` of the same pattern, which
+/// agree with `pins` and each other. Cribs are placed on the most common words of their pattern
+/// first, and at most [`MAX_CRIB_PLACEMENTS`] ways are given.
+///
+/// # Errors
+///
+/// Returns [`Error::UnplacedCrib`] with the first crib which can't be placed along with the ones
+/// before it.
+pub(crate) fn place_cribs(
+    input: &[u8],
+    cribs: &[Vec<u8>],
+    pins: &[(u8, u8)],
+    alphabet: &Alphabet,
+) -> Result<Vec<Placement>, Error> {
+    // Distinct words from the most common, the earlier ones first when as common
+    let mut counts: Vec<(&[u8], usize)> = Vec::new();
+    for word in input
+        .split(u8::is_ascii_whitespace)
+        .filter(|word| !word.is_empty())
+    {
+        match counts.iter_mut().find(|(other, _)| *other == word) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((word, 1)),
+        }
+    }
+    counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+
+    let agrees = |pins: &[(u8, u8)], (c, p): (u8, u8)| {
+        pins.iter()
+            .all(|(other_c, other_p)| (c == *other_c) == (p == *other_p))
+    };
+    let mut placements = vec![Placement {
+        pins: Vec::new(),
+        words: Vec::new(),
+        domains: None,
+    }];
+    for crib in cribs {
+        let pattern = crate::Pattern::of_letters(crib.iter().copied());
+        let mut next = Vec::new();
+        for placement in &placements {
+            for (word, _) in counts.iter().filter(|(word, _)| {
+                word.len() == crib.len()
+                    && crate::Pattern::of_letters(word.iter().copied()) == pattern
+            }) {
+                let mut placed = placement.clone();
+                let fits = word.iter().zip(crib).all(|(c, p)| {
+                    let pin = (*c, *p);
+                    if !agrees(pins, pin) || !agrees(&placed.pins, pin) {
+                        return false;
+                    }
+                    if !pins.contains(&pin) && !placed.pins.contains(&pin) {
+                        placed.pins.push(pin);
+                    }
+                    true
+                });
+                if fits {
+                    placed.words.push(word.to_vec());
+                    next.push(placed);
+                }
+            }
+        }
+        if next.is_empty() {
+            return Err(Error::UnplacedCrib(alphabet.decode(crib)));
+        }
+        next.truncate(MAX_CRIB_PLACEMENTS);
+        placements = next;
+    }
+    Ok(placements)
+}
+
+/// Where a [`Frame`] continues from when it's back on top of the stack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Resume {
+    /// The word hasn't been looked at yet
+    Start,
+    /// Going on without deciphering the word, because it's already skipped, failed
+    SkipListed,
+    /// The current guesses should be tested
+    Test,
+    /// The current guesses made the word match, but the following words failed
+    Matched,
+    /// Skipping the word failed
+    Skipped,
+}
+
+/// What the search of the rest of the words from a word depends on, other than the skip budget,
+/// see [`Search::state`]
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct State {
+    placement: usize,
+    word: usize,
+    /// Guesses for the letters of the rest of the words
+    table: [u8; R],
+    /// Letters which are taken by any guess
+    guesses: bitset::BitSet64<4>,
+    /// First occurrence of each skipped word which comes again later
+    skipped: Vec<usize>,
+}
+
+/// Deciphering of one input word, what the recursive search would keep in a call
+struct Frame {
+    /// Index of the word, or the number of words after the last one
+    word: usize,
+    /// Skip budget left
+    can_skip: usize,
+    /// Letters of the word which this frame guesses
+    free_chars: Vec<u8>,
+    /// Whether the word is only one repeated free letter
+    uniform: bool,
+    /// Guesses for the free letters of each dictionary word of the word's pattern, one after
+    /// another, if they are guessed from the words instead of letter by letter
+    candidates: Vec<u8>,
+    /// How many of `candidates` have been guessed
+    guessed: usize,
+    /// State of the search when the frame started, see [`Search::state`]
+    state: Option<State>,
+    /// Smallest skip budget with which any of the failed ways to go on from the word could have
+    /// gone further
+    fails_below: usize,
+    resume: Resume,
+}
+
+impl Frame {
+    fn new(word: usize, can_skip: usize) -> Self {
+        Self {
+            word,
+            can_skip,
+            free_chars: Vec::new(),
+            uniform: false,
+            candidates: Vec::new(),
+            guessed: 0,
+            state: None,
+            fails_below: usize::MAX,
+            resume: Resume::Start,
+        }
+    }
+}
+
+/// Limits on the work of a [`Search`], set with [`SolverConfig::max_nodes`] and
+/// [`SolverConfig::max_backtracks`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Budget {
+    pub(crate) max_nodes: Option<usize>,
+    pub(crate) max_backtracks: Option<usize>,
+}
+
+/// Backtracking deciphering word by word, with the stack of words kept in [`Frame`]s so that it
+/// can be stopped and continued at any point
+pub(crate) struct Search {
+    /// Words in the order they are deciphered in, and the cost of skipping each
+    words: Vec<(Vec<u8>, usize)>,
+    /// Biggest skip budget to search with, by default one less than the cost of skipping all of
+    /// the words
+    pub(crate) max_skip: usize,
+    /// Ciphertext letters which every key deciphers as the given plaintext letters, see
+    /// [`SolverConfig::pin_letter`]
+    pub(crate) pins: Vec<(u8, u8)>,
+    /// Ways of placing the cribs, each of which is searched with in every round, if there are
+    /// cribs
+    placements: Vec<Placement>,
+    /// Index of the placement of the cribs of the current round
+    placement: usize,
+    /// Index of the placement of the cribs of the next round
+    next_placement: usize,
+    /// Skip cost of each word before the cribs are placed
+    skip_costs: Vec<usize>,
+    /// Skip budget of the next round
+    next_round: usize,
+    input: Vec<u8>,
+    alphabet: Alphabet,
+    freqs: LanguageFreqs,
+    key: Key,
+    chars_set: bitset::BitSet64<1>,
+    /// Indices of the words which are skipped
+    skip_words: Vec<usize>,
+    /// Index of the first and the last word of the same letters as each word
+    repeats: Vec<(usize, usize)>,
+    /// Letters of the words from each word on
+    later_chars: Vec<bitset::BitSet64<1>>,
+    /// Plaintext letters which each ciphertext letter can be deciphered into when every word
+    /// which can't be skipped for free is in the dictionary, see [`Search::propagate`]
+    domains: Option<[bitset::BitSet64<1>; R]>,
+    /// Skip budget below which the rest of the search fails from each state seen so far, see
+    /// [`Search::state`]
+    failed: HashMap<State, usize>,
+    scratch: Vec<u8>,
+    stack: Vec<Frame>,
+    budget: Budget,
+    cancel: Option<CancelToken>,
+    progress: Option<ProgressHook>,
+    /// When progress is reported next
+    next_progress: Option<Instant>,
+    /// Whether to keep track of the best guesses, for progress reports and partial solutions
+    track_best: bool,
+    /// What the best guesses are the best by
+    objective: Objective,
+    /// Words placed by the best guesses so far, and the key of the guesses
+    best: (usize, Key),
+    /// Worth of the words placed by the best guesses by the objective, see
+    /// [`Search::placed_weight`]
+    best_weight: usize,
+    /// Guesses tested so far
+    nodes: usize,
+    /// Times that a word has run out of guesses so far
+    backtracks: usize,
+}
+
+impl Search {
+    /// Prepare to search for a key which deciphers every word in filtered `input` into a
+    /// dictionary word, allowing some words to be skipped. Letters are guessed in the frequency
+    /// order of `freqs`.
+    ///
+    /// Words in `free_skips` may be skipped without using up the skip budget, unless every word is
+    /// one of them. Skipping the rest costs by `objective`. Words longer than
+    /// [`crate::MAX_WORD_LEN`] are left out. Words are deciphered in `ordering`, around
+    /// `word_order_target` distinct letters, or the tuned target if it's `None`.
+    pub(crate) fn new(
+        input: &[u8],
+        alphabet: &Alphabet,
+        freqs: LanguageFreqs,
+        free_skips: &[String],
+        word_order_target: Option<usize>,
+        ordering: WordOrdering,
+        objective: Objective,
+    ) -> Result<Self, Error> {
+        // Create a list of input words
+        let mut words: Vec<&[u8]> = input
+            .split(u8::is_ascii_whitespace)
+            .filter(|word| !word.is_empty())
+            .collect();
+        if words.is_empty() {
+            return Err(Error::UnsupportedInput);
+        }
+        crate::leave_out_overlong(&mut words);
+
+        // Associate each input word with it's number of unique characters and order them around the
+        // sweet spot
+        let target = word_order_target.unwrap_or_else(|| {
+            tuning::WORD_ORDER_TARGETS
+                .iter()
+                .find(|(max_words, _)| words.len() <= *max_words)
+                .map_or(7, |(_, target)| *target)
+        });
+        let mut words: Vec<(&[u8], usize)> = words
+            .iter()
+            .map(|word| (*word, unique_chars(word).len()))
+            .collect();
+        ordering.sort(&mut words, target);
+
+        // Replace the number of unique characters with the cost of skipping each word
+        let free_skips: Vec<Vec<u8>> = free_skips
+            .iter()
+            .map(|free| alphabet.encode(free))
+            .collect();
+        let all_free = words
+            .iter()
+            .all(|(word, _)| free_skips.iter().any(|free| free == word));
+        let words: Vec<(Vec<u8>, usize)> = words
+            .iter()
+            .map(|(word, _)| {
+                let free = !all_free && free_skips.iter().any(|free| free == word);
+                (
+                    word.to_vec(),
+                    if free { 0 } else { objective.skip_cost(word) },
+                )
+            })
+            .collect();
+
+        let mut occurrences: HashMap<&[u8], (usize, usize)> = HashMap::new();
+        for (idx, (word, _)) in words.iter().enumerate() {
+            occurrences.entry(word).or_insert((idx, idx)).1 = idx;
+        }
+        let repeats = words
+            .iter()
+            .map(|(word, _)| occurrences[&word[..]])
+            .collect();
+        let mut later_chars = vec![bitset::BitSet64::<1>::new(); words.len() + 1];
+        for (idx, (word, _)) in words.iter().enumerate().rev() {
+            later_chars[idx] = later_chars[idx + 1];
+            for c in word {
+                later_chars[idx].insert(c - START);
+            }
+        }
+
+        let key = Key::new(input, alphabet, &freqs);
+        Ok(Self {
+            max_skip: words
+                .iter()
+                .map(|(_, cost)| cost)
+                .sum::<usize>()
+                .saturating_sub(1),
+            pins: Vec::new(),
+            placements: Vec::new(),
+            placement: 0,
+            next_placement: 0,
+            skip_costs: Vec::new(),
+            next_round: 0,
+            best: (0, key.clone()),
+            key,
+            input: input.to_vec(),
+            alphabet: alphabet.clone(),
+            freqs,
+            chars_set: bitset::BitSet64::<1>::new(),
+            skip_words: Vec::with_capacity(words.len()),
+            repeats,
+            later_chars,
+            domains: None,
+            failed: HashMap::new(),
+            scratch: vec![0; input.len()],
+            stack: Vec::with_capacity(words.len() + 1),
+            words,
+            budget: Budget::default(),
+            cancel: None,
+            progress: None,
+            next_progress: None,
+            track_best: false,
+            objective,
+            best_weight: 0,
+            nodes: 0,
+            backtracks: 0,
+        })
+    }
+
+    /// Make skipping words cost by [`Objective::AllWords`] with words of at most `short_word_len`
+    /// letters counting as short and the number of words of `dict` which have each word's
+    /// pattern, and return the cost of skipping all of the words. Free words stay free.
+    pub(crate) fn weigh_skips(&mut self, dict: &impl WordSource, short_word_len: usize) -> usize {
+        if self.objective == Objective::AllWords {
+            for (word, cost) in &mut self.words {
+                if *cost > 0 {
+                    let pattern = crate::Pattern::of_letters(word.iter().copied());
+                    *cost = skip_cost(word, short_word_len, dict.pattern_count(&pattern));
+                }
+            }
+        }
+        self.words.iter().map(|(_, cost)| cost).sum()
+    }
+
+    /// Let words shorter than `min_len` letters be skipped without using up the skip budget,
+    /// unless every word is free to skip, and return the cost of skipping all of the words
+    pub(crate) fn free_short_words(&mut self, min_len: usize) -> usize {
+        let short = |word: &[u8], cost: usize| cost == 0 || word.len() < min_len;
+        if !self.words.iter().all(|(word, cost)| short(word, *cost)) {
+            for (word, cost) in &mut self.words {
+                if word.len() < min_len {
+                    *cost = 0;
+                }
+            }
+        }
+        self.words.iter().map(|(_, cost)| cost).sum()
+    }
+
+    /// Search with each of `placements` of the cribs in turn in every round, see
+    /// [`place_cribs`]. The words which the cribs are placed on are free to skip.
+    pub(crate) fn set_placements(&mut self, placements: Vec<Placement>) {
+        self.skip_costs = self.words.iter().map(|(_, cost)| *cost).collect();
+        self.placements = placements;
+    }
+
+    /// Pinned letters of the current round: the given ones and the ones of the placed cribs
+    fn round_pins(&self) -> Vec<(u8, u8)> {
+        let mut pins = self.pins.clone();
+        if let Some(placement) = self.placements.get(self.placement) {
+            pins.extend_from_slice(&placement.pins);
+        }
+        pins
+    }
+
+    /// What to decipher with each placement of the cribs, or just once if there are no cribs
+    fn seeds(&self) -> Vec<Seed> {
+        if self.placements.is_empty() {
+            return vec![Seed {
+                pins: self.pins.clone(),
+                words: self.words.clone(),
+            }];
+        }
+        self.placements
+            .iter()
+            .map(|placement| {
+                let mut pins = self.pins.clone();
+                pins.extend_from_slice(&placement.pins);
+                let words = self
+                    .words
+                    .iter()
+                    .zip(&self.skip_costs)
+                    .map(|((word, _), cost)| {
+                        let free = placement.words.contains(word);
+                        (word.clone(), if free { 0 } else { *cost })
+                    })
+                    .collect();
+                Seed { pins, words }
+            })
+            .collect()
+    }
+
+    /// Make the next rounds search with the placement of the cribs at `idx`
+    fn place(&mut self, idx: usize, dict: &impl WordSource) {
+        self.placement = idx;
+        let placement = &self.placements[idx];
+        for ((word, cost), base) in self.words.iter_mut().zip(&self.skip_costs) {
+            *cost = if placement.words.contains(word) {
+                0
+            } else {
+                *base
+            };
+        }
+        if let Some(domains) = placement.domains {
+            self.domains = Some(domains);
+        } else {
+            self.propagate(dict);
+            self.placements[idx].domains = self.domains;
+        }
+    }
+
+    /// Narrow down the plaintext letters which each ciphertext letter can be deciphered into, for
+    /// the search to only guess them while no word can be skipped.
+    ///
+    /// A letter can only be deciphered into the letters which are in its place in dictionary
+    /// words of the pattern of each of its words. Dictionary words which need a letter that
+    /// another word rules out are left out, and a letter which can only be deciphered into one
+    /// letter takes it from the others, until nothing changes.
+    pub(crate) fn propagate(&mut self, dict: &impl WordSource) {
+        let mut words: Vec<&[u8]> = self
+            .words
+            .iter()
+            .filter(|(_, cost)| *cost > 0)
+            .map(|(word, _)| word.as_slice())
+            .collect();
+        words.sort_unstable();
+        words.dedup();
+        let pins = self.round_pins();
+        let mut plains: Vec<Vec<&[u8]>> = words
+            .iter()
+            .map(|word| {
+                let pattern = crate::Pattern::of_letters(word.iter().copied());
+                // Pinned letters only leave the words which have them in their places
+                dict.candidates_for_pattern(&pattern)
+                    .filter(|plain| {
+                        word.iter()
+                            .zip(*plain)
+                            .all(|(c, p)| pins.iter().all(|(pc, pp)| (c == pc) == (p == pp)))
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut all = bitset::BitSet64::<1>::new();
+        for c in self.alphabet.codes() {
+            all.insert(c - START);
+        }
+        let mut domains = [all; R];
+        loop {
+            let mut next = [all; R];
+            for (word, plains) in words.iter().zip(&plains) {
+                let mut letters = [bitset::BitSet64::<1>::new(); R];
+                for plain in plains {
+                    for (c, p) in word.iter().zip(*plain) {
+                        letters[Key::index(*c)].insert(p - START);
+                    }
+                }
+                for c in *word {
+                    next[Key::index(*c)].intersect(&letters[Key::index(*c)]);
+                }
+            }
+            // A letter which has to be deciphered into one letter takes it from the others
+            for c in 0..R {
+                let Some(taken) = (next[c].len() == 1)
+                    .then(|| self.alphabet.codes().find(|p| next[c].contains(p - START)))
+                    .flatten()
+                else {
+                    continue;
+                };
+                for (other, domain) in next.iter_mut().enumerate() {
+                    if other != c {
+                        domain.remove(taken - START);
+                    }
+                }
+            }
+
+            let mut changed = false;
+            for (word, plains) in words.iter().zip(&mut plains) {
+                let before = plains.len();
+                plains.retain(|plain| {
+                    word.iter()
+                        .zip(*plain)
+                        .all(|(c, p)| next[Key::index(*c)].contains(p - START))
+                });
+                changed |= plains.len() != before;
+            }
+            changed |= domains.iter().zip(&next).any(|(a, b)| a.len() != b.len());
+            domains = next;
+            if !changed {
+                break;
+            }
+        }
+        self.domains = Some(domains);
+    }
+
+    /// Search until a key is found, every key has been tried, the budget is used up, the search is
+    /// cancelled or `deadline` passes, in which case `None` is returned and the search can be
+    /// continued by running it again
+    pub(crate) fn run(
+        &mut self,
+        dict: &impl WordSource,
+        deadline: Option<Instant>,
+    ) -> Option<Result<Key, Error>> {
+        let outcome = self.run_until(dict, deadline);
+        if outcome.is_some() {
+            // Where the search ended is reported even if it was reported recently
+            self.next_progress = None;
+            self.report_progress();
+        }
+        outcome
+    }
+
+    /// Search like [`Search::run`], without reporting where the search ended
+    fn run_until(
+        &mut self,
+        dict: &impl WordSource,
+        deadline: Option<Instant>,
+    ) -> Option<Result<Key, Error>> {
+        if self.is_cancelled() {
+            return Some(Err(Error::Cancelled));
+        }
+        let mut steps = 0;
+        loop {
+            if steps >= STEPS_PER_CLOCK_CHECK {
+                if self.is_cancelled() {
+                    return Some(Err(Error::Cancelled));
+                }
+                if deadline.is_some_and(|d| Instant::now() >= d) {
+                    return None;
+                }
+                self.report_progress();
+                steps = 0;
+            }
+            steps += 1;
+
+            if self.budget.max_nodes.is_some_and(|max| self.nodes > max)
+                || (self.budget.max_backtracks).is_some_and(|max| self.backtracks > max)
+            {
+                return Some(Err(Error::BudgetExceeded));
+            }
+
+            #[cfg(feature = "debug-invariants")]
+            self.check_invariants();
+
+            let Some(frame) = self.stack.last() else {
+                // Iterative deepening on the skip budget: a clean solution without skipped words
+                // is tried first, and every following round is a fresh search with a bigger budget
+                // for skipping words. The budget never covers skipping all of the words, at least
+                // one has to match the dictionary.
+                if self.next_round > self.max_skip {
+                    return Some(Err(Error::SearchExhausted));
+                }
+                self.start_round(dict);
+                continue;
+            };
+
+            match frame.resume {
+                Resume::Start if frame.word == self.words.len() => {
+                    return Some(Ok(self.key.clone()));
+                }
+                Resume::Start => {
+                    let state = self.state();
+                    if let Some(&fails_below) = self
+                        .failed
+                        .get(&state)
+                        .filter(|fails_below| frame.can_skip < **fails_below)
+                    {
+                        // The same guesses have already failed to decipher the rest of the words
+                        // with a skip budget which this one doesn't add to
+                        self.pop_failed(fails_below);
+                        continue;
+                    }
+                    self.top().state = Some(state);
+                    // Check if this word should be skipped for now
+                    let frame = &self.stack[self.stack.len() - 1];
+                    let word = &self.words[frame.word].0;
+                    if self.skip_words.iter().any(|i| self.words[*i].0 == *word) {
+                        // Proceed to next
+                        let next = Frame::new(frame.word + 1, frame.can_skip);
+                        self.top().resume = Resume::SkipListed;
+                        self.stack.push(next);
+                    } else {
+                        self.enter(dict);
+                    }
+                }
+                Resume::SkipListed => self.enter(dict),
+                Resume::Test => {
+                    let tries = self.test(dict);
+                    steps += tries;
+                    self.nodes += tries;
+                }
+                Resume::Matched => {
+                    let len = self.words[frame.word].0.len();
+                    let top = self.stack.len() - 1;
+                    let frame = &mut self.stack[top];
+                    if Self::next_guess(&mut self.key, frame, len, dict, &mut self.scratch) {
+                        self.top().resume = Resume::Test;
+                    } else {
+                        self.leave();
+                    }
+                }
+                Resume::Skipped => {
+                    self.skip_words.pop();
+                    #[cfg(debug_assertions)]
+                    eprintln!("Failed, backtracking");
+                    self.fail();
+                }
+            }
+        }
+    }
+
+    /// Go on from the key which the search found, so that running it again finds the next key.
+    /// The guesses which found it count as failing with the skip budget they had, so that the
+    /// same key is only found again in later rounds with bigger budgets.
+    pub(crate) fn pass_found(&mut self) {
+        if let Some(frame) = self.stack.last() {
+            if frame.word == self.words.len() {
+                self.pop_failed(frame.can_skip + 1);
+            }
+        }
+    }
+
+    /// What the search of the rest of the words from the top frame's word depends on, other than
+    /// the skip budget: the word, the guesses for the letters of the rest of the words, which
+    /// letters are taken by any guess, and which of the skipped words come again later
+    ///
+    /// The letters which are set in stone are the ones which have a guess, when a frame starts or
+    /// fails. The whole state is kept instead of a hash of it, so that two states can't be
+    /// mistaken for each other and prune a branch which could still succeed.
+    fn state(&self) -> State {
+        let word = self.stack[self.stack.len() - 1].word;
+        let later_chars = &self.later_chars[word];
+        let mut table = [0; R];
+        for ((c, guess), later) in (0u8..).zip(&self.key.table).zip(&mut table) {
+            if later_chars.contains(c) {
+                *later = *guess;
+            }
+        }
+        let mut skipped: Vec<usize> = self
+            .skip_words
+            .iter()
+            .map(|skipped| self.repeats[*skipped])
+            .filter(|(_, last)| *last >= word)
+            .map(|(first, _)| first)
+            .collect();
+        skipped.sort_unstable();
+        skipped.dedup();
+        State {
+            placement: self.placement,
+            word,
+            table,
+            guesses: self.key.guesses,
+            skipped,
+        }
+    }
+
+    /// Pop the top frame, whose word and the words after it can't be deciphered with the guesses
+    /// so far, and remember that so that the same guesses aren't searched again
+    fn fail(&mut self) {
+        if self.failed.len() >= MAX_FAILED_STATES {
+            self.failed.clear();
+        }
+        let frame = self.top();
+        let fails_below = frame.fails_below;
+        if let Some(state) = frame.state.take() {
+            let failed = self.failed.entry(state).or_default();
+            *failed = (*failed).max(fails_below);
+        }
+        self.pop_failed(fails_below);
+    }
+
+    /// Pop the top frame, which fails with any skip budget below `fails_below`, and pass that on
+    /// to the frame which went on to it
+    fn pop_failed(&mut self, fails_below: usize) {
+        self.stack.pop();
+        let Some(parent) = self.stack.last() else {
+            return;
+        };
+        // A skipped word used up some of the budget before going on
+        let spent = match parent.resume {
+            Resume::Skipped => self.words[parent.word].1,
+            _ => 0,
+        };
+        let parent = self.top();
+        parent.fails_below = parent.fails_below.min(fails_below.saturating_add(spent));
+    }
+
+    /// Panic if the key is inconsistent, or if the letters which are set in stone aren't the
+    /// letters which the frames on the stack are guessing
+    #[cfg(feature = "debug-invariants")]
+    fn check_invariants(&self) {
+        self.key.check_invariants();
+        let mut guessing = bitset::BitSet64::<1>::new();
+        for frame in &self.stack {
+            if matches!(frame.resume, Resume::Test | Resume::Matched) {
+                for c in &frame.free_chars {
+                    assert!(
+                        !guessing.contains(c - START),
+                        "Two frames guess '{}'",
+                        char::from(*c)
+                    );
+                    guessing.insert(c - START);
+                }
+            }
+        }
+        for (code, guess) in self.alphabet.codes().zip(&self.key.table) {
+            let c = code - START;
+            assert_eq!(
+                self.chars_set.contains(c),
+                guessing.contains(c),
+                "Letter {c} is set in stone without a frame guessing it, or the other way round"
+            );
+            assert!(
+                *guess == 0 || guessing.contains(c) || self.key.pinned.contains(c),
+                "Letter {c} is mapped without a frame guessing it or being pinned"
+            );
+        }
+    }
+
+    /// Number of words which the current guesses decipher into dictionary words
+    fn words_placed(&self) -> usize {
+        self.stack
+            .iter()
+            .filter(|frame| matches!(frame.resume, Resume::Matched))
+            .count()
+    }
+
+    /// Worth of the words placed in the dictionary so far: their letters with
+    /// [`Objective::MatchedLetters`], and otherwise their number
+    fn placed_weight(&self) -> usize {
+        self.stack
+            .iter()
+            .filter(|frame| matches!(frame.resume, Resume::Matched))
+            .map(|frame| match self.objective {
+                Objective::MatchedLetters => self.words[frame.word].0.len(),
+                _ => 1,
+            })
+            .sum()
+    }
+
+    /// Give the progress hook the state of the search, unless it was given it recently
+    fn report_progress(&mut self) {
+        let Some(hook) = &self.progress else {
+            return;
+        };
+        let now = Instant::now();
+        if self.next_progress.is_some_and(|next| now < next) {
+            return;
+        }
+        self.next_progress = now.checked_add(PROGRESS_INTERVAL);
+        hook.0(&SearchProgress {
+            words: self.words.len(),
+            words_placed: self.words_placed(),
+            best_words_placed: self.best.0,
+            best_key: self.best.1.inverse(),
+            nodes: self.nodes,
+            backtracks: self.backtracks,
+        });
+    }
+
+    /// Returns true if the search has been cancelled with its [`CancelToken`]
+    fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(CancelToken::is_cancelled)
+    }
+
+    /// Start a search with the next skip budget, or with the next placement of the cribs. Every
+    /// placement is searched with a skip budget before going on to a bigger one.
+    fn start_round(&mut self, dict: &impl WordSource) {
+        #[cfg(debug_assertions)]
+        eprintln!("Skip budget {}", self.next_round);
+
+        if !self.placements.is_empty() {
+            self.place(self.next_placement, dict);
+        }
+        // Create a key for deciphering
+        self.key = Key::new(&self.input, &self.alphabet, &self.freqs).with_pins(&self.round_pins());
+        self.chars_set = bitset::BitSet64::<1>::new();
+        self.skip_words.clear();
+        self.stack.push(Frame::new(0, self.next_round));
+        self.next_placement += 1;
+        if self.next_placement >= self.placements.len() {
+            self.next_placement = 0;
+            self.next_round += 1;
+        }
+    }
+
+    /// Frame on top of the stack
+    fn top(&mut self) -> &mut Frame {
+        let top = self.stack.len() - 1;
+        &mut self.stack[top]
+    }
+
+    /// Start guessing the letters of the top frame's word which earlier words haven't set
+    fn enter(&mut self, dict: &impl WordSource) {
+        let chars_set = &mut self.chars_set;
+        let pinned = &self.key.pinned;
+        let word = &self.words[self.stack[self.stack.len() - 1].word].0;
+
+        // Generate list of currently relevant and unset chars in input
+        let free_chars: Vec<u8> = unique_chars(word)
+            .into_iter()
+            .filter(|c| !chars_set.contains(c - START) && !pinned.contains(c - START))
+            .collect();
+
+        // Set input chars in stone for next frames so they won't be iterated
+        for c in &free_chars {
+            chars_set.insert(*c - START);
+        }
+
+        // A word of a single repeated letter (like "aaa") can only match dictionary words of the
+        // same pattern, so its guesses are looked up by the pattern instead of following the
+        // frequency order
+        let len = word.len();
+        let uniform = crate::uniform_free_char(word, &free_chars).is_some();
+        // The narrowed down letters hold while every word has to be in the dictionary
+        let top = self.stack.len() - 1;
+        let domains = self.domains.as_ref().filter(|_| {
+            self.stack[top].can_skip == 0
+                && self.skip_words.iter().all(|idx| self.words[*idx].1 == 0)
+        });
+        if domains.is_some() {
+            // Guesses which were left out could have gone further with a skip budget
+            self.stack[top].fails_below = self.stack[top].fails_below.min(1);
+        }
+        let candidates = if uniform || free_chars.is_empty() {
+            None
+        } else {
+            Some(self.candidate_guesses(word, &free_chars, dict, domains))
+        };
+        let exhausted = if uniform {
+            self.key
+                .attach_next_uniform(free_chars[0], len, dict, &mut self.scratch)
+                .is_err()
+        } else {
+            candidates.as_ref().is_some_and(Vec::is_empty)
+        };
+
+        let frame = &mut self.stack[top];
+        frame.free_chars = free_chars;
+        frame.uniform = uniform;
+        frame.candidates = candidates.unwrap_or_default();
+        frame.guessed = 0;
+        frame.resume = Resume::Test;
+        if exhausted {
+            self.leave();
+        } else if !frame.candidates.is_empty() {
+            Self::next_guess(&mut self.key, frame, len, dict, &mut self.scratch);
+        }
+    }
+
+    /// Guesses for `free_chars` of each dictionary word which `word` can be deciphered into,
+    /// keeping the letters which are already guessed, one after another. They are in the order
+    /// in which guessing letter by letter would come to them, but without going through the
+    /// guesses which make no word.
+    ///
+    /// The words are looked up by the pattern of `word` if it has fewer words than there are
+    /// guesses for the free letters, and otherwise by walking the dictionary trie only along the
+    /// letters which can come next. Free letters are only guessed into their `domains` if given.
+    fn candidate_guesses(
+        &self,
+        word: &[u8],
+        free_chars: &[u8],
+        dict: &impl WordSource,
+        domains: Option<&[bitset::BitSet64<1>; R]>,
+    ) -> Vec<u8> {
+        let key = &self.key;
+        let in_domain =
+            |c: u8, p: u8| domains.is_none_or(|domains| domains[Key::index(c)].contains(p - START));
+        // Letters which were set in stone without a guess decipher to themselves
+        let fixed = |c: u8| match key.table[usize::from(c - START)] {
+            0 => c,
+            guess => guess,
+        };
+
+        // Dictionary words which the word can be deciphered into, one after another
+        let mut plains = Vec::new();
+        let pattern = crate::Pattern::of_letters(word.iter().copied());
+        let letter_by_letter = u32::try_from(free_chars.len())
+            .ok()
+            .and_then(|free| self.alphabet.len().checked_pow(free))
+            .unwrap_or(usize::MAX);
+        if dict.pattern_count(&pattern) < letter_by_letter {
+            for plain in dict.candidates_for_pattern(&pattern) {
+                if word.iter().zip(plain).all(|(c, p)| {
+                    if free_chars.contains(c) {
+                        !key.guesses.contains(*p) && in_domain(*c, *p)
+                    } else {
+                        fixed(*c) == *p
+                    }
+                }) {
+                    plains.extend_from_slice(plain);
+                }
+            }
+        } else {
+            let allow = |prefix: &[usize], plain: usize| {
+                let (Some(c), Ok(plain)) = (word.get(prefix.len()), u8::try_from(plain)) else {
+                    return false;
+                };
+                if !free_chars.contains(c) {
+                    return fixed(*c) == plain;
+                }
+                match word[..prefix.len()].iter().position(|earlier| earlier == c) {
+                    // The letter was guessed earlier in the word
+                    Some(idx) => prefix[idx] == usize::from(plain),
+                    None => {
+                        !key.guesses.contains(plain)
+                            && !prefix.contains(&usize::from(plain))
+                            && in_domain(*c, plain)
+                    }
+                }
+            };
+            dict.for_each_allowed(&pattern, allow, |plain| plains.extend_from_slice(plain));
+        }
+
+        // Where each free letter first appears in the word, and the rank of each guess for it. A
+        // letter which isn't in the word can't be guessed from it.
+        let Some(positions) = free_chars
+            .iter()
+            .map(|c| word.iter().position(|w| w == c))
+            .collect::<Option<Vec<usize>>>()
+        else {
+            return Vec::new();
+        };
+        let ranks: Vec<[usize; R]> = free_chars
+            .iter()
+            .map(|c| {
+                let mut ranks = [usize::MAX; R];
+                for (rank, guess) in self.key.guess_order(*c).enumerate() {
+                    ranks[usize::from(guess - START)] = rank;
+                }
+                ranks
+            })
+            .collect();
+
+        let mut words: Vec<(Vec<usize>, &[u8])> = plains
+            .chunks(word.len())
+            .map(|plain| {
+                // The first free letter changes the fastest, like the digits of an odometer
+                let order = positions
+                    .iter()
+                    .zip(&ranks)
+                    .rev()
+                    .map(|(pos, ranks)| ranks[usize::from(plain[*pos] - START)])
+                    .collect();
+                (order, plain)
+            })
+            .collect();
+        words.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        words
+            .iter()
+            .flat_map(|(_, plain)| positions.iter().map(|pos| plain[*pos]))
+            .collect()
+    }
+
+    /// Test guesses of the top frame's word until one deciphers it into a dictionary word, the
+    /// guesses run out, or [`STEPS_PER_CLOCK_CHECK`] guesses have been tested. Returns the number
+    /// of guesses tested.
+    fn test(&mut self, dict: &impl WordSource) -> usize {
+        let top = self.stack.len() - 1;
+        let (idx, can_skip) = (self.stack[top].word, self.stack[top].can_skip);
+        let word = self.words[idx].0.as_slice();
+        for tries in 1..=STEPS_PER_CLOCK_CHECK {
+            // Set input word to scratch and try to translate by current key state
+            let scratch = &mut self.scratch[..word.len()];
+            scratch.copy_from_slice(word);
+            self.key.translate(scratch);
+
+            // Check the validity of the attempt
+            // Deciphered letters are always in the alphabet, but if not, the word doesn't match
+            let score = dict.prefix_score(scratch);
+            if score == word.len() + 1 {
+                #[cfg(debug_assertions)]
+                eprintln!(
+                    "Found likely word \"{}\"",
+                    String::from_utf8_lossy(&self.scratch[..word.len()])
+                );
+
+                // Proceed to next without skipping current
+                let next = Frame::new(idx + 1, can_skip);
+                self.stack[top].resume = Resume::Matched;
+                self.stack.push(next);
+                if self.track_best {
+                    let weight = self.placed_weight();
+                    if weight > self.best_weight {
+                        self.best_weight = weight;
+                        self.best = (self.words_placed(), self.key.clone());
+                    }
+                }
+                return tries;
+            }
+
+            if !Self::next_guess(
+                &mut self.key,
+                &mut self.stack[top],
+                word.len(),
+                dict,
+                &mut self.scratch,
+            ) {
+                self.leave();
+                return tries;
+            }
+        }
+        STEPS_PER_CLOCK_CHECK
+    }
+
+    /// Move `key` on to the next guesses for the free letters of `frame`, whose word is `len`
+    /// letters long, from its candidates or for a word of one repeated letter, to the next letter
+    /// which repeated makes a dictionary word. Returns false if every guess has been tried.
+    fn next_guess(
+        key: &mut Key,
+        frame: &mut Frame,
+        len: usize,
+        dict: &impl WordSource,
+        scratch: &mut [u8],
+    ) -> bool {
+        if !frame.candidates.is_empty() {
+            for chr in &frame.free_chars {
+                key.clear(*chr);
+            }
+            let free = frame.free_chars.len();
+            while let Some(guesses) = frame.candidates.chunks(free).nth(frame.guessed) {
+                frame.guessed += 1;
+                if frame
+                    .free_chars
+                    .iter()
+                    .zip(guesses)
+                    .all(|(chr, guess)| key.attach(*chr, *guess).is_ok())
+                {
+                    return true;
+                }
+                // Candidates don't have taken letters, but if one does, it's no guess
+                for chr in &frame.free_chars {
+                    key.clear(*chr);
+                }
+            }
+            return false;
+        }
+        match frame.free_chars.first() {
+            Some(chr) if frame.uniform => {
+                if key.attach_next_uniform(*chr, len, dict, scratch).is_ok() {
+                    return true;
+                }
+                key.clear(*chr);
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// Search like [`Search::run`] without a deadline, but split each round between threads by the
+    /// guesses for the first word. The key is the same as the one which [`Search::run`] finds.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn run_parallel(&mut self, dict: &(impl WordSource + Sync)) -> Result<Key, Error> {
+        while self.next_round <= self.max_skip {
+            self.start_round(dict);
+            self.enter(dict);
+            let found = std::sync::Mutex::new(None);
+            rayon::scope(|scope| self.spawn_branches(scope, 0, dict, &found));
+            // A branch which panicked still leaves the key of an earlier one
+            let found = found
+                .into_inner()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            if let Some((_, key)) = found {
+                return Ok(key);
+            }
+            if self.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+        }
+        Err(Error::SearchExhausted)
+    }
+
+    /// Make the next branch of the round and search it, while the following branches are made in
+    /// another task which an idle thread can take. On a single thread, this goes through the
+    /// branches in order like [`Search::run`] does.
+    ///
+    /// `found` has the key of the first branch which has one, and its `index` in the order of the
+    /// branches. Branches after it are stopped, because the key is in an earlier one.
+    #[cfg(feature = "rayon")]
+    fn spawn_branches<'s, W: WordSource + Sync>(
+        &'s mut self,
+        scope: &rayon::Scope<'s>,
+        index: usize,
+        dict: &'s W,
+        found: &'s std::sync::Mutex<Option<(usize, Key)>>,
+    ) {
+        let found_earlier = || {
+            found
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .as_ref()
+                .is_some_and(|(earlier, _)| *earlier < index)
+        };
+        if found_earlier() || self.is_cancelled() {
+            return;
+        }
+        let Some(mut branch) = self.next_branch(dict) else {
+            return;
+        };
+        let pinned = self.key.pinned;
+        scope.spawn(move |scope| self.spawn_branches(scope, index + 1, dict, found));
+
+        // Search a slice at a time to stop soon after an earlier branch finds the key
+        loop {
+            match branch.run(dict, Instant::now().checked_add(BRANCH_SLICE)) {
+                None if found_earlier() => return,
+                None => {}
+                Some(Ok(mut key)) => {
+                    // Only the given letters stay pinned in the key which is found
+                    key.pinned = pinned;
+                    let mut found = found
+                        .lock()
+                        .unwrap_or_else(std::sync::PoisonError::into_inner);
+                    if found.as_ref().is_none_or(|(other, _)| index < *other) {
+                        *found = Some((index, key));
+                    }
+                    return;
+                }
+                Some(Err(_)) => return,
+            }
+        }
+    }
+
+    /// Go on with the guesses for the first word of the round until one matches the dictionary
+    /// or the word is skipped, and return a search of the following words in that branch, which
+    /// ends at the end of the round. Returns `None` when the first word has no branches left.
+    #[cfg(feature = "rayon")]
+    fn next_branch(&mut self, dict: &impl WordSource) -> Option<Self> {
+        loop {
+            let frame = self.stack.last()?;
+            if self.stack.len() > 1 {
+                // The first word matched or was skipped, and the frame of the next one was pushed
+                let frame = self.stack.pop()?;
+                // The branch has no frame for the first word, so its guesses are pinned instead
+                let mut key = self.key.clone();
+                for c in self.alphabet.codes() {
+                    if self.chars_set.contains(c - START) {
+                        key.pinned.insert(c - START);
+                    }
+                }
+                return Some(Self {
+                    words: self.words.clone(),
+                    max_skip: self.max_skip,
+                    pins: self.pins.clone(),
+                    // The branch ends with its round, so it places no more cribs
+                    placements: Vec::new(),
+                    placement: self.placement,
+                    next_placement: 0,
+                    skip_costs: Vec::new(),
+                    next_round: self.max_skip + 1,
+                    input: self.input.clone(),
+                    alphabet: self.alphabet.clone(),
+                    freqs: self.freqs.clone(),
+                    key,
+                    chars_set: bitset::BitSet64::<1>::new(),
+                    skip_words: self.skip_words.clone(),
+                    repeats: self.repeats.clone(),
+                    later_chars: self.later_chars.clone(),
+                    domains: self.domains,
+                    failed: HashMap::new(),
+                    scratch: self.scratch.clone(),
+                    stack: vec![frame],
+                    budget: self.budget,
+                    cancel: self.cancel.clone(),
+                    progress: self.progress.clone(),
+                    next_progress: None,
+                    track_best: self.track_best,
+                    objective: self.objective,
+                    best: self.best.clone(),
+                    best_weight: self.best_weight,
+                    nodes: 0,
+                    backtracks: 0,
+                });
+            }
+            match frame.resume {
+                Resume::Test => {
+                    self.test(dict);
+                }
+                Resume::Matched => {
+                    let len = self.words[frame.word].0.len();
+                    let top = self.stack.len() - 1;
+                    let frame = &mut self.stack[top];
+                    if Self::next_guess(&mut self.key, frame, len, dict, &mut self.scratch) {
+                        self.top().resume = Resume::Test;
+                    } else {
+                        self.leave();
+                    }
+                }
+                Resume::Skipped => {
+                    self.skip_words.pop();
+                    self.stack.pop();
+                }
+                // The first word hasn't been entered, and nothing is skipped before it
+                Resume::Start | Resume::SkipListed => self.enter(dict),
+            }
+        }
+    }
+
+    /// The top frame's guesses are exhausted, so try skipping its word or fail
+    fn leave(&mut self) {
+        self.backtracks += 1;
+
+        // Clear set characters so that the following words or earlier frames can keep iterating
+        // them
+        let frame = &self.stack[self.stack.len() - 1];
+        for c in &frame.free_chars {
+            self.chars_set.remove(*c - START);
+        }
+
+        // Key exhausted but it's possible that this word is not in the dictionary, try skipping
+        let cost = self.words[frame.word].1;
+        if frame.can_skip >= cost {
+            self.skip_words.push(frame.word);
+            // Proceed to next, skipping current
+            let next = Frame::new(frame.word + 1, frame.can_skip - cost);
+            self.top().resume = Resume::Skipped;
+            self.stack.push(next);
+        } else {
+            // Skipping the word would have taken a bigger budget
+            self.top().fails_below = self.top().fails_below.min(cost);
+            self.fail();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testgen;
+    use rand::prelude::*;
+
+    /// A cryptogram which takes many steps to solve, because the dictionary doesn't have one of
+    /// its words and its words are deciphered in a bad order, with the dictionary and the config
+    /// which make it so. The letters of the missing word are in the other words, so the plaintext
+    /// still comes out right.
+    fn slow_cryptogram() -> (Dictionary, testgen::Cryptogram, SolverConfig) {
+        let cryptogram = testgen::cryptograms(testgen::CORPUS, 20, 1, 6..=6).remove(0);
+        let corpus = testgen::CORPUS.replace("only", "");
+        let dict = Dictionary::from_reader(corpus.as_bytes()).unwrap();
+        (dict, cryptogram, SolverConfig::new().word_order_target(1))
+    }
+
+    #[test]
+    fn steps_continue_where_they_left_off() {
+        let (dict, cryptogram, config) = slow_cryptogram();
+        let mut solver = Solver::new(&dict, &cryptogram.ciphertext, &config).unwrap();
+        let mut steps = 0;
+        let solution = loop {
+            steps += 1;
+            match solver.step(Duration::ZERO) {
+                StepResult::Pending => {}
+                StepResult::Solved(solution) => break *solution,
+                result => panic!("Search failed: {result:?}"),
+            }
+        };
+        assert!(steps > 1);
+        assert_eq!(solution.plaintext, cryptogram.plaintext);
+        assert_eq!(
+            solution,
+            dict.decrypt_full(&cryptogram.ciphertext, &config).unwrap()
+        );
+        assert_eq!(
+            solver.step(Duration::ZERO),
+            StepResult::Solved(Box::new(solution))
+        );
+    }
+
+    #[test]
+    fn overlap_takes_words_with_known_letters_first() {
+        let mut words: Vec<(&[u8], usize)> =
+            vec![(b"wxyz", 4), (b"abc", 3), (b"abde", 4), (b"abc", 3)];
+        WordOrdering::Overlap.sort(&mut words, 3);
+        let words: Vec<&[u8]> = words.into_iter().map(|(word, _)| word).collect();
+        assert_eq!(words, [&b"abc"[..], b"abc", b"abde", b"wxyz"]);
+        assert_eq!(
+            "Target".parse::<WordOrdering>().unwrap(),
+            WordOrdering::Target
+        );
+    }
+
+    #[test]
+    fn objective_weighs_the_words() {
+        let dict = Dictionary::from_reader("no\non\nisland\n".as_bytes()).unwrap();
+        let solve = |objective| {
+            let config = SolverConfig::new().objective(objective);
+            dict.decrypt_full("ab ba abcdef", &config).unwrap()
+        };
+        let words = solve(Objective::MatchedWords);
+        assert!(words.plaintext.starts_with("no on "));
+        assert_eq!(words.matched_words, 2);
+        let letters = solve(Objective::MatchedLetters);
+        assert!(letters.plaintext.ends_with(" island"));
+        assert_eq!(letters.matched_words, 1);
+        assert_eq!(
+            "matched-letters".parse::<Objective>().unwrap(),
+            Objective::MatchedLetters
+        );
+    }
+
+    #[test]
+    fn skip_budget_limits_skipped_words() {
+        let dict = Dictionary::from_reader("hello\nworld\n".as_bytes()).unwrap();
+        let solve = |config: SolverConfig| dict.decrypt_full("ifmmp xpsme zzzzy", &config);
+        // No dictionary word has the pattern of "zzzzy", so it's as cheap to skip as a short word
+        let solution = solve(SolverConfig::new().max_skip_cost(1)).unwrap();
+        assert!(solution.plaintext.starts_with("hello world "));
+        assert_eq!(solution.skipped_words, [&solution.plaintext[12..]]);
+        assert!(matches!(
+            solve(SolverConfig::new().max_skip_cost(0)),
+            Err(Error::SearchExhausted)
+        ));
+        assert!(solve(SolverConfig::new().max_skip_ratio(0.3)).is_err());
+        assert!(solve(SolverConfig::new().max_skip_ratio(0.4)).is_ok());
+        assert!(solve(SolverConfig::new().skipping(false)).is_err());
+        // Every word is shorter, so none is free to skip
+        assert!(solve(SolverConfig::new().skipping(false).min_word_len(6)).is_err());
+        assert!(solve(
+            SolverConfig::new()
+                .algorithm(Algorithm::Beam)
+                .max_skip_cost(0)
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn short_words_and_rare_patterns_are_cheap_to_skip() {
+        let dict = Dictionary::from_reader("able\nbear\ncold\nhello\n".as_bytes()).unwrap();
+        let filtered = crate::filter_input("wxyz abccd");
+        let mut search = Search::new(
+            &filtered,
+            &dict.alphabet,
+            SolverConfig::new().language_freqs(&dict.alphabet),
+            &[],
+            None,
+            WordOrdering::default(),
+            Objective::default(),
+        )
+        .unwrap();
+        // Three words have the pattern of "wxyz", but only one has the pattern of "abccd"
+        assert_eq!(search.weigh_skips(&dict, 3), 2 + 1);
+        assert_eq!(search.weigh_skips(&dict, 4), 1 + 1);
+    }
+
+    #[test]
+    fn pinned_letters_are_kept() {
+        let dict = Dictionary::from_reader("hello\nworld\n".as_bytes()).unwrap();
+        let config = SolverConfig::new().pin_letter('h', 'i');
+        let solution = dict.decrypt_full("ifmmp xpsme", &config).unwrap();
+        assert_eq!(solution.plaintext, "hello world");
+
+        // No key which fits the dictionary deciphers 'i' as 'o'
+        for algorithm in Algorithm::ALL {
+            let config = SolverConfig::new()
+                .algorithm(algorithm)
+                .pin_letter('o', 'i')
+                .fallback(true);
+            let solution = dict.decrypt_full("ifmmp xpsme", &config).unwrap();
+            assert_eq!(
+                solution.key.to_string().chars().nth(14),
+                Some('i'),
+                "{algorithm}"
+            );
+        }
+
+        let solve = |config: SolverConfig| dict.decrypt_full("ifmmp xpsme", &config);
+        assert!(matches!(
+            solve(
+                SolverConfig::new()
+                    .pin_letter('h', 'i')
+                    .pin_letter('e', 'i')
+            ),
+            Err(Error::KeyConflict {
+                cipher: 'i',
+                plain: 'e'
+            })
+        ));
+        assert!(matches!(
+            solve(SolverConfig::new().pin_letter('h', 'ä')),
+            Err(Error::UnknownLetter('ä'))
+        ));
+    }
+
+    #[test]
+    fn cribs_are_placed_on_words_of_their_pattern() {
+        let (dict, cryptogram, config) = slow_cryptogram();
+        let config = config.crib("only");
+        let solution = dict.decrypt_full(&cryptogram.ciphertext, &config).unwrap();
+        assert_eq!(solution.plaintext, cryptogram.plaintext);
+
+        // The most common words go first, and pinned letters rule out words
+        let alphabet = Alphabet::english();
+        let placed = |pins: &[(u8, u8)]| {
+            place_cribs(b"abc xyz abc xxy", &[b"the".to_vec()], pins, &alphabet)
+                .unwrap()
+                .iter()
+                .map(|placement| placement.words.concat())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(placed(&[]), [b"abc", b"xyz"]);
+        assert_eq!(placed(&[(b'a', b'q')]), [b"xyz"]);
+
+        let result = dict.decrypt_full("qq", &SolverConfig::new().crib("the"));
+        assert!(matches!(result, Err(Error::UnplacedCrib(crib)) if crib == "the"));
+    }
+
+    #[test]
+    fn cribs_seed_every_algorithm() {
+        let dict = Dictionary::from_reader("hello\nworld\nhold\nheld\nwell\n".as_bytes()).unwrap();
+        // The crib only fits the second word, so its letters are pinned for every algorithm
+        let input = "ifmmp xpsme";
+        for algorithm in [
+            Algorithm::Backtracking,
+            Algorithm::Beam,
+            Algorithm::HillClimbing,
+            Algorithm::Annealing,
+        ] {
+            let config = SolverConfig::new().algorithm(algorithm).crib("world");
+            let mut solver = Solver::new(&dict, input, &config).unwrap();
+            assert_eq!(solver.search.seeds().len(), 1);
+            let solution = loop {
+                match solver.step(Duration::MAX) {
+                    StepResult::Pending => {}
+                    StepResult::Solved(solution) => break solution,
+                    result => panic!("{algorithm:?} failed: {result:?}"),
+                }
+            };
+            assert!(
+                solution.plaintext.ends_with("world"),
+                "{algorithm:?} gave {}",
+                solution.plaintext
+            );
+        }
+    }
+
+    #[test]
+    fn exhausted_search_stays_exhausted() {
+        let dict = Dictionary::from_reader("hello\nworld\n".as_bytes()).unwrap();
+        let mut solver = Solver::new(&dict, "qqqqqqq", &SolverConfig::new()).unwrap();
+        while solver.step(Duration::MAX) == StepResult::Pending {}
+        assert_eq!(solver.step(Duration::MAX), StepResult::Exhausted);
+    }
+
+    #[test]
+    fn key_search_finds_every_key_once() {
+        let dict = Dictionary::from_reader("cat\ncot\ndog\nhat\n".as_bytes()).unwrap();
+        let candidates: Vec<Candidate> = KeySearch::new("dpu ibu", &dict, &SolverConfig::new())
+            .unwrap()
+            .collect();
+        let mut plaintexts: Vec<&str> = candidates
+            .iter()
+            .map(|candidate| candidate.plaintext.as_str())
+            .collect();
+        // Keys which skip a word come after the ones which don't
+        assert_eq!(plaintexts[..2], ["cot hat", "hat cot"]);
+        assert!(candidates[..2]
+            .iter()
+            .all(|candidate| candidate.stats.matched_words == 2
+                && candidate.stats.skipped_words.is_empty()));
+        assert!(candidates[2..]
+            .iter()
+            .all(|candidate| candidate.stats.skipped_words.len() == 1));
+        plaintexts.sort_unstable();
+        plaintexts.dedup();
+        assert_eq!(plaintexts.len(), candidates.len());
+        assert!(candidates.iter().all(|candidate| candidate.stats.nodes > 0));
+
+        // The first key is the one which the solver finds
+        let (dict, cryptogram, config) = slow_cryptogram();
+        let mut search = KeySearch::new(&cryptogram.ciphertext, &dict, &config).unwrap();
+        let first = search.next().unwrap();
+        let solution = dict.decrypt_full(&cryptogram.ciphertext, &config).unwrap();
+        assert_eq!(first.plaintext, solution.plaintext);
+        assert_eq!(first.key, solution.key);
+        assert!(search.error().is_none());
+
+        let mut search =
+            KeySearch::new(&cryptogram.ciphertext, &dict, &config.clone().max_nodes(10)).unwrap();
+        assert!(search.next().is_none());
+        assert!(matches!(search.error(), Some(Error::BudgetExceeded)));
+    }
+
+    #[test]
+    fn budgets_stop_the_search() {
+        let (dict, cryptogram, config) = slow_cryptogram();
+        for config in [
+            config.clone().max_nodes(10),
+            config.clone().max_backtracks(1),
+        ] {
+            let result = dict.decrypt_full(&cryptogram.ciphertext, &config);
+            assert!(matches!(result, Err(Error::BudgetExceeded)));
+
+            let mut solver = Solver::new(&dict, &cryptogram.ciphertext, &config).unwrap();
+            assert_eq!(solver.step(Duration::MAX), StepResult::OutOfBudget);
+            assert_eq!(solver.step(Duration::MAX), StepResult::OutOfBudget);
+
+            let config = config.fallback(true);
+            let solution = dict.decrypt_full(&cryptogram.ciphertext, &config).unwrap();
+            assert!(solution.fallback);
+        }
+
+        let config = config.max_duration(Duration::ZERO);
+        let mut solver = Solver::new(&dict, &cryptogram.ciphertext, &config).unwrap();
+        assert_eq!(solver.step(Duration::MAX), StepResult::OutOfBudget);
+        let config = SolverConfig::new()
+            .max_nodes(10_000_000)
+            .max_backtracks(100);
+        let solution = dict.decrypt_full(&cryptogram.ciphertext, &config).unwrap();
+        assert_eq!(solution.plaintext, cryptogram.plaintext);
+    }
+
+    #[test]
+    fn progress_is_reported() {
+        let (dict, cryptogram, config) = slow_cryptogram();
+        let reports = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let reported = Arc::clone(&reports);
+        // A deadline keeps the search on one thread, where it takes long enough to be reported
+        let config = config
+            .max_duration(Duration::from_secs(1000))
+            .on_progress(move |progress| reported.lock().unwrap().push(progress.clone()));
+        dict.decrypt_full(&cryptogram.ciphertext, &config).unwrap();
+
+        let reports = reports.lock().unwrap();
+        assert!(!reports.is_empty());
+        for (earlier, later) in reports.iter().zip(reports.iter().skip(1)) {
+            assert!(earlier.nodes <= later.nodes);
+            assert!(earlier.best_words_placed <= later.best_words_placed);
+        }
+        for progress in reports.iter() {
+            assert_eq!(progress.words, 6);
+            assert!(progress.words_placed <= progress.best_words_placed);
+            assert!(progress.best_words_placed <= progress.words);
+        }
+    }
+
+    #[test]
+    fn cancelled_search_stays_cancelled() {
+        let (dict, cryptogram, config) = slow_cryptogram();
+        let token = CancelToken::new();
+        let config = config.cancel_token(token.clone());
+        let mut solver = Solver::new(&dict, &cryptogram.ciphertext, &config).unwrap();
+        assert_eq!(solver.step(Duration::ZERO), StepResult::Pending);
+
+        let canceller = std::thread::spawn(move || token.cancel());
+        canceller.join().unwrap();
+        assert_eq!(solver.step(Duration::MAX), StepResult::Cancelled);
+        assert_eq!(solver.step(Duration::MAX), StepResult::Cancelled);
+        let result = dict.decrypt_full(&cryptogram.ciphertext, &config.fallback(true));
+        assert!(matches!(result, Err(Error::Cancelled)));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_search_finds_the_same_key() {
+        let dict = Dictionary::from_reader(testgen::CORPUS.as_bytes()).unwrap();
+        for cryptogram in testgen::cryptograms(testgen::CORPUS, 5, 3, 3..=6) {
+            let filtered = crate::filter_input(&cryptogram.ciphertext);
+            let search = || {
+                let freqs = SolverConfig::new().language_freqs(&dict.alphabet);
+                Search::new(
+                    &filtered,
+                    &dict.alphabet,
+                    freqs,
+                    &[],
+                    None,
+                    WordOrdering::default(),
+                    Objective::default(),
+                )
+                .unwrap()
+            };
+            let key = search().run(&dict, None).unwrap().unwrap();
+            assert_eq!(search().run_parallel(&dict).unwrap(), key);
+        }
+    }
+
+    #[cfg(all(feature = "rayon", feature = "debug-invariants"))]
+    #[test]
+    fn parallel_branches_keep_the_invariants() {
+        let dict = Dictionary::from_reader("hello\nhelp\nworld\nword\n".as_bytes()).unwrap();
+        let filtered = crate::filter_input("ifmmp xpsme ifmq xpse");
+        let mut search = Search::new(
+            &filtered,
+            &dict.alphabet,
+            SolverConfig::new().language_freqs(&dict.alphabet),
+            &[],
+            None,
+            WordOrdering::default(),
+            Objective::default(),
+        )
+        .unwrap();
+        search.pins = vec![(b'i', b'h')];
+        // The branches check their state at every step, with the first word's guesses pinned
+        let key = search.run_parallel(&dict).unwrap();
+        assert_eq!(crate::encrypt_with_key("ifmmp xpsme", &key), "hello world");
+        // but only the given letter stays pinned in the key
+        assert_eq!(key.pinned.len(), 1);
+    }
+
+    #[test]
+    fn failed_states_arent_searched_again() {
+        let dict = Dictionary::from_reader("hello\nworld\n".as_bytes()).unwrap();
+        let filtered = crate::filter_input("bc ifmmp xpsme");
+        let freqs = SolverConfig::new().language_freqs(&dict.alphabet);
+        let mut search = Search::new(
+            &filtered,
+            &dict.alphabet,
+            freqs,
+            &[],
+            Some(5),
+            WordOrdering::Target,
+            Objective::default(),
+        )
+        .unwrap();
+        let key = search.run(&dict, None).unwrap().unwrap();
+        let nodes = search.nodes;
+
+        // The last word has to be skipped, and the round without a budget for it has failed
+        search.next_round = 0;
+        search.stack.clear();
+        search.start_round(&dict);
+        search.nodes = 0;
+        assert_eq!(search.run(&dict, None).unwrap().unwrap(), key);
+        assert!(search.nodes < nodes);
+    }
+
+    #[test]
+    fn propagation_narrows_down_guesses() {
+        let dict = Dictionary::from_reader("hello\nworld\nhold\nheld\nwell\n".as_bytes()).unwrap();
+        let filtered = crate::filter_input("ifmmp xpsme");
+        let freqs = SolverConfig::new().language_freqs(&dict.alphabet);
+        let mut search = Search::new(
+            &filtered,
+            &dict.alphabet,
+            freqs,
+            &[],
+            None,
+            WordOrdering::default(),
+            Objective::default(),
+        )
+        .unwrap();
+        search.propagate(&dict);
+        let domains = search.domains.unwrap();
+        for (cipher, plain) in [(b'i', b'h'), (b'm', b'l'), (b'p', b'o'), (b'x', b'w')] {
+            let domain = domains[Key::index(cipher)];
+            assert_eq!(domain.len(), 1);
+            assert!(domain.contains(plain - START));
+        }
+        let key = search.run(&dict, None).unwrap().unwrap();
+        assert_eq!(
+            crate::decrypt_with_key("ifmmp xpsme", &key.inverse()),
+            "hello world"
+        );
+        assert_eq!(search.nodes, 2);
+    }
+
+    #[test]
+    fn candidates_are_dictionary_words_with_free_letters() {
+        let alphabet = Alphabet::new("abc").unwrap();
+        let dict = Dictionary::from_reader_in("ab\nac\nba\nbc\nca\ncb\naa\n".as_bytes(), &alphabet)
+            .unwrap();
+        let freqs = SolverConfig::new().language_freqs(&alphabet);
+        let mut search = Search::new(
+            b"ab c",
+            &alphabet,
+            freqs,
+            &[],
+            None,
+            WordOrdering::default(),
+            Objective::default(),
+        )
+        .unwrap();
+        let candidates = |search: &Search, free_chars: &[u8]| {
+            let mut words: Vec<Vec<u8>> = search
+                .candidate_guesses(b"ab", free_chars, &dict, None)
+                .chunks(free_chars.len())
+                .map(<[u8]>::to_vec)
+                .collect();
+            words.sort();
+            words
+        };
+
+        // Fewer words of the pattern than guesses for both letters are looked up by the pattern
+        assert_eq!(
+            candidates(&search, b"ab"),
+            [b"ab", b"ac", b"ba", b"bc", b"ca", b"cb"]
+        );
+
+        // More words than guesses for the second letter are looked up by walking the trie
+        search.key.attach(b'a', b'c').unwrap();
+        assert_eq!(candidates(&search, b"b"), [b"a", b"b"]);
+
+        // A letter which is taken or pinned rules out the words which have it
+        search.key.attach(b'c', b'a').unwrap();
+        assert_eq!(candidates(&search, b"b"), [b"b"]);
+        search.key.clear(b'c');
+        search.key = search.key.clone().with_pins(&[(b'c', b'b')]);
+        assert_eq!(candidates(&search, b"b"), [b"a"]);
+    }
+
+    #[test]
+    fn fallback_ranks_letters_by_frequency() {
+        let dict = Dictionary::from_reader("hello\nworld\n".as_bytes()).unwrap();
+        let config = SolverConfig::new().fallback(true);
+        let mut solver = Solver::new(&dict, "qqqx qx", &config).unwrap();
+        let StepResult::Solved(solution) = solver.step(Duration::MAX) else {
+            panic!("No fallback solution");
+        };
+        assert!(solution.fallback);
+        assert_eq!(solution.plaintext, "eeet et");
+        assert_eq!(solver.step(Duration::MAX), StepResult::Solved(solution));
+
+        let solution = dict.decrypt_full("ifmmp xpsme", &config).unwrap();
+        assert!(!solution.fallback);
+    }
+
+    #[test]
+    fn long_inputs_dont_overflow_the_stack() {
+        let dict = Dictionary::from_reader("hello\nworld\n".as_bytes()).unwrap();
+        let input = "ifmmp xpsme ".repeat(50_000);
+        let solution = dict.decrypt_full(&input, &SolverConfig::new()).unwrap();
+        assert_eq!(solution.matched_words, 100_000);
+
+        let config = SolverConfig::new().max_words(99_999);
+        assert!(matches!(
+            dict.decrypt_full(&input, &config),
+            Err(Error::InputTooLong {
+                words: 100_000,
+                max: 99_999
+            })
+        ));
+    }
+
+    #[test]
+    fn anytime_gives_the_best_partial_key() {
+        let dict = Dictionary::from_reader("hello\nworld\n".as_bytes()).unwrap();
+        let config = SolverConfig::new().max_backtracks(0);
+        let input = "Ifmmp, xpsme! Zzzzy";
+        assert!(matches!(
+            dict.decrypt_full(input, &config),
+            Err(Error::BudgetExceeded)
+        ));
+
+        let config = config.anytime(true).fallback(true).keep_formatting(true);
+        let solution = dict.decrypt_full(input, &config).unwrap();
+        assert!(solution.partial && !solution.fallback);
+        assert_eq!(solution.plaintext, "Hello, world! _____");
+        assert_eq!(solution.matched_words, 2);
+        assert_eq!(solution.key.to_string(), "___ef__i___m__p__s____x___");
+
+        // Nothing was placed before the budget ran out, so the fallback is used
+        let solution = dict.decrypt_full("Zzzzy", &config).unwrap();
+        assert!(solution.fallback && !solution.partial);
+    }
+
+    #[test]
+    fn auto_algorithm_anneals_when_backtracking_fails() {
+        let dict = Dictionary::from_reader(testgen::CORPUS.as_bytes()).unwrap();
+        let plaintext = crate::normalize::Normalizer::default().normalize(testgen::CORPUS);
+        let plaintext: Vec<&str> = plaintext.split_whitespace().take(80).collect();
+        let plaintext = plaintext.join(" ");
+        let key = Key::random_in(&dict.alphabet, &mut StdRng::seed_from_u64(3));
+        let ciphertext = key.encipher(&plaintext);
+
+        let config = SolverConfig::new().algorithm(Algorithm::Auto);
+        let start = key.encipher("the old harbor town was quiet");
+        let solution = dict.decrypt_full(&start, &config).unwrap();
+        assert_eq!(solution.algorithm, Some(Algorithm::Backtracking));
+
+        let solution = dict
+            .decrypt_full(&ciphertext, &config.max_nodes(0))
+            .unwrap();
+        assert_eq!(solution.algorithm, Some(Algorithm::Annealing));
+        assert_eq!(solution.plaintext, plaintext);
+    }
+
+    #[test]
+    fn close_ranks_lean_on_word_starts() {
+        // "a long time ago": 'v' is as common as five other letters, so its rank is weak
+        // evidence, and it's guessed to be a letter which starts few words, like 'n' does
+        let input = b"g zyve wsci gey";
+        let english = Alphabet::english();
+        let freqs = LanguageFreqs::new(
+            crate::Language::English.frequency_order(),
+            &crate::Language::English,
+            &english,
+        );
+        let key = Key::new(input, &english, &freqs);
+        assert_eq!(key.guess_order(b'v').next(), Some(b'n'));
+
+        // Weighing every rank as if it could be told apart guesses an 'h', which starts words
+        let mut by_rank = key.clone();
+        by_rank.pick_first_guesses(input, &freqs.initial, &[0; R]);
+        assert_eq!(by_rank.guess_order(b'v').next(), Some(b'h'));
+    }
+}
@@ -0,0 +1,146 @@
+//! Where the solver looks up the words which it deciphers into, whatever they are stored in
+
+use crate::{Dictionary, Pattern};
+
+/// Words which ciphertext words are deciphered into, as letter codes of an [`crate::Alphabet`].
+/// The solver only looks words up through this, so that how they are stored can change without
+/// changing the search.
+pub(crate) trait WordSource {
+    /// Number of letters at the start of `word` which some word starts with, plus one if `word`
+    /// is a word itself
+    fn prefix_score(&self, word: &[u8]) -> usize;
+
+    /// Returns true if `word` is a word
+    fn contains(&self, word: &[u8]) -> bool {
+        self.prefix_score(word) == word.len() + 1
+    }
+
+    /// Words of `pattern`, see [`Pattern`]
+    fn candidates_for_pattern<'a>(&'a self, pattern: &Pattern) -> impl Iterator<Item = &'a [u8]>;
+
+    /// Number of words of `pattern`
+    fn pattern_count(&self, pattern: &Pattern) -> usize {
+        self.candidates_for_pattern(pattern).count()
+    }
+
+    /// Call `f` with each word of `pattern` whose every letter `allow` lets through, given the
+    /// letters before it. `allow` is passed letters as indices like the keys of a trie.
+    ///
+    /// By default, every word of the pattern is looked at. Sources which are sorted by prefix
+    /// can leave out the words after a letter which isn't allowed instead, so `allow` has to keep
+    /// to the pattern by itself.
+    fn for_each_allowed(
+        &self,
+        pattern: &Pattern,
+        mut allow: impl FnMut(&[usize], usize) -> bool,
+        mut f: impl FnMut(&[u8]),
+    ) {
+        for word in self.candidates_for_pattern(pattern) {
+            let indices: Vec<usize> = word.iter().map(|c| usize::from(*c)).collect();
+            if (0..word.len()).all(|len| allow(&indices[..len], indices[len])) {
+                f(word);
+            }
+        }
+    }
+}
+
+/// Words of a dictionary, looked up in its trie and its index of patterns
+impl WordSource for Dictionary {
+    fn prefix_score(&self, word: &[u8]) -> usize {
+        // Letters outside the alphabet start no words
+        self.words.prefix_score(word).unwrap_or(0)
+    }
+
+    fn candidates_for_pattern<'a>(&'a self, pattern: &Pattern) -> impl Iterator<Item = &'a [u8]> {
+        self.patterns.words(pattern)
+    }
+
+    fn pattern_count(&self, pattern: &Pattern) -> usize {
+        self.patterns.count(pattern)
+    }
+
+    fn for_each_allowed(
+        &self,
+        pattern: &Pattern,
+        allow: impl FnMut(&[usize], usize) -> bool,
+        mut f: impl FnMut(&[u8]),
+    ) {
+        let mut codes = Vec::with_capacity(pattern.len());
+        self.words.for_each_where(allow, |word| {
+            if word.len() == pattern.len() {
+                // The keys of the trie are letter codes
+                codes.clear();
+                codes.extend(word.iter().filter_map(|c| u8::try_from(*c).ok()));
+                f(&codes);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Words which only know their patterns, going through the provided lookups
+    struct Words(Vec<Vec<u8>>);
+
+    impl WordSource for Words {
+        fn prefix_score(&self, word: &[u8]) -> usize {
+            let prefix = (0..=word.len())
+                .rev()
+                .find(|len| self.0.iter().any(|other| other.starts_with(&word[..*len])))
+                .unwrap_or(0);
+            prefix + usize::from(self.0.iter().any(|other| other == word))
+        }
+
+        fn candidates_for_pattern<'a>(
+            &'a self,
+            pattern: &Pattern,
+        ) -> impl Iterator<Item = &'a [u8]> {
+            let pattern = pattern.clone();
+            self.0
+                .iter()
+                .filter(move |word| Pattern::of_letters(word.iter().copied()) == pattern)
+                .map(Vec::as_slice)
+        }
+    }
+
+    #[test]
+    fn sources_find_the_same_words() {
+        let dict = Dictionary::from_reader("hello\nhelps\nworld\nhold\n".as_bytes()).unwrap();
+        let words = Words(vec![
+            b"hello".to_vec(),
+            b"helps".to_vec(),
+            b"world".to_vec(),
+            b"hold".to_vec(),
+        ]);
+        for word in [&b"hello"[..], b"help", b"hells", b"xyz", b""] {
+            assert_eq!(dict.prefix_score(word), words.prefix_score(word));
+            assert_eq!(
+                WordSource::contains(&dict, word),
+                WordSource::contains(&words, word)
+            );
+        }
+
+        let pattern = Pattern::of_letters(b"abcde".iter().copied());
+        assert_eq!(dict.pattern_count(&pattern), 2);
+        assert_eq!(words.pattern_count(&pattern), 2);
+        assert_eq!(allowed(&dict, &pattern, b'w'), [b"world"]);
+        assert_eq!(allowed(&words, &pattern, b'w'), [b"world"]);
+        assert_eq!(allowed(&dict, &pattern, b'h'), [b"helps"]);
+        assert_eq!(allowed(&words, &pattern, b'h'), [b"helps"]);
+    }
+
+    /// Words of `pattern` in `source` which start with `first`, with no letter twice
+    fn allowed(source: &impl WordSource, pattern: &Pattern, first: u8) -> Vec<Vec<u8>> {
+        let mut found = Vec::new();
+        source.for_each_allowed(
+            pattern,
+            |prefix, letter| {
+                !prefix.contains(&letter) && (!prefix.is_empty() || letter == usize::from(first))
+            },
+            |word| found.push(word.to_vec()),
+        );
+        found
+    }
+}
@@ -0,0 +1,164 @@
+use crate::{ASCII_LETTERS as R, END, START};
+use std::io::Read;
+
+/// Size of the chunks which [`LetterStats::from_reader`] reads at a time
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Letter and bigram counts of text, accumulated in pieces of any size.
+///
+/// Letters are counted case-insensitively. A bigram is two letters next to each other, so
+/// anything else between letters, like whitespace, separates them.
+///
+/// ```
+/// let mut stats = substitution::LetterStats::new();
+/// stats.update(b"Hello wo");
+/// stats.update(b"rld");
+/// assert_eq!(stats.letter_count('l'), 3);
+/// assert_eq!(stats.bigram_count('o', 'r'), 1);
+/// assert_eq!(stats.bigram_count('o', 'w'), 0);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LetterStats {
+    letters: [u64; R],
+    bigrams: [[u64; R]; R],
+    previous: Option<usize>,
+}
+
+impl Default for LetterStats {
+    fn default() -> Self {
+        Self {
+            letters: [0; R],
+            bigrams: [[0; R]; R],
+            previous: None,
+        }
+    }
+}
+
+impl LetterStats {
+    /// Create empty statistics
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accumulate statistics from reading `reader` to the end, a chunk at a time
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from reading.
+    pub fn from_reader(mut reader: impl Read) -> std::io::Result<Self> {
+        let mut stats = Self::new();
+        let mut chunk = vec![0; CHUNK_SIZE];
+        loop {
+            match reader.read(&mut chunk) {
+                Ok(0) => return Ok(stats),
+                Ok(len) => stats.update(&chunk[..len]),
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Accumulate statistics from the next piece of text. A bigram may span two pieces.
+    pub fn update(&mut self, text: &[u8]) {
+        for chr in text {
+            let chr = chr.to_ascii_lowercase();
+            if (START..=END).contains(&chr) {
+                let idx = usize::from(chr - START);
+                self.letters[idx] += 1;
+                if let Some(previous) = self.previous {
+                    self.bigrams[previous][idx] += 1;
+                }
+                self.previous = Some(idx);
+            } else {
+                self.previous = None;
+            }
+        }
+    }
+
+    /// Number of letters counted
+    #[must_use]
+    pub fn total_letters(&self) -> u64 {
+        self.letters.iter().sum()
+    }
+
+    /// Number of times `letter` was counted, or 0 if it's not an ASCII letter
+    #[must_use]
+    pub fn letter_count(&self, letter: char) -> u64 {
+        Self::index(letter).map_or(0, |idx| self.letters[idx])
+    }
+
+    /// Number of times `first` was directly followed by `second`
+    #[must_use]
+    pub fn bigram_count(&self, first: char, second: char) -> u64 {
+        match (Self::index(first), Self::index(second)) {
+            (Some(first), Some(second)) => self.bigrams[first][second],
+            _ => 0,
+        }
+    }
+
+    /// Letters which were counted at least once, most common first
+    #[must_use]
+    pub fn letters(&self) -> Vec<(char, u64)> {
+        let mut letters: Vec<(char, u64)> = (START..=END)
+            .map(char::from)
+            .zip(self.letters)
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        letters.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        letters
+    }
+
+    /// Bigrams which were counted at least once, most common first
+    #[must_use]
+    pub fn bigrams(&self) -> Vec<([char; 2], u64)> {
+        let mut bigrams: Vec<([char; 2], u64)> = (START..=END)
+            .zip(self.bigrams)
+            .flat_map(|(first, row)| {
+                (START..=END)
+                    .zip(row)
+                    .map(move |(second, count)| ([char::from(first), char::from(second)], count))
+            })
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        bigrams.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        bigrams
+    }
+
+    /// Table index of an ASCII letter
+    fn index(letter: char) -> Option<usize> {
+        let letter = u8::try_from(letter.to_ascii_lowercase()).ok()?;
+        (START..=END)
+            .contains(&letter)
+            .then(|| usize::from(letter - START))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn chunked_updates_equal_one_update() {
+        let text = b"The quick brown fox, the lazy dog. \xff THE END";
+        let mut whole = LetterStats::new();
+        whole.update(text);
+        for split in 0..text.len() {
+            let mut pieces = LetterStats::new();
+            pieces.update(&text[..split]);
+            pieces.update(&text[split..]);
+            assert_eq!(pieces, whole);
+        }
+        assert_eq!(LetterStats::from_reader(&text[..]).unwrap(), whole);
+    }
+
+    #[test]
+    fn most_common_first() {
+        let mut stats = LetterStats::new();
+        stats.update(b"that then them");
+        assert_eq!(stats.total_letters(), 12);
+        assert_eq!(stats.letters()[..3], [('t', 4), ('h', 3), ('e', 2)]);
+        assert_eq!(stats.bigrams()[0], (['t', 'h'], 3));
+        assert_eq!(stats.letter_count('!'), 0);
+    }
+}
@@ -0,0 +1,94 @@
+//! Reproducible cryptograms for benchmarks and solver experiments.
+//!
+//! ```
+//! use substitution::testgen;
+//!
+//! let cryptograms = testgen::cryptograms(testgen::CORPUS, 42, 3, 4..=8);
+//! assert_eq!(cryptograms, testgen::cryptograms(testgen::CORPUS, 42, 3, 4..=8));
+//! assert_eq!(cryptograms.len(), 3);
+//! ```
+
+use crate::{normalize::Normalizer, Key};
+use rand::prelude::*;
+use std::ops::RangeInclusive;
+
+/// Built-in English plaintext which cryptograms can be generated from
+pub const CORPUS: &str = include_str!("corpus.txt");
+
+/// A plaintext passage, its encryption and the key which was used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cryptogram {
+    /// Normalized passage of the corpus
+    pub plaintext: String,
+    /// Encrypted plaintext
+    pub ciphertext: String,
+    /// Ciphertext letter of each plaintext letter from a to z
+    pub key: String,
+}
+
+/// Generate `count` cryptograms from random passages of `corpus`, each with a random number of
+/// words in `words` and its own random key. The same arguments always give the same cryptograms.
+///
+/// Passage lengths are capped to the number of words in `corpus`, and an empty corpus gives no
+/// cryptograms.
+#[must_use]
+pub fn cryptograms(
+    corpus: &str,
+    seed: u64,
+    count: usize,
+    words: RangeInclusive<usize>,
+) -> Vec<Cryptogram> {
+    let corpus = Normalizer::default().normalize(corpus);
+    let corpus: Vec<&str> = corpus.split_whitespace().collect();
+    if corpus.is_empty() {
+        return Vec::new();
+    }
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let (min, max) = words.into_inner();
+    let max = max.min(corpus.len());
+    let min = min.clamp(1, max);
+
+    (0..count)
+        .map(|_| {
+            let len = rng.gen_range(min..=max);
+            let start = rng.gen_range(0..=corpus.len() - len);
+            let plaintext = corpus[start..start + len].join(" ");
+
+            let key = Key::random_with(&mut rng);
+            Cryptogram {
+                ciphertext: key.encipher(&plaintext),
+                plaintext,
+                key: key.to_string(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn key_encrypts_plaintext() {
+        for cryptogram in cryptograms(CORPUS, 0, 10, 1..=12) {
+            let encrypted: String = cryptogram
+                .plaintext
+                .bytes()
+                .map(|c| match c {
+                    b'a'..=b'z' => char::from(cryptogram.key.as_bytes()[usize::from(c - b'a')]),
+                    _ => char::from(c),
+                })
+                .collect();
+            assert_eq!(encrypted, cryptogram.ciphertext);
+            assert!((1..=12).contains(&cryptogram.plaintext.split(' ').count()));
+        }
+    }
+
+    #[test]
+    fn lengths_are_capped_to_corpus() {
+        let cryptograms = cryptograms("One, two-three!", 1, 3, 5..=10);
+        assert!(cryptograms.iter().all(|c| c.plaintext == "one two three"));
+        assert!(super::cryptograms("", 1, 3, 5..=10).is_empty());
+    }
+}
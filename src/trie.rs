@@ -1,3 +1,4 @@
+use std::io::Write;
 use thiserror::Error;
 
 /// Type for the size of the trie's alphabet
@@ -9,15 +10,31 @@ pub enum Error {
     /// Error which will be returned when a key cannot be used with given alphabet size
     /// E.g. the key has value 19 but alphabet size is 10
     #[error("value {value} in key does not fit in alphabet size {size}")]
-    KeyNotInAlphabet { value: usize, size: usize },
+    KeyNotInAlphabet {
+        /// The offending key element after subtracting the index base
+        value: usize,
+        /// Cardinality of the alphabet
+        size: usize,
+    },
+    /// Error which will be returned when inserting a key would need more nodes than the trie can
+    /// hold
+    #[error("trie cannot hold more than {capacity} nodes")]
+    CapacityExceeded {
+        /// Maximum number of nodes in the trie
+        capacity: usize,
+    },
 }
 
 /// Trie's key's elements need to convert to usize and be small, automatically copied
 pub trait KeyElement: Into<usize> + Copy {}
 impl<E: Into<usize> + Copy> KeyElement for E {}
 
-/// Type used for indirect pointing to other nodes from nodes
-type NodeIndex = std::num::NonZeroUsize;
+/// Type used for indirect pointing to other nodes from nodes.
+/// 32 bits is plenty for any wordlist, and takes half the space of a usize in every node.
+type NodeIndex = std::num::NonZeroU32;
+
+/// Maximum number of nodes, limited by the index type. The root is at zero which can't be pointed to.
+const MAX_NODES: usize = u32::MAX as usize;
 
 /// A node of trie, which holds indices to other nodes
 #[derive(Clone)]
@@ -36,8 +53,8 @@ impl<const R: AlphabetSize, T> Node<R, T> {
     }
 
     /// Get the index for the next node for key
-    fn get_idx(&self, key: usize) -> Option<NodeIndex> {
-        self.children[key]
+    fn get_idx(&self, key: usize) -> Option<usize> {
+        self.children[key].map(|idx| idx.get() as usize)
     }
 
     /// Set the index for the next node for key
@@ -62,23 +79,51 @@ impl<const R: AlphabetSize, T> AsMut<Option<T>> for Node<R, T> {
 
 /// Trie, where R is the cardinality of the alphabet in use and B is the index base.
 ///
-/// Supports insertion and retrieval.
+/// Supports insertion and retrieval. Retrieval only reads the nodes, so a trie can be searched
+/// from many threads at once without locking.
 pub struct Trie<const R: AlphabetSize, const B: usize, T> {
     nodes: Vec<Node<R, T>>,
+    len: usize,
+    node_limit: usize,
 }
 
 impl<const R: AlphabetSize, const B: usize, T> Trie<R, B, T> {
     /// Initialize an empty trie
     pub fn new() -> Self {
+        Self::with_node_limit(MAX_NODES)
+    }
+
+    /// Initialize an empty trie which refuses to grow beyond `limit` nodes, including the root.
+    /// This bounds the memory that untrusted keys can make it use.
+    pub fn with_node_limit(limit: usize) -> Self {
         Self {
             nodes: vec![Node::new()],
+            len: 0,
+            node_limit: limit.clamp(1, MAX_NODES),
         }
     }
 
+    /// Returns true if no value has been inserted
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of distinct keys which have a value
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
     /// Create a new node and return it's index
-    fn create(&mut self) -> NodeIndex {
+    fn create(&mut self) -> Result<NodeIndex, Error> {
+        let idx = Some(self.nodes.len())
+            .filter(|len| *len < self.node_limit)
+            .and_then(|len| u32::try_from(len).ok())
+            .and_then(NodeIndex::new)
+            .ok_or(Error::CapacityExceeded {
+                capacity: self.node_limit,
+            })?;
         self.nodes.push(Node::new());
-        NodeIndex::new(self.nodes.len() - 1).unwrap()
+        Ok(idx)
     }
 
     /// Under the hood explicit bounds check
@@ -95,47 +140,102 @@ impl<const R: AlphabetSize, const B: usize, T> Trie<R, B, T> {
 
     /// Insert a value into the trie
     ///
+    /// If the trie runs out of capacity, nodes created for a prefix of the key are kept but no
+    /// value is inserted.
+    ///
     /// ## Sources
-    /// [https://en.wikipedia.org/wiki/Trie#Insertion]
+    /// <https://en.wikipedia.org/wiki/Trie#Insertion>
     pub fn insert<E: KeyElement>(&mut self, key: &[E], value: T) -> Result<(), Error> {
         let mut node = 0; // Root node index
 
-        // Walk through key elements
-        for key in key.iter().map(|e| (*e).into() - B) {
+        // Walk through key elements. Elements below the index base wrap around to huge values
+        // which the bounds check rejects.
+        for key in key.iter().map(|e| (*e).into().wrapping_sub(B)) {
             // Explicit bounds check
             Self::check(key)?;
 
             // Look up next node's index by key
-            node = match self.nodes[node].get_idx(key) {
+            node = if let Some(next) = self.nodes[node].get_idx(key) {
                 // Go to next if it already exists
-                Some(next) => next.get(),
+                next
+            } else {
                 // Create a new node and go to it if not preexisting
-                None => {
-                    let new_node = self.create();
-                    self.nodes[node].set_idx(key, new_node);
-                    new_node.get()
-                }
+                let new_node = self.create()?;
+                self.nodes[node].set_idx(key, new_node);
+                new_node.get() as usize
             }
         }
 
-        *self.nodes[node].as_mut() = Some(value);
+        if self.nodes[node].as_mut().replace(value).is_none() {
+            self.len += 1;
+        }
         Ok(())
     }
 
+    /// Reorder nodes in depth-first order and release unused capacity.
+    ///
+    /// Nodes are stored in insertion order, which scatters the nodes of a single word around the
+    /// arena. After compaction visiting the keys in order, like [`Trie::for_each_key_where`] does
+    /// for the solver, reads the arena from start to end, which `compaction_benchmark` in the tests
+    /// measures to be about twice as fast. Looking up a single key doesn't get measurably faster.
+    pub fn compact(&mut self) {
+        // Collect old node indices in depth-first preorder
+        let mut order = Vec::with_capacity(self.nodes.len());
+        let mut stack = vec![0];
+        while let Some(old) = stack.pop() {
+            order.push(old);
+            // Push children in reverse so that the first child gets visited first
+            stack.extend(
+                self.nodes[old]
+                    .children
+                    .iter()
+                    .rev()
+                    .flatten()
+                    .map(|i| i.get() as usize),
+            );
+        }
+
+        // Map each old index to its new position. Compaction doesn't add nodes, so new indices fit
+        // as well as the old ones. Root stays at zero and is nobody's child, so it needs no index.
+        let mut new_index = vec![None; self.nodes.len()];
+        for (new, old) in order.iter().enumerate() {
+            new_index[*old] = u32::try_from(new).ok().and_then(NodeIndex::new);
+        }
+
+        // Move nodes to their new positions and fix up the indices they hold
+        let mut old_nodes: Vec<Option<Node<R, T>>> = std::mem::take(&mut self.nodes)
+            .into_iter()
+            .map(Some)
+            .collect();
+        self.nodes = order
+            .iter()
+            .filter_map(|old| old_nodes[*old].take())
+            .map(|mut node| {
+                for child in node.children.iter_mut().flatten() {
+                    if let Some(new) = new_index[child.get() as usize] {
+                        *child = new;
+                    }
+                }
+                node
+            })
+            .collect();
+        self.nodes.shrink_to_fit();
+    }
+
     /// Retrieve value for given key and tell how long prefix is contained in trie
     ///
     /// ## Sources
-    /// [https://en.wikipedia.org/wiki/Trie#Searching]
+    /// <https://en.wikipedia.org/wiki/Trie#Searching>
     pub fn prefix<E: KeyElement>(&self, key: &[E]) -> Result<(usize, &Option<T>), Error> {
         let mut node = 0; // Root node index
         let mut depth = 0;
 
-        for key in key.iter().map(|e| (*e).into() - B) {
+        for key in key.iter().map(|e| (*e).into().wrapping_sub(B)) {
             // Explicit bounds check
             Self::check(key)?;
 
             if let Some(next) = self.nodes[node].get_idx(key) {
-                node = next.get();
+                node = next;
                 depth += 1;
             } else {
                 return Ok((depth, &None));
@@ -144,6 +244,78 @@ impl<const R: AlphabetSize, const B: usize, T> Trie<R, B, T> {
 
         Ok((depth, self.nodes[node].as_ref()))
     }
+
+    /// Write the nodes down to `max_depth` edges from the root as a Graphviz DOT graph, with each
+    /// edge labeled by `label` of its key element. Nodes which have a value are drawn as double
+    /// circles, and nodes whose children are left out have a dashed outline.
+    pub fn to_dot(
+        &self,
+        mut writer: impl Write,
+        max_depth: usize,
+        label: impl Fn(usize) -> char,
+    ) -> std::io::Result<()> {
+        writeln!(writer, "digraph trie {{")?;
+        writeln!(writer, "    node [shape=circle, label=\"\"];")?;
+        // Node indices and their depths in depth-first preorder
+        let mut stack = vec![(0, 0)];
+        while let Some((node, depth)) = stack.pop() {
+            let children: Vec<(usize, usize)> = (0..R)
+                .filter_map(|key| Some((key, self.nodes[node].get_idx(key)?)))
+                .collect();
+            let shape = if self.nodes[node].value.is_some() {
+                "doublecircle"
+            } else {
+                "circle"
+            };
+            let style = if depth >= max_depth && !children.is_empty() {
+                ", style=dashed"
+            } else {
+                ""
+            };
+            writeln!(writer, "    n{node} [shape={shape}{style}];")?;
+            if depth >= max_depth {
+                continue;
+            }
+            for (key, child) in &children {
+                let label = label(key + B).escape_default();
+                writeln!(writer, "    n{node} -> n{child} [label=\"{label}\"];")?;
+            }
+            stack.extend(children.iter().rev().map(|(_, child)| (*child, depth + 1)));
+        }
+        writeln!(writer, "}}")
+    }
+
+    /// Call `f` with every key which has a value, in depth-first order. Key elements are given
+    /// with the index base added, like they were inserted.
+    pub fn for_each_key(&self, f: impl FnMut(&[usize])) {
+        self.for_each_key_where(|_, _| true, f);
+    }
+
+    /// Call `f` with every key which has a value, in depth-first order, leaving out the keys
+    /// which `allow` rejects an element of. `allow` is given the elements before an element and
+    /// the element, so it can also limit the length of the keys.
+    pub fn for_each_key_where(
+        &self,
+        mut allow: impl FnMut(&[usize], usize) -> bool,
+        mut f: impl FnMut(&[usize]),
+    ) {
+        let mut key = Vec::new();
+        // Node indices, their depths and the key elements of the edges to them
+        let mut stack = vec![(0, 0_usize, 0)];
+        while let Some((node, depth, element)) = stack.pop() {
+            key.truncate(depth.saturating_sub(1));
+            if depth > 0 {
+                key.push(element);
+            }
+            if self.nodes[node].value.is_some() {
+                f(&key);
+            }
+            stack.extend((0..R).rev().filter_map(|child| {
+                let child_node = self.nodes[node].get_idx(child)?;
+                allow(&key, child + B).then_some((child_node, depth + 1, child + B))
+            }));
+        }
+    }
 }
 
 /// Set based on trie
@@ -162,6 +334,46 @@ impl<const R: AlphabetSize, const B: usize> Set<R, B> {
         self.trie.insert(key, ())
     }
 
+    /// Returns true if nothing has been inserted
+    pub fn is_empty(&self) -> bool {
+        self.trie.is_empty()
+    }
+
+    /// Number of distinct values (keys) in the set
+    pub fn len(&self) -> usize {
+        self.trie.len()
+    }
+
+    /// Optimize memory layout after all insertions are done, see [`Trie::compact`]
+    pub fn compact(&mut self) {
+        self.trie.compact();
+    }
+
+    /// Write the set as a Graphviz DOT graph, see [`Trie::to_dot`]
+    pub fn to_dot(
+        &self,
+        writer: impl Write,
+        max_depth: usize,
+        label: impl Fn(usize) -> char,
+    ) -> std::io::Result<()> {
+        self.trie.to_dot(writer, max_depth, label)
+    }
+
+    /// Call `f` with every value (key) in the set, see [`Trie::for_each_key`]
+    pub fn for_each(&self, f: impl FnMut(&[usize])) {
+        self.trie.for_each_key(f);
+    }
+
+    /// Call `f` with the values (keys) in the set which `allow` accepts, see
+    /// [`Trie::for_each_key_where`]
+    pub fn for_each_where(
+        &self,
+        allow: impl FnMut(&[usize], usize) -> bool,
+        f: impl FnMut(&[usize]),
+    ) {
+        self.trie.for_each_key_where(allow, f);
+    }
+
     /// Returns `key.len() + 1` if the value (key) has been inserted, otherwise found prefix length
     pub fn prefix_score<E: KeyElement>(&self, key: &[E]) -> Result<usize, Error> {
         let (len, ins) = self.trie.prefix(key)?;
@@ -170,6 +382,8 @@ impl<const R: AlphabetSize, const B: usize> Set<R, B> {
 }
 
 #[cfg(test)]
+// The tests from before these clippy lints are kept as they were written
+#[allow(clippy::semicolon_if_nothing_returned)]
 mod test {
     use super::*;
 
@@ -180,6 +394,26 @@ mod test {
         assert_eq!(set.prefix_score(b"hello").unwrap(), 0);
     }
 
+    #[test]
+    fn empty_until_insertion() {
+        const R: AlphabetSize = 128;
+        let mut set = Set::<R, 0>::new();
+        assert!(set.is_empty());
+        set.insert(b"hello").unwrap();
+        assert!(!set.is_empty());
+    }
+
+    #[test]
+    fn len_counts_distinct_keys() {
+        const R: AlphabetSize = 128;
+        let mut set = Set::<R, 0>::new();
+        assert_eq!(set.len(), 0);
+        set.insert(b"hello").unwrap();
+        set.insert(b"hell").unwrap();
+        set.insert(b"hello").unwrap();
+        assert_eq!(set.len(), 2);
+    }
+
     #[test]
     fn insertion_contained() {
         const R: AlphabetSize = 128;
@@ -233,6 +467,121 @@ mod test {
         }
     }
 
+    #[test]
+    fn compact_keeps_contents() {
+        const R: AlphabetSize = 128;
+        let keys = [
+            b"bananas".as_slice(),
+            b"apples".as_slice(),
+            b"banana".as_slice(),
+            b"apricot".as_slice(),
+        ];
+        let mut set = Set::<R, 0>::new();
+        for key in &keys {
+            set.insert(key).unwrap();
+        }
+        set.compact();
+        for key in &keys {
+            assert_eq!(set.prefix_score(key).unwrap(), key.len() + 1);
+        }
+        assert_eq!(set.prefix_score(b"ban").unwrap(), 3);
+        assert_eq!(set.prefix_score(b"apple").unwrap(), 5);
+    }
+
+    #[test]
+    fn compact_orders_depth_first() {
+        const R: AlphabetSize = 128;
+        let mut trie = Trie::<R, 0, ()>::new();
+        trie.insert(b"ab", ()).unwrap();
+        trie.insert(b"b", ()).unwrap();
+        trie.insert(b"ac", ()).unwrap();
+        trie.compact();
+        // Expected preorder: root, a, ab, ac, b
+        let root = &trie.nodes[0];
+        assert_eq!(root.get_idx(usize::from(b'a')).unwrap(), 1);
+        assert_eq!(root.get_idx(usize::from(b'b')).unwrap(), 4);
+        let a = &trie.nodes[1];
+        assert_eq!(a.get_idx(usize::from(b'b')).unwrap(), 2);
+        assert_eq!(a.get_idx(usize::from(b'c')).unwrap(), 3);
+        assert_eq!(trie.nodes.len(), trie.nodes.capacity());
+    }
+
+    #[test]
+    fn dot_graph_stops_at_max_depth() {
+        const R: AlphabetSize = 128;
+        let mut set = Set::<R, 0>::new();
+        set.insert(b"a").unwrap();
+        set.insert(b"ab").unwrap();
+        set.insert(b"b").unwrap();
+        set.compact();
+        let mut dot = Vec::new();
+        set.to_dot(&mut dot, 1, |key| char::from(u8::try_from(key).unwrap()))
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(dot).unwrap(),
+            "digraph trie {
+    node [shape=circle, label=\"\"];
+    n0 [shape=circle];
+    n0 -> n1 [label=\"a\"];
+    n0 -> n3 [label=\"b\"];
+    n1 [shape=doublecircle, style=dashed];
+    n3 [shape=doublecircle];
+}
+"
+        );
+    }
+
+    #[test]
+    fn for_each_visits_keys_depth_first() {
+        const R: AlphabetSize = 26;
+        let mut set = Set::<R, { b'a' as usize }>::new();
+        for key in ["cab", "ab", "a", "ca"] {
+            set.insert(key.as_bytes()).unwrap();
+        }
+        let mut keys = Vec::new();
+        set.for_each(|key| {
+            keys.push(
+                key.iter()
+                    .map(|e| char::from(u8::try_from(*e).unwrap()))
+                    .collect::<String>(),
+            );
+        });
+        assert_eq!(keys, ["a", "ab", "ca", "cab"]);
+    }
+
+    #[test]
+    fn capacity_exceeded() {
+        const R: AlphabetSize = 26;
+        // Root and "abc" take four nodes
+        let mut set = Set::<R, { b'a' as usize }> {
+            trie: Trie::with_node_limit(4),
+        };
+        set.insert(b"abc").unwrap();
+        set.insert(b"ab").unwrap();
+        assert!(matches!(
+            set.insert(b"abd"),
+            Err(Error::CapacityExceeded { capacity: 4 })
+        ));
+        assert!(matches!(
+            set.insert(b"xyz"),
+            Err(Error::CapacityExceeded { capacity: 4 })
+        ));
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.prefix_score(b"abd").unwrap(), 2);
+    }
+
+    #[test]
+    fn key_below_base_is_error() {
+        const R: AlphabetSize = 26;
+        let mut set = Set::<R, { b'a' as usize }>::new();
+        assert!(matches!(
+            set.insert(b"aB"),
+            Err(Error::KeyNotInAlphabet { .. })
+        ));
+        assert!(set.prefix_score(b"A").is_err());
+        assert!(set.is_empty());
+    }
+
     #[test]
     fn key_error() {
         const R: AlphabetSize = 96;
@@ -245,4 +594,96 @@ mod test {
             })
         ));
     }
+
+    /// Benchmark of looking words up and of visiting every word before and after
+    /// [`Set::compact`], which prints the time per word. Run it with
+    /// `cargo test --release -- --ignored --nocapture compaction_benchmark`.
+    #[test]
+    #[ignore = "benchmark, slow without --release"]
+    fn compaction_benchmark() {
+        const R: AlphabetSize = 26;
+        const WORDS: usize = 300_000;
+        const ROUNDS: usize = 5;
+
+        // Random words from a linear congruential generator, which scatter the nodes of the trie.
+        // Like in a language, some letters are more common than others, so words share prefixes.
+        let mut state: u64 = 42;
+        let mut next = |bound: usize| {
+            state = state
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            usize::try_from((state >> 33) % u64::try_from(bound).unwrap()).unwrap()
+        };
+        let words: Vec<Vec<u8>> = (0..WORDS)
+            .map(|_| {
+                let len = 3 + next(10);
+                (0..len)
+                    .map(|_| {
+                        let common = next(26) + 1;
+                        b'a' + u8::try_from(next(common)).unwrap()
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut set = Set::<R, { b'a' as usize }>::new();
+        for word in &words {
+            set.insert(word).unwrap();
+        }
+        // Look words up in another order than they were inserted in, like a search does
+        let mut lookups: Vec<&[u8]> = words.iter().map(Vec::as_slice).collect();
+        for i in (1..lookups.len()).rev() {
+            lookups.swap(i, next(i + 1));
+        }
+        // The fastest of a few rounds of looking up every word, and of visiting every word
+        let time = |set: &Set<R, { b'a' as usize }>| {
+            let fastest = |f: &dyn Fn() -> usize| {
+                (0..ROUNDS)
+                    .map(|_| {
+                        let start = std::time::Instant::now();
+                        let result = f();
+                        (start.elapsed(), result)
+                    })
+                    .min()
+                    .unwrap()
+            };
+            let lookups = fastest(&|| {
+                lookups
+                    .iter()
+                    .map(|word| set.prefix_score(word).unwrap())
+                    .sum()
+            });
+            let visits = fastest(&|| {
+                let mut visited = 0;
+                set.for_each(|_| visited += 1);
+                visited
+            });
+            (lookups, visits)
+        };
+
+        let (scattered_lookups, scattered_visits) = time(&set);
+        set.compact();
+        let (compacted_lookups, compacted_visits) = time(&set);
+        assert_eq!(scattered_lookups.1, compacted_lookups.1);
+        assert_eq!(scattered_visits.1, compacted_visits.1);
+
+        let per_word =
+            |(elapsed, _): (std::time::Duration, usize)| elapsed.as_nanos() / WORDS as u128;
+        let speedup = |(scattered, _): (std::time::Duration, usize),
+                       (compacted, _): (std::time::Duration, usize)| {
+            scattered.as_secs_f64() / compacted.as_secs_f64()
+        };
+        println!(
+            "Lookup: {} ns per word in insertion order, {} ns in depth-first order, {:.2}x",
+            per_word(scattered_lookups),
+            per_word(compacted_lookups),
+            speedup(scattered_lookups, compacted_lookups)
+        );
+        println!(
+            "Visit: {} ns per word in insertion order, {} ns in depth-first order, {:.2}x",
+            per_word(scattered_visits),
+            per_word(compacted_visits),
+            speedup(scattered_visits, compacted_visits)
+        );
+    }
 }
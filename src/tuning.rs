@@ -0,0 +1,6 @@
+//! Solver heuristics tuned on generated cryptograms. Generated with `substitution tune`, which
+//! prints a new version of this file.
+
+/// Number of distinct letters which the words deciphered first should have, by the maximum number
+/// of words in the input. Words are deciphered in order of distance from this target.
+pub static WORD_ORDER_TARGETS: [(usize, usize); 3] = [(4, 7), (8, 7), (usize::MAX, 7)];
@@ -0,0 +1,109 @@
+//! Checking every word of an input against a full or partial key, shared by all the strategies
+//! which score a key as a whole
+
+use crate::{source::WordSource, R, START};
+use std::collections::HashMap;
+
+/// Number of distinct words from which they are deciphered on all CPU cores. Fewer are quicker to
+/// decipher on one than to hand out.
+#[cfg(feature = "rayon")]
+const PARALLEL_WORDS: usize = 1024;
+
+/// An input word deciphered with a key, see [`verify_key`]
+pub(crate) struct Deciphered<'a> {
+    /// Plaintext letter codes of the word up to its first letter which the key doesn't map
+    pub(crate) letters: &'a [u8],
+    /// True if the key maps every letter of the word, so `letters` is all of it
+    pub(crate) whole: bool,
+    /// Number of times the word occurs in the input
+    pub(crate) count: usize,
+}
+
+/// Decipher each of `words`, letter codes which occur the given number of times, with `key`, the
+/// plaintext letter code of each ciphertext letter or 0 if it isn't known, and sum what `scorer`
+/// makes of each deciphered word and `dict`. With the `rayon` feature, long inputs are
+/// deciphered on all CPU cores.
+pub(crate) fn verify_key<D, S>(
+    key: &[u8; R],
+    words: &[(&[u8], usize)],
+    dict: &D,
+    scorer: impl Fn(&Deciphered, &D) -> S + Sync,
+) -> S
+where
+    D: WordSource + Sync,
+    S: std::iter::Sum + Send,
+{
+    let score = |plain: &mut Vec<u8>, (word, count): &(&[u8], usize)| {
+        plain.clear();
+        plain.extend(
+            word.iter()
+                .map(|c| key[usize::from(c - START)])
+                .take_while(|p| *p != 0),
+        );
+        let deciphered = Deciphered {
+            letters: plain,
+            whole: plain.len() == word.len(),
+            count: *count,
+        };
+        scorer(&deciphered, dict)
+    };
+
+    #[cfg(feature = "rayon")]
+    if words.len() >= PARALLEL_WORDS {
+        use rayon::prelude::*;
+        return words.par_iter().map_init(Vec::new, score).sum();
+    }
+    let mut plain = Vec::new();
+    words.iter().map(|word| score(&mut plain, word)).sum()
+}
+
+/// Distinct words of filtered `input` and the number of times each occurs, in the order they
+/// first occur
+pub(crate) fn count_words(input: &[u8]) -> Vec<(&[u8], usize)> {
+    let mut words: Vec<(&[u8], usize)> = Vec::new();
+    // Index of each word in `words`
+    let mut indices: HashMap<&[u8], usize> = HashMap::new();
+    for word in input
+        .split(u8::is_ascii_whitespace)
+        .filter(|word| !word.is_empty())
+    {
+        let idx = *indices.entry(word).or_insert_with(|| {
+            words.push((word, 0));
+            words.len() - 1
+        });
+        words[idx].1 += 1;
+    }
+    words
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Dictionary, Key};
+
+    #[test]
+    fn words_are_deciphered_up_to_unknown_letters() {
+        let dict = Dictionary::from_reader("hello\nhelp\nworld\n".as_bytes()).unwrap();
+        let key: Key = "bcdefghijklmnopqrstuvwxyza".parse().unwrap();
+        let ciphertext = crate::filter_input(&key.encipher("hello world hello help"));
+        let words = count_words(&ciphertext);
+        assert_eq!(words.len(), 3);
+        assert_eq!(words[0].1, 2);
+
+        let matched = |table: &[u8; R]| {
+            verify_key(table, &words, &dict, |word, dict| {
+                usize::from(word.whole && dict.has_word(word.letters)) * word.count
+            })
+        };
+        let mut table = key.inverse().table;
+        assert_eq!(matched(&table), 4);
+
+        // Without 'p', "help" gets as far as "hel" and isn't matched
+        table[Key::index(b'q')] = 0;
+        assert_eq!(matched(&table), 3);
+        let prefixes = verify_key(&table, &words, &dict, |word, dict| {
+            dict.prefix_score(word.letters)
+        });
+        assert_eq!(prefixes, 6 + 6 + 3);
+    }
+}
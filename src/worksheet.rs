@@ -0,0 +1,403 @@
+//! Partial keys written out for people to work on together, see [`Worksheet`]
+
+use crate::{Alphabet, Error, Key, Metadata, SolverConfig};
+use std::collections::BTreeMap;
+
+/// What has been worked out about a cipher: the plaintext letters of some ciphertext letters,
+/// words known to be in the plaintext, and a label and notes. A worksheet is saved as a small
+/// TOML file for people to edit, and worksheets which several people have worked on are merged,
+/// with their contradictions pointed out, before the solver goes on from them.
+///
+/// ```toml
+/// label = "puzzle 12"
+/// notes = ["From the Sunday paper"]
+/// # Letters of the cipher, a to z if left out
+/// alphabet = "abcdefghijklmnopqrstuvwxyz"
+/// # Words which are known to be in the plaintext
+/// cribs = ["alice"]
+///
+/// # The plaintext letter of each ciphertext letter which has been worked out
+/// [letters]
+/// q = "e"
+/// x = "t"
+/// ```
+///
+/// ```
+/// use substitution::{Dictionary, Worksheet};
+///
+/// let alice = Worksheet::from_toml("[letters]\nj = 'i'").unwrap();
+/// let bob = Worksheet::from_toml("[letters]\nf = 'e'").unwrap();
+/// let merged = alice.merge(&bob).unwrap();
+///
+/// let dict = Dictionary::from_reader("hello\nworld\n".as_bytes()).unwrap();
+/// let solution = dict.decrypt_full("ifmmp xpsme", &merged.apply(Default::default())).unwrap();
+/// assert_eq!(solution.plaintext, "hello world");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Worksheet {
+    /// Label and notes of the cipher
+    pub metadata: Metadata,
+    alphabet: Alphabet,
+    /// Plaintext letter of each ciphertext letter which has been worked out
+    letters: BTreeMap<char, char>,
+    /// Words which are known to be in the plaintext, see [`SolverConfig::crib`]
+    pub cribs: Vec<String>,
+}
+
+/// A worksheet as it's written in a file, before it's checked
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+struct SheetFile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    notes: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alphabet: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    cribs: Vec<String>,
+    #[serde(default)]
+    letters: BTreeMap<String, String>,
+}
+
+/// Two letter mappings of worksheets which contradict each other, see [`Worksheet::merge`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Conflict {
+    /// A ciphertext letter is deciphered into two plaintext letters
+    Cipher {
+        /// The ciphertext letter
+        cipher: char,
+        /// Its plaintext letter in this worksheet and in the other one
+        plain: (char, char),
+    },
+    /// Two ciphertext letters are deciphered into the same plaintext letter
+    Plain {
+        /// The ciphertext letter in this worksheet and in the other one
+        cipher: (char, char),
+        /// The plaintext letter
+        plain: char,
+    },
+    /// The worksheets are of different alphabets
+    Alphabet,
+}
+
+impl std::fmt::Display for Conflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Cipher { cipher, plain } => write!(
+                f,
+                "'{cipher}' is '{}' in one worksheet and '{}' in the other",
+                plain.0, plain.1
+            ),
+            Self::Plain { cipher, plain } => {
+                write!(f, "'{}' and '{}' are both '{plain}'", cipher.0, cipher.1)
+            }
+            Self::Alphabet => write!(f, "the worksheets are of different alphabets"),
+        }
+    }
+}
+
+/// Contradictions between worksheets which stop them from being merged, found by
+/// [`Worksheet::merge`]
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("Worksheets conflict: {}", .conflicts.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+pub struct Conflicts {
+    /// Each contradiction, in the order of the letters of this worksheet
+    pub conflicts: Vec<Conflict>,
+}
+
+impl Worksheet {
+    /// Create an empty worksheet for a cipher of the letters of `alphabet`
+    #[must_use]
+    pub fn new(alphabet: &Alphabet) -> Self {
+        Self {
+            alphabet: alphabet.clone(),
+            ..Self::default()
+        }
+    }
+
+    /// Create a worksheet of the letters which `key` maps, like the partial key of a
+    /// [`Solution`](crate::Solution), with the label and notes of the key
+    #[must_use]
+    pub fn from_key(key: &Key) -> Self {
+        let alphabet = key.alphabet();
+        let letters = alphabet
+            .codes()
+            .zip(key.table)
+            .filter_map(|(plain, cipher)| Some((alphabet.letter(cipher)?, alphabet.letter(plain)?)))
+            .collect();
+        Self {
+            metadata: key.metadata().clone(),
+            alphabet: alphabet.clone(),
+            letters,
+            cribs: Vec::new(),
+        }
+    }
+
+    /// Read a worksheet from TOML in the format of [`Worksheet`]
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidWorksheet`] if `text` isn't in the format, if a letter isn't in
+    /// the alphabet, or if two ciphertext letters have the same plaintext letter.
+    pub fn from_toml(text: &str) -> Result<Self, Error> {
+        let invalid = |problem: String| Error::InvalidWorksheet(problem);
+        let file: SheetFile = toml::from_str(text).map_err(|e| invalid(e.to_string()))?;
+        let alphabet = match &file.alphabet {
+            Some(letters) => {
+                Alphabet::new(letters).map_err(|e| invalid(format!("alphabet: {e}")))?
+            }
+            None => Alphabet::english(),
+        };
+        let letter = |text: &str| {
+            let mut chars = text.chars().flat_map(char::to_lowercase);
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if alphabet.contains(c) => Ok(c),
+                _ => Err(invalid(format!("'{text}' is not a letter of the alphabet"))),
+            }
+        };
+        let mut sheet = Self::new(&alphabet);
+        for (cipher, plain) in &file.letters {
+            let (cipher, plain) = (letter(cipher)?, letter(plain)?);
+            if let Some((other, _)) = sheet.letters.iter().find(|(_, p)| **p == plain) {
+                return Err(invalid(format!(
+                    "'{other}' and '{cipher}' are both '{plain}'"
+                )));
+            }
+            sheet.letters.insert(cipher, plain);
+        }
+        sheet.metadata = file
+            .notes
+            .iter()
+            .fold(Metadata::new(), |metadata, note| metadata.note(note));
+        if let Some(label) = &file.label {
+            sheet.metadata = sheet.metadata.label(label);
+        }
+        sheet.cribs = file.cribs;
+        Ok(sheet)
+    }
+
+    /// Write the worksheet as TOML which [`Worksheet::from_toml`] reads
+    ///
+    /// ```
+    /// use substitution::{Key, Worksheet};
+    ///
+    /// let key: Key = "b_________________________".parse().unwrap();
+    /// let toml = Worksheet::from_key(&key).to_toml();
+    /// assert!(toml.ends_with("[letters]\nb = \"a\"\n"));
+    /// assert_eq!(Worksheet::from_toml(&toml).unwrap(), Worksheet::from_key(&key));
+    /// ```
+    #[must_use]
+    pub fn to_toml(&self) -> String {
+        let file = SheetFile {
+            label: self.metadata.label.clone(),
+            notes: self.metadata.notes.clone(),
+            alphabet: (self.alphabet != Alphabet::english()).then(|| self.alphabet.to_string()),
+            cribs: self.cribs.clone(),
+            letters: self
+                .letters
+                .iter()
+                .map(|(cipher, plain)| (cipher.to_string(), plain.to_string()))
+                .collect(),
+        };
+        // Strings and lists of strings always serialize
+        toml::to_string(&file).unwrap_or_default()
+    }
+
+    /// The alphabet of the cipher
+    #[must_use]
+    pub fn alphabet(&self) -> &Alphabet {
+        &self.alphabet
+    }
+
+    /// Ciphertext letters which have been worked out and their plaintext letters, in the order
+    /// of the ciphertext letters
+    pub fn letters(&self) -> impl Iterator<Item = (char, char)> + '_ {
+        self.letters.iter().map(|(cipher, plain)| (*cipher, *plain))
+    }
+
+    /// Combine the letters, cribs, label and notes of two worksheets of the same cipher. The
+    /// label of this worksheet is kept if both have one.
+    ///
+    /// ```
+    /// use substitution::Worksheet;
+    ///
+    /// let alice = Worksheet::from_toml("[letters]\nq = 'e'").unwrap();
+    /// let bob = Worksheet::from_toml("[letters]\nq = 't'").unwrap();
+    /// let error = alice.merge(&bob).unwrap_err();
+    /// assert_eq!(
+    ///     error.to_string(),
+    ///     "Worksheets conflict: 'q' is 'e' in one worksheet and 't' in the other"
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns every [`Conflict`] between the letters of the worksheets, or
+    /// [`Conflict::Alphabet`] if they are of different alphabets.
+    pub fn merge(&self, other: &Self) -> Result<Self, Conflicts> {
+        if self.alphabet != other.alphabet {
+            return Err(Conflicts {
+                conflicts: vec![Conflict::Alphabet],
+            });
+        }
+        let mut merged = self.clone();
+        let mut conflicts = Vec::new();
+        for (cipher, plain) in other.letters() {
+            let taken = merged.letters.iter().find(|(_, p)| **p == plain);
+            match (merged.letters.get(&cipher), taken) {
+                (Some(ours), _) if *ours != plain => conflicts.push(Conflict::Cipher {
+                    cipher,
+                    plain: (*ours, plain),
+                }),
+                (None, Some((ours, _))) => conflicts.push(Conflict::Plain {
+                    cipher: (*ours, cipher),
+                    plain,
+                }),
+                _ => {
+                    merged.letters.insert(cipher, plain);
+                }
+            }
+        }
+        if !conflicts.is_empty() {
+            return Err(Conflicts { conflicts });
+        }
+        for crib in &other.cribs {
+            if !merged.cribs.contains(crib) {
+                merged.cribs.push(crib.clone());
+            }
+        }
+        if merged.metadata.label.is_none() {
+            merged.metadata.label.clone_from(&other.metadata.label);
+        }
+        for note in &other.metadata.notes {
+            if !merged.metadata.notes.contains(note) {
+                merged.metadata.notes.push(note.clone());
+            }
+        }
+        Ok(merged)
+    }
+
+    /// Configure a search to go on from the worksheet: its letters are pinned, its cribs are
+    /// placed and its label and notes are attached to the solution
+    #[must_use]
+    pub fn apply(&self, config: SolverConfig) -> SolverConfig {
+        let config = self.letters().fold(config, |config, (cipher, plain)| {
+            config.pin_letter(plain, cipher)
+        });
+        let config = self
+            .cribs
+            .iter()
+            .fold(config, |config, crib| config.crib(crib));
+        config.metadata(self.metadata.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Dictionary;
+
+    #[test]
+    fn worksheets_round_trip() {
+        let key = Key::from_letters("bc_", &Alphabet::new("abc").unwrap())
+            .unwrap()
+            .with_metadata(Metadata::new().label("puzzle 12").note("Rated hard"));
+        let mut sheet = Worksheet::from_key(&key);
+        sheet.cribs.push("cab".to_owned());
+        let toml = sheet.to_toml();
+        assert_eq!(
+            toml,
+            "label = \"puzzle 12\"\nnotes = [\"Rated hard\"]\nalphabet = \"abc\"\n\
+             cribs = [\"cab\"]\n\n[letters]\nb = \"a\"\nc = \"b\"\n"
+        );
+        assert_eq!(Worksheet::from_toml(&toml).unwrap(), sheet);
+        assert_eq!(
+            sheet.letters().collect::<Vec<_>>(),
+            [('b', 'a'), ('c', 'b')]
+        );
+    }
+
+    #[test]
+    fn invalid_worksheets_are_rejected() {
+        for (toml, problem) in [
+            ("[letters]\nq = 'é'", "'é' is not a letter of the alphabet"),
+            (
+                "[letters]\nqq = 'e'",
+                "'qq' is not a letter of the alphabet",
+            ),
+            ("[letters]\nq = 'e'\nx = 'e'", "'q' and 'x' are both 'e'"),
+        ] {
+            match Worksheet::from_toml(toml) {
+                Err(Error::InvalidWorksheet(message)) => assert_eq!(message, problem),
+                result => panic!("{toml} gave {result:?}"),
+            }
+        }
+        assert!(Worksheet::from_toml("letter = 1").is_err());
+        assert!(Worksheet::from_toml("alphabet = 'aa'").is_err());
+        // Letters are read in either case
+        let sheet = Worksheet::from_toml("[letters]\nQ = 'E'").unwrap();
+        assert_eq!(sheet.letters().collect::<Vec<_>>(), [('q', 'e')]);
+    }
+
+    #[test]
+    fn merging_finds_conflicts() {
+        let sheet = |toml: &str| Worksheet::from_toml(toml).unwrap();
+        let alice = sheet("label = 'a'\nnotes = ['x']\ncribs = ['alice']\n[letters]\nq = 'e'");
+        let bob = sheet("label = 'b'\nnotes = ['y']\ncribs = ['bob']\n[letters]\nq = 'e'\nx = 't'");
+        let merged = alice.merge(&bob).unwrap();
+        assert_eq!(
+            merged.letters().collect::<Vec<_>>(),
+            [('q', 'e'), ('x', 't')]
+        );
+        assert_eq!(merged.cribs, ["alice", "bob"]);
+        assert_eq!(
+            merged.metadata,
+            Metadata::new().label("a").note("x").note("y")
+        );
+        assert_eq!(merged.merge(&alice).unwrap(), merged);
+
+        let carol = sheet("[letters]\nq = 't'\ny = 'e'\nz = 'a'");
+        assert_eq!(
+            merged.merge(&carol).unwrap_err().conflicts,
+            [
+                Conflict::Cipher {
+                    cipher: 'q',
+                    plain: ('e', 't')
+                },
+                Conflict::Plain {
+                    cipher: ('q', 'y'),
+                    plain: 'e'
+                },
+            ]
+        );
+        let finnish = sheet("alphabet = 'abcdefghijklmnopqrstuvwxyzåäö'\n[letters]\n\"ä\" = 'a'");
+        assert_eq!(
+            alice.merge(&finnish).unwrap_err().to_string(),
+            "Worksheets conflict: the worksheets are of different alphabets"
+        );
+    }
+
+    #[test]
+    fn solving_goes_on_from_worksheets() {
+        let dict = Dictionary::from_reader("dog\ncat\n".as_bytes()).unwrap();
+        let solve = |toml: &str| {
+            let config = Worksheet::from_toml(toml)
+                .unwrap()
+                .apply(SolverConfig::new());
+            dict.decrypt_full("abc", &config).unwrap()
+        };
+        assert_eq!(solve("[letters]\na = 'c'").plaintext, "cat");
+        assert_eq!(solve("[letters]\na = 'd'").plaintext, "dog");
+        assert_eq!(solve("cribs = ['cat']").plaintext, "cat");
+        let solution = solve("label = 'puzzle 12'\n[letters]\nb = 'o'");
+        assert_eq!(solution.plaintext, "dog");
+        assert_eq!(solution.key.metadata().label.as_deref(), Some("puzzle 12"));
+        assert_eq!(
+            Worksheet::from_key(&solution.key)
+                .letters()
+                .collect::<Vec<_>>(),
+            [('a', 'd'), ('b', 'o'), ('c', 'g')]
+        );
+    }
+}